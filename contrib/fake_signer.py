@@ -0,0 +1,146 @@
+#!/usr/bin/env python3
+"""Scripted stand-in for HWI, for trying frost_byte's external signer support
+without a hardware wallet. Use "python3 contrib/fake_signer.py" as the signer
+command.
+
+It speaks the subset of the HWI command line that frost_byte and Bitcoin Core's
+-signer use: enumerate, getdescriptors, displayaddress and signtx (with
+--stdin). Its behaviour can be scripted with environment variables:
+
+  FAKE_SIGNER_FINGERPRINT  master key fingerprint to report (default 00000001)
+  FAKE_SIGNER_XPUB         account xpub/tpub used in the descriptors
+  FAKE_SIGNER_ADDRESS      address reported by displayaddress
+  FAKE_SIGNER_PSBT         file holding the base64 PSBT that signtx returns;
+                           without it the PSBT is returned unchanged
+  FAKE_SIGNER_ERROR        make every command fail with this error message
+"""
+
+import argparse
+import json
+import os
+import sys
+
+# The test tpub used by Bitcoin Core's functional tests
+DEFAULT_XPUB = (
+    "tpubD6NzVbkrYhZ4WaWSyoBvQwbpLkojyoTZPRsgXELWz3Popb3qkjcJyJUGLnL4qHHoQvao8ESa"
+    "AstxYSnhyswJ76uZPStJRJCTKvosUCJZL5B"
+)
+
+INPUT_CHARSET = (
+    "0123456789()[],'/*abcdefgh@:$%{}"
+    "IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~"
+    "ijklmnopqrstuvwxyzABCDEFGH`#\"\\ "
+)
+CHECKSUM_CHARSET = "qpzry9x8gf2tvdw0s3jn54khce6mua7l"
+GENERATOR = [0xF5DEE51989, 0xA9FDCA3312, 0x1BAB10E32D, 0x3706B1677A, 0x644D626FFD]
+
+
+def polymod(c, value):
+    top = c >> 35
+    c = ((c & 0x7FFFFFFFF) << 5) ^ value
+    for i, generator in enumerate(GENERATOR):
+        if (top >> i) & 1:
+            c ^= generator
+    return c
+
+
+def add_checksum(descriptor):
+    c = 1
+    groups = []
+    for ch in descriptor:
+        position = INPUT_CHARSET.index(ch)
+        c = polymod(c, position & 31)
+        groups.append(position >> 5)
+        if len(groups) == 3:
+            c = polymod(c, groups[0] * 9 + groups[1] * 3 + groups[2])
+            groups = []
+    if len(groups) == 1:
+        c = polymod(c, groups[0])
+    elif len(groups) == 2:
+        c = polymod(c, groups[0] * 3 + groups[1])
+    for _ in range(8):
+        c = polymod(c, 0)
+    c ^= 1
+    checksum = "".join(CHECKSUM_CHARSET[(c >> (5 * (7 - i))) & 31] for i in range(8))
+    return descriptor + "#" + checksum
+
+
+def coin_type(chain):
+    return "0" if chain == "main" else "1"
+
+
+def enumerate_devices(args):
+    return [
+        {
+            "type": "fake",
+            "model": "fake_signer",
+            "path": "fake",
+            "needs_pin_sent": False,
+            "needs_passphrase_sent": False,
+            "fingerprint": os.environ.get("FAKE_SIGNER_FINGERPRINT", "00000001"),
+        }
+    ]
+
+
+def get_descriptors(args):
+    xpub = os.environ.get("FAKE_SIGNER_XPUB", DEFAULT_XPUB)
+    origin = "{}/84h/{}h/{}h".format(args.fingerprint, coin_type(args.chain), args.account)
+
+    def descriptor(branch):
+        return add_checksum("wpkh([{}]{}/{}/*)".format(origin, xpub, branch))
+
+    return {"receive": [descriptor(0)], "internal": [descriptor(1)]}
+
+
+def display_address(args):
+    return {"address": os.environ.get("FAKE_SIGNER_ADDRESS", "")}
+
+
+def sign_tx(psbt):
+    path = os.environ.get("FAKE_SIGNER_PSBT")
+    if path:
+        with open(path) as f:
+            psbt = f.read().strip()
+    return {"psbt": psbt}
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__)
+    parser.add_argument("--fingerprint")
+    parser.add_argument("--chain", default="main")
+    parser.add_argument("--stdin", action="store_true")
+    subparsers = parser.add_subparsers(dest="command")
+    subparsers.add_parser("enumerate")
+    descriptors = subparsers.add_parser("getdescriptors")
+    descriptors.add_argument("--account", default="0")
+    display = subparsers.add_parser("displayaddress")
+    display.add_argument("--desc", required=True)
+    sign = subparsers.add_parser("signtx")
+    sign.add_argument("psbt", nargs="?")
+
+    argv = sys.argv[1:]
+    # Like HWI, --stdin reads the command and its arguments from standard input
+    if "--stdin" in argv:
+        argv = argv + sys.stdin.read().split()
+    args = parser.parse_args(argv)
+
+    error = os.environ.get("FAKE_SIGNER_ERROR")
+    if error:
+        result = {"error": error, "code": -1}
+    elif args.command == "enumerate":
+        result = enumerate_devices(args)
+    elif args.command == "getdescriptors":
+        result = get_descriptors(args)
+    elif args.command == "displayaddress":
+        result = display_address(args)
+    elif args.command == "signtx":
+        result = sign_tx(args.psbt)
+    else:
+        parser.print_usage(sys.stderr)
+        return 1
+    print(json.dumps(result))
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
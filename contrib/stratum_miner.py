@@ -0,0 +1,222 @@
+#!/usr/bin/env python3
+"""CPU stratum v1 miner, for trying frost_byte's stratum server against a
+regtest node. Start the server with
+
+  cargo run --features stratum -- stratum --address <address>
+
+and then run "python3 contrib/stratum_miner.py". Hashing in Python is slow, so
+this is only useful at regtest difficulty, where nearly every hash is a block.
+
+The miner speaks the basic protocol any pool miner does: mining.subscribe,
+mining.authorize, mining.set_difficulty, mining.notify and mining.submit. It
+exits after --shares accepted shares.
+"""
+
+import argparse
+import hashlib
+import json
+import select
+import socket
+import sys
+from fractions import Fraction
+
+# Difficulty 1 is the largest mainnet target
+DIFF1_TARGET = 0xFFFF << 208
+# Nonces tried between checks for new jobs
+BATCH = 10000
+
+
+def sha256d(data):
+    return hashlib.sha256(hashlib.sha256(data).digest()).digest()
+
+
+def swap_words(data):
+    return b"".join(data[i : i + 4][::-1] for i in range(0, len(data), 4))
+
+
+def bits_to_target(bits):
+    exponent = bits >> 24
+    mantissa = bits & 0x7FFFFF
+    if exponent <= 3:
+        return mantissa >> (8 * (3 - exponent))
+    return mantissa << (8 * (exponent - 3))
+
+
+def share_target(difficulty):
+    return int(Fraction(DIFF1_TARGET) / Fraction(difficulty))
+
+
+class Job:
+    def __init__(self, params, extranonce1, extranonce2_size):
+        (
+            self.id,
+            prevhash,
+            coinb1,
+            coinb2,
+            branch,
+            version,
+            bits,
+            ntime,
+            self.clean,
+        ) = params
+        self.prevhash = swap_words(bytes.fromhex(prevhash))
+        self.coinb1 = bytes.fromhex(coinb1)
+        self.coinb2 = bytes.fromhex(coinb2)
+        self.branch = [bytes.fromhex(node) for node in branch]
+        self.version = int(version, 16)
+        self.bits = int(bits, 16)
+        self.ntime = int(ntime, 16)
+        self.extranonce1 = extranonce1
+        self.extranonce2_size = extranonce2_size
+        self.extranonce2 = 0
+        self.nonce = 0
+
+    def header(self):
+        extranonce2 = self.extranonce2.to_bytes(self.extranonce2_size, "big")
+        root = sha256d(self.coinb1 + self.extranonce1 + extranonce2 + self.coinb2)
+        for node in self.branch:
+            root = sha256d(root + node)
+        return (
+            self.version.to_bytes(4, "little")
+            + self.prevhash
+            + root
+            + self.ntime.to_bytes(4, "little")
+            + self.bits.to_bytes(4, "little")
+        )
+
+    def next_extranonce2(self):
+        self.extranonce2 = (self.extranonce2 + 1) % (1 << (8 * self.extranonce2_size))
+        self.nonce = 0
+
+
+class Miner:
+    def __init__(self, args):
+        self.args = args
+        self.sock = socket.create_connection((args.host, args.port))
+        self.buffer = b""
+        self.next_id = 1
+        self.pending = {}
+        self.extranonce1 = None
+        self.extranonce2_size = None
+        self.difficulty = 1.0
+        self.job = None
+        self.accepted = 0
+        self.rejected = 0
+        # After finding a block, wait for the job on the new tip
+        self.waiting = False
+
+    def send(self, method, params):
+        request_id = self.next_id
+        self.next_id += 1
+        self.pending[request_id] = method
+        message = {"id": request_id, "method": method, "params": params}
+        self.sock.sendall((json.dumps(message) + "\n").encode())
+        return request_id
+
+    def read(self, timeout):
+        ready, _, _ = select.select([self.sock], [], [], timeout)
+        if not ready:
+            return
+        data = self.sock.recv(65536)
+        if not data:
+            raise ConnectionError("Server closed the connection")
+        self.buffer += data
+        while b"\n" in self.buffer:
+            line, self.buffer = self.buffer.split(b"\n", 1)
+            if line.strip():
+                self.handle(json.loads(line))
+
+    def handle(self, message):
+        method = message.get("method")
+        if method == "mining.set_difficulty":
+            self.difficulty = message["params"][0]
+        elif method == "mining.notify":
+            self.job = Job(message["params"], self.extranonce1, self.extranonce2_size)
+            self.waiting = False
+        elif method is None:
+            self.handle_response(message)
+
+    def handle_response(self, message):
+        sent = self.pending.pop(message.get("id"), None)
+        error = message.get("error")
+        if sent == "mining.subscribe":
+            if error:
+                raise RuntimeError("Subscribe failed: {}".format(error))
+            _, extranonce1, extranonce2_size = message["result"]
+            self.extranonce1 = bytes.fromhex(extranonce1)
+            self.extranonce2_size = extranonce2_size
+        elif sent == "mining.authorize":
+            if error or not message["result"]:
+                raise RuntimeError("Authorize failed: {}".format(error))
+        elif sent == "mining.submit":
+            if error:
+                self.rejected += 1
+                print("Share rejected: {}".format(error[1]))
+            else:
+                self.accepted += 1
+                print("Share accepted ({}/{})".format(self.accepted, self.args.shares))
+
+    def submit(self, job):
+        self.send(
+            "mining.submit",
+            [
+                self.args.user,
+                job.id,
+                job.extranonce2.to_bytes(job.extranonce2_size, "big").hex(),
+                "{:08x}".format(job.ntime),
+                "{:08x}".format(job.nonce),
+            ],
+        )
+
+    def mine(self, job):
+        target = share_target(self.difficulty)
+        block_target = bits_to_target(job.bits)
+        prefix = job.header()
+        for _ in range(BATCH):
+            header = prefix + job.nonce.to_bytes(4, "little")
+            value = int.from_bytes(sha256d(header), "little")
+            if value <= target:
+                self.submit(job)
+                if value <= block_target:
+                    print("Found block {}".format(sha256d(header)[::-1].hex()))
+                    self.waiting = True
+                    job.next_extranonce2()
+                    return
+            if job.nonce == 0xFFFFFFFF:
+                job.next_extranonce2()
+                return
+            job.nonce += 1
+
+    def run(self):
+        self.send("mining.subscribe", ["stratum_miner.py"])
+        self.send("mining.authorize", [self.args.user, self.args.password])
+        while self.accepted < self.args.shares:
+            busy = self.job is not None and not self.waiting
+            self.read(0 if busy else 1)
+            if self.job is not None and not self.waiting:
+                self.mine(self.job)
+        # Collect the answers to shares still in flight
+        while any(method == "mining.submit" for method in self.pending.values()):
+            self.read(5)
+        return 0 if self.rejected == 0 else 1
+
+
+def main():
+    parser = argparse.ArgumentParser(
+        description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter
+    )
+    parser.add_argument("--host", default="127.0.0.1")
+    parser.add_argument("--port", type=int, default=3333)
+    parser.add_argument("--user", default="cpu")
+    parser.add_argument("--password", default="x")
+    parser.add_argument("--shares", type=int, default=1, help="accepted shares to mine")
+    args = parser.parse_args()
+    try:
+        return Miner(args).run()
+    except (ConnectionError, RuntimeError) as e:
+        print(e, file=sys.stderr)
+        return 1
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,244 @@
+use crate::rpc::execute_rpc;
+use bitcoin::hashes::Hash;
+use bitcoin::key::TweakedPublicKey;
+use bitcoin::{
+    Address, Amount, Denomination, Network, PubkeyHash, Script, ScriptBuf, ScriptHash, WPubkeyHash,
+    WScriptHash, WitnessProgram, WitnessVersion, XOnlyPublicKey,
+};
+use bitcoin_ipc::node_capnp::node;
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::tx_destination;
+use serde_json::json;
+use std::fmt;
+
+// Mirrors OutputType in Bitcoin Core
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputType {
+    Legacy = 0,
+    P2shSegwit = 1,
+    Bech32 = 2,
+    Bech32m = 3,
+}
+
+impl OutputType {
+    pub const ALL: [OutputType; 4] = [
+        OutputType::Bech32,
+        OutputType::Bech32m,
+        OutputType::P2shSegwit,
+        OutputType::Legacy,
+    ];
+}
+
+impl fmt::Display for OutputType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OutputType::Legacy => "legacy",
+            OutputType::P2shSegwit => "p2sh-segwit",
+            OutputType::Bech32 => "bech32",
+            OutputType::Bech32m => "bech32m",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Mirrors wallet::AddressPurpose in Bitcoin Core
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressPurpose {
+    Receive = 1,
+    Send = 2,
+    Refund = 3,
+}
+
+impl AddressPurpose {
+    pub fn from_i32(purpose: i32) -> Option<Self> {
+        match purpose {
+            1 => Some(AddressPurpose::Receive),
+            2 => Some(AddressPurpose::Send),
+            3 => Some(AddressPurpose::Refund),
+            _ => None,
+        }
+    }
+}
+
+// Convert an IPC TxDestination into the output script it pays to
+pub fn read_destination(
+    destination: tx_destination::Reader,
+) -> Result<ScriptBuf, Box<dyn std::error::Error>> {
+    if destination.has_pk_hash() {
+        let hash = PubkeyHash::from_slice(destination.get_pk_hash()?)?;
+        return Ok(ScriptBuf::new_p2pkh(&hash));
+    }
+    if destination.has_script_hash() {
+        let hash = ScriptHash::from_slice(destination.get_script_hash()?)?;
+        return Ok(ScriptBuf::new_p2sh(&hash));
+    }
+    if destination.has_witness_v0_key_hash() {
+        let hash = WPubkeyHash::from_slice(destination.get_witness_v0_key_hash()?)?;
+        return Ok(ScriptBuf::new_p2wpkh(&hash));
+    }
+    if destination.has_witness_v0_script_hash() {
+        let hash = WScriptHash::from_slice(destination.get_witness_v0_script_hash()?)?;
+        return Ok(ScriptBuf::new_p2wsh(&hash));
+    }
+    if destination.has_witness_v1_taproot() {
+        let key = XOnlyPublicKey::from_slice(destination.get_witness_v1_taproot()?)?;
+        return Ok(ScriptBuf::new_p2tr_tweaked(
+            TweakedPublicKey::dangerous_assume_tweaked(key),
+        ));
+    }
+    if destination.has_witness_unknown() {
+        let unknown = destination.get_witness_unknown()?;
+        let version = WitnessVersion::try_from(unknown.get_version() as u8)?;
+        let program = WitnessProgram::new(version, unknown.get_program()?)?;
+        return Ok(ScriptBuf::new_witness_program(&program));
+    }
+    Err("Destination has no address".into())
+}
+
+// Fill an IPC TxDestination from an output script
+pub fn write_destination(
+    mut destination: tx_destination::Builder,
+    script: &Script,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = script.as_bytes();
+    if script.is_p2pkh() {
+        destination.set_pk_hash(&bytes[3..23]);
+    } else if script.is_p2sh() {
+        destination.set_script_hash(&bytes[2..22]);
+    } else if script.is_p2wpkh() {
+        destination.set_witness_v0_key_hash(&bytes[2..22]);
+    } else if script.is_p2wsh() {
+        destination.set_witness_v0_script_hash(&bytes[2..34]);
+    } else if script.is_p2tr() {
+        destination.set_witness_v1_taproot(&bytes[2..34]);
+    } else if let Some(version) = script.witness_version() {
+        let mut unknown = destination.init_witness_unknown();
+        unknown.set_version(version.to_num() as u32);
+        unknown.set_program(&bytes[2..]);
+    } else {
+        return Err("Script has no address form".into());
+    }
+    Ok(())
+}
+
+// Parse an address and check it belongs to `network`
+pub fn parse_address(address: &str, network: Network) -> Result<Address, String> {
+    address
+        .trim()
+        .parse::<Address<_>>()
+        .map_err(|e| format!("Invalid address: {}", e))?
+        .require_network(network)
+        .map_err(|_| format!("Address is not valid on {}", network))
+}
+
+// The chain the node is running on
+pub async fn get_network(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Network, Box<dyn std::error::Error>> {
+    let info = execute_rpc(node_client, thread_client, "getblockchaininfo", json!([])).await?;
+    let chain = info["chain"]
+        .as_str()
+        .ok_or("Missing chain in getblockchaininfo")?;
+    Ok(Network::from_core_arg(chain)?)
+}
+
+// Build a BIP21 payment URI
+pub fn bip21_uri(address: &Address, amount: Option<Amount>, label: &str, message: &str) -> String {
+    let mut params = Vec::new();
+    if let Some(amount) = amount {
+        // Exact decimal BTC without trailing zeros; an f64 loses satoshis
+        params.push(format!(
+            "amount={}",
+            amount.display_in(Denomination::Bitcoin)
+        ));
+    }
+    if !label.is_empty() {
+        params.push(format!("label={}", percent_encode(label)));
+    }
+    if !message.is_empty() {
+        params.push(format!("message={}", percent_encode(message)));
+    }
+
+    let mut uri = format!("bitcoin:{}", address);
+    if !params.is_empty() {
+        uri.push('?');
+        uri.push_str(&params.join("&"));
+    }
+    uri
+}
+
+pub(crate) fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn address() -> Address {
+        ADDRESS
+            .parse::<Address<_>>()
+            .unwrap()
+            .require_network(Network::Bitcoin)
+            .unwrap()
+    }
+
+    #[test]
+    fn bip21_uri_without_parameters() {
+        assert_eq!(
+            bip21_uri(&address(), None, "", ""),
+            format!("bitcoin:{}", ADDRESS)
+        );
+    }
+
+    #[test]
+    fn bip21_uri_with_parameters() {
+        assert_eq!(
+            bip21_uri(
+                &address(),
+                Some(Amount::from_sat(50_000_000)),
+                "Luke-Jr",
+                "Donation for project xyz"
+            ),
+            format!(
+                "bitcoin:{}?amount=0.5&label=Luke-Jr&message=Donation%20for%20project%20xyz",
+                ADDRESS
+            )
+        );
+        assert_eq!(
+            bip21_uri(&address(), Some(Amount::from_sat(1)), "", ""),
+            format!("bitcoin:{}?amount=0.00000001", ADDRESS)
+        );
+        assert_eq!(
+            bip21_uri(&address(), Some(Amount::from_sat(2_000_000_000)), "", "a&b"),
+            format!("bitcoin:{}?amount=20&message=a%26b", ADDRESS)
+        );
+        assert_eq!(
+            bip21_uri(
+                &address(),
+                Some(Amount::from_sat(2_099_999_999_999_999)),
+                "",
+                ""
+            ),
+            format!("bitcoin:{}?amount=20999999.99999999", ADDRESS)
+        );
+    }
+
+    #[test]
+    fn percent_encode_reserved_and_utf8() {
+        assert_eq!(percent_encode("A-z_0.9~"), "A-z_0.9~");
+        assert_eq!(percent_encode("a b=c?d#"), "a%20b%3Dc%3Fd%23");
+        assert_eq!(percent_encode("café"), "caf%C3%A9");
+    }
+}
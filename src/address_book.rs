@@ -0,0 +1,292 @@
+use crate::address::{parse_address, read_destination, write_destination, AddressPurpose};
+use crate::gui::AppEvent;
+use bitcoin::{Address, Network, Script, ScriptBuf};
+use bitcoin_ipc::handler_capnp::handler;
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::{address_book_changed_callback, wallet};
+use capnp::capability::Promise;
+use capnp_rpc::pry;
+use tokio::sync::mpsc;
+
+// An address book entry, mirroring interfaces::WalletAddress
+#[derive(Debug, Clone)]
+pub struct AddressEntry {
+    pub script_pubkey: ScriptBuf,
+    pub label: String,
+    pub is_mine: bool,
+    pub purpose: Option<AddressPurpose>,
+}
+
+impl AddressEntry {
+    // Receiving entries are our own addresses, whatever purpose they were stored with
+    pub fn is_receiving(&self) -> bool {
+        self.is_mine || self.purpose == Some(AddressPurpose::Receive)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum AddressBookEvent {
+    Updated(AddressEntry),
+    Deleted(ScriptBuf),
+}
+
+pub async fn get_addresses(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Vec<AddressEntry>, Box<dyn std::error::Error>> {
+    let mut addresses_request = wallet_client.get_addresses_request();
+    addresses_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let addresses_response = addresses_request.send().promise.await?;
+
+    let mut entries = Vec::new();
+    for address in addresses_response.get()?.get_result()? {
+        // Entries without an address form, e.g. bare scripts, can not be shown
+        let Ok(script_pubkey) = read_destination(address.get_dest()?) else {
+            continue;
+        };
+        entries.push(AddressEntry {
+            script_pubkey,
+            label: address.get_name()?.to_string()?,
+            is_mine: address.get_is_mine() != 0,
+            purpose: AddressPurpose::from_i32(address.get_purpose()),
+        });
+    }
+    entries.sort_by(|a, b| a.label.cmp(&b.label));
+    Ok(entries)
+}
+
+pub async fn delete_address(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    script: &Script,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut delete_request = wallet_client.del_address_book_request();
+    delete_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    write_destination(delete_request.get().init_dest(), script)?;
+    let delete_response = delete_request.send().promise.await?;
+    if !delete_response.get()?.get_result() {
+        return Err("Address is not in the address book".into());
+    }
+    Ok(())
+}
+
+// Quote a CSV field the way Bitcoin Core's address book export does
+fn csv_field(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+// Export entries as "Label","Address" rows, compatible with Bitcoin Core
+pub fn to_csv(entries: &[AddressEntry], network: Network) -> String {
+    let mut csv = format!("{},{}\n", csv_field("Label"), csv_field("Address"));
+    for entry in entries {
+        let Ok(address) = Address::from_script(&entry.script_pubkey, network) else {
+            continue;
+        };
+        csv.push_str(&format!(
+            "{},{}\n",
+            csv_field(&entry.label),
+            csv_field(&address.to_string())
+        ));
+    }
+    csv
+}
+
+// Parse "Label","Address" rows, as written by `to_csv` or Bitcoin Core
+pub fn parse_csv(csv: &str, network: Network) -> Result<Vec<(Address, String)>, String> {
+    let mut rows = Vec::new();
+    for (i, line) in csv.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = parse_csv_line(line);
+        let [label, address] = fields.as_slice() else {
+            return Err(format!("Line {}: expected a label and an address", i + 1));
+        };
+        if i == 0 && label == "Label" && address == "Address" {
+            continue;
+        }
+        let address =
+            parse_address(address, network).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        rows.push((address, label.clone()));
+    }
+    Ok(rows)
+}
+
+// Server side of AddressBookChangedCallback
+pub struct AddressBookChangedHandler {
+    wallet: String,
+    events: mpsc::UnboundedSender<AppEvent>,
+}
+
+impl address_book_changed_callback::Server for AddressBookChangedHandler {
+    fn destroy(
+        &mut self,
+        _: address_book_changed_callback::DestroyParams,
+        _: address_book_changed_callback::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn call(
+        &mut self,
+        params: address_book_changed_callback::CallParams,
+        _: address_book_changed_callback::CallResults,
+    ) -> Promise<(), capnp::Error> {
+        let params = pry!(params.get());
+        let script_pubkey = match read_destination(pry!(params.get_address())) {
+            Ok(script_pubkey) => script_pubkey,
+            // Nothing to show for destinations without an address
+            Err(_) => return Promise::ok(()),
+        };
+
+        // CT_DELETED
+        let event = if params.get_status() == 2 {
+            AddressBookEvent::Deleted(script_pubkey)
+        } else {
+            AddressBookEvent::Updated(AddressEntry {
+                script_pubkey,
+                label: pry!(pry!(params.get_label()).to_string()),
+                is_mine: params.get_is_mine(),
+                purpose: AddressPurpose::from_i32(params.get_purpose()),
+            })
+        };
+        let _ = self
+            .events
+            .send(AppEvent::AddressBook(self.wallet.clone(), event));
+        Promise::ok(())
+    }
+}
+
+pub async fn subscribe_address_book(
+    wallet: &str,
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    events: mpsc::UnboundedSender<AppEvent>,
+) -> Result<handler::Client, Box<dyn std::error::Error>> {
+    let mut handle_request = wallet_client.handle_address_book_changed_request();
+    handle_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    handle_request
+        .get()
+        .set_fn(capnp_rpc::new_client(AddressBookChangedHandler {
+            wallet: wallet.to_string(),
+            events,
+        }));
+    let handle_response = handle_request.send().promise.await?;
+    Ok(handle_response.get()?.get_result()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const P2PKH: &str = "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2";
+
+    fn entry(address: &str, label: &str) -> AddressEntry {
+        let address = parse_address(address, Network::Bitcoin).unwrap();
+        AddressEntry {
+            script_pubkey: address.script_pubkey(),
+            label: label.to_string(),
+            is_mine: false,
+            purpose: Some(AddressPurpose::Send),
+        }
+    }
+
+    #[test]
+    fn export_quotes_fields() {
+        let entries = [
+            entry(ADDRESS, "Alice"),
+            entry(P2PKH, "Bob, \"the builder\""),
+            // Scripts without an address are left out
+            AddressEntry {
+                script_pubkey: ScriptBuf::from_bytes(vec![0x6a, 0x03, 1, 2, 3]),
+                label: "Data".to_string(),
+                is_mine: false,
+                purpose: None,
+            },
+        ];
+        assert_eq!(
+            to_csv(&entries, Network::Bitcoin),
+            format!(
+                "\"Label\",\"Address\"\n\"Alice\",\"{}\"\n\"Bob, \"\"the builder\"\"\",\"{}\"\n",
+                ADDRESS, P2PKH
+            )
+        );
+    }
+
+    #[test]
+    fn import_round_trips_export() {
+        let entries = [
+            entry(ADDRESS, "Alice"),
+            entry(P2PKH, "Bob, \"the builder\""),
+        ];
+        let rows = parse_csv(&to_csv(&entries, Network::Bitcoin), Network::Bitcoin).unwrap();
+        let rows: Vec<(String, String)> = rows
+            .into_iter()
+            .map(|(address, label)| (address.to_string(), label))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (ADDRESS.to_string(), "Alice".to_string()),
+                (P2PKH.to_string(), "Bob, \"the builder\"".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn import_without_header_or_quotes() {
+        let csv = format!("\n{},{}\r\n\n", "Savings", ADDRESS);
+        let rows = parse_csv(&csv, Network::Bitcoin).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].0.to_string(), ADDRESS);
+        assert_eq!(rows[0].1, "Savings");
+        assert!(parse_csv("", Network::Bitcoin).unwrap().is_empty());
+    }
+
+    #[test]
+    fn import_reports_bad_lines() {
+        let error = parse_csv("\"Label\",\"Address\"\n\"Alice\"\n", Network::Bitcoin).unwrap_err();
+        assert_eq!(error, "Line 2: expected a label and an address");
+
+        let error = parse_csv("\"Alice\",\"nonsense\"\n", Network::Bitcoin).unwrap_err();
+        assert!(error.starts_with("Line 1: Invalid address"), "{}", error);
+
+        let csv = format!("\"Label\",\"Address\"\n\"Alice\",\"{}\"\n", ADDRESS);
+        let error = parse_csv(&csv, Network::Testnet).unwrap_err();
+        assert!(
+            error.starts_with("Line 2: Address is not valid on"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use crate::clients::Clients;
+use crate::gui::AppEvent;
+use crate::rpc::execute_wallet_rpc;
+use crate::wallet::restore_wallet;
+use bitcoin_ipc::node_capnp::node;
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::{wallet, wallet_loader};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+// How often the schedule checks whether a wallet is due for a backup
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+// Wait before retrying a failed backup, rather than failing every check
+const RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Automatic backups of every loaded wallet into `directory`/<wallet>/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupSchedule {
+    pub directory: PathBuf,
+    // Back up after this many new transactions; 0 disables the trigger
+    pub every_transactions: u32,
+    pub daily: bool,
+    // Number of backups kept per wallet, oldest are deleted first
+    pub keep: usize,
+}
+
+// Outcome of a backup, shown in the GUI status bar
+#[derive(Debug, Clone)]
+pub struct BackupReport {
+    pub wallet: String,
+    pub path: PathBuf,
+    pub time: DateTime<Utc>,
+    pub error: Option<String>,
+}
+
+// Progress of a wallet towards its next scheduled backup
+struct ScheduleState {
+    last_backup: SystemTime,
+    tx_count: usize,
+    failed_at: Option<SystemTime>,
+}
+
+pub async fn backup_wallet(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let filename = path.to_str().ok_or("Backup path is not valid UTF-8")?;
+    let mut backup_request = wallet_client.backup_wallet_request();
+    backup_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    backup_request.get().set_filename(filename);
+    let backup_response = backup_request.send().promise.await?;
+    if !backup_response.get()?.get_result() {
+        return Err(format!("Failed to write backup to {}", path.display()).into());
+    }
+    Ok(())
+}
+
+pub async fn get_wallet_dir(
+    wallet_loader_client: &wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let mut wallet_dir_request = wallet_loader_client.get_wallet_dir_request();
+    wallet_dir_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let wallet_dir_response = wallet_dir_request.send().promise.await?;
+    Ok(PathBuf::from(
+        wallet_dir_response.get()?.get_result()?.to_str()?,
+    ))
+}
+
+// Check a backup can be loaded by restoring it under a throwaway name. The node
+// has no read-only load, so the restored copy is unloaded and deleted afterwards.
+pub async fn verify_backup(
+    node_client: &node::Client,
+    wallet_loader_client: &wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let name = format!("frost_byte-verify-{}", Utc::now().format("%Y%m%d%H%M%S%3f"));
+    let wallet_dir = get_wallet_dir(wallet_loader_client, thread_client).await?;
+
+    // Clean up whatever fails, so failed runs don't leave wallets behind
+    let mut errors = Vec::new();
+    let mut unloaded = true;
+    match restore_wallet(wallet_loader_client, thread_client, path, &name).await {
+        Ok((restored, _)) => {
+            // The node only closes a wallet once no client holds on to it, so
+            // let go of ours. Unlike the IPC remove, unloadwallet waits for the
+            // close, after which the files are no longer in use.
+            drop(restored);
+            if let Err(e) =
+                execute_wallet_rpc(node_client, thread_client, &name, "unloadwallet", json!([]))
+                    .await
+            {
+                errors.push(format!("Failed to unload {}: {}", name, e));
+                unloaded = false;
+            }
+        }
+        Err(e) => errors.push(format!("Backup could not be restored: {}", e)),
+    }
+    // The node runs on this machine, as the IPC socket is local. A wallet that
+    // is still open is left in place rather than deleted under the node.
+    let restored_dir = wallet_dir.join(&name);
+    if unloaded && restored_dir.is_dir() {
+        if let Err(e) = fs::remove_dir_all(&restored_dir) {
+            errors.push(format!(
+                "Failed to remove {}: {}",
+                restored_dir.display(),
+                e
+            ));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; ").into())
+    }
+}
+
+// Write a backup and make sure it restores
+pub async fn backup_and_verify(
+    wallet_client: &wallet::Client,
+    node_client: &node::Client,
+    wallet_loader_client: &wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    backup_wallet(wallet_client, thread_client, path).await?;
+    verify_backup(node_client, wallet_loader_client, thread_client, path).await
+}
+
+fn wallet_backup_dir(directory: &Path, wallet: &str) -> PathBuf {
+    // The default wallet has an empty name
+    let name = if wallet.is_empty() { "default" } else { wallet };
+    directory.join(name)
+}
+
+// Existing backups of a wallet, oldest first
+fn list_backups(dir: &Path) -> Vec<(SystemTime, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bak"))
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .collect();
+    backups.sort();
+    backups
+}
+
+// Delete all but the newest `keep` backups
+fn rotate_backups(dir: &Path, keep: usize) -> std::io::Result<()> {
+    let backups = list_backups(dir);
+    let excess = backups.len().saturating_sub(keep.max(1));
+    for (_, path) in backups.into_iter().take(excess) {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+async fn scheduled_backup(
+    shared_state: &Clients,
+    schedule: &BackupSchedule,
+    wallet: &str,
+    wallet_client: &wallet::Client,
+) -> BackupReport {
+    let time = Utc::now();
+    let dir = wallet_backup_dir(&schedule.directory, wallet);
+    let path = dir.join(format!("{}.bak", time.format("%Y%m%d-%H%M%S")));
+    let result = async {
+        let node_client = shared_state.node_client.read().unwrap().clone();
+        let wallet_loader_client = shared_state.wallet_loader_client.read().unwrap().clone();
+        let thread_client = shared_state.thread_client.read().unwrap().clone();
+        let (Some(node), Some(wallet_loader), Some(thread)) =
+            (node_client, wallet_loader_client, thread_client)
+        else {
+            return Err("Clients are not initialized".into());
+        };
+        fs::create_dir_all(&dir)?;
+        backup_and_verify(wallet_client, &node, &wallet_loader, &thread, &path).await?;
+        rotate_backups(&dir, schedule.keep)?;
+        Ok::<_, Box<dyn std::error::Error>>(())
+    }
+    .await;
+    BackupReport {
+        wallet: wallet.to_string(),
+        path,
+        time,
+        error: result.err().map(|e| e.to_string()),
+    }
+}
+
+// Back up each loaded wallet whenever it is due, until the task is aborted
+pub async fn run_schedule(
+    shared_state: Arc<Clients>,
+    schedule: BackupSchedule,
+    events: mpsc::UnboundedSender<AppEvent>,
+) {
+    let mut states: HashMap<String, ScheduleState> = HashMap::new();
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let names = shared_state.wallets.read().unwrap().names();
+        for wallet in names {
+            // New transactions are counted by the wallet's store from its
+            // notifications; without a store only the daily trigger applies
+            let (wallet_client, tx_count) = {
+                let wallets = shared_state.wallets.read().unwrap();
+                let Some(entry) = wallets.get(&wallet) else {
+                    continue;
+                };
+                let tx_count = entry
+                    .store
+                    .as_ref()
+                    .map_or(0, |store| store.new_transactions());
+                (entry.client.clone(), tx_count)
+            };
+            // Pick up from the newest backup on disk, so restarts don't reset the daily timer
+            let state = states
+                .entry(wallet.clone())
+                .or_insert_with(|| ScheduleState {
+                    last_backup: list_backups(&wallet_backup_dir(&schedule.directory, &wallet))
+                        .last()
+                        .map(|(time, _)| *time)
+                        .unwrap_or(SystemTime::UNIX_EPOCH),
+                    tx_count,
+                    failed_at: None,
+                });
+            // A reloaded wallet gets a new store, counting from zero again
+            if tx_count < state.tx_count {
+                state.tx_count = tx_count;
+            }
+            let retrying = state
+                .failed_at
+                .is_some_and(|failed_at| failed_at.elapsed().unwrap_or_default() < RETRY_INTERVAL);
+            if retrying {
+                continue;
+            }
+
+            let new_transactions = tx_count.saturating_sub(state.tx_count);
+            let due_by_count = schedule.every_transactions > 0
+                && new_transactions >= schedule.every_transactions as usize;
+            let due_by_time = schedule.daily
+                && state
+                    .last_backup
+                    .elapsed()
+                    .map_or(true, |elapsed| elapsed >= DAY);
+            if !due_by_count && !due_by_time {
+                continue;
+            }
+
+            let report = scheduled_backup(&shared_state, &schedule, &wallet, &wallet_client).await;
+            match &report.error {
+                Some(e) => {
+                    eprintln!("Scheduled backup of {} failed: {}", wallet, e);
+                    state.failed_at = Some(SystemTime::now());
+                }
+                None => {
+                    println!("Backed up {} to {}", wallet, report.path.display());
+                    state.last_backup = SystemTime::now();
+                    state.tx_count = tx_count;
+                    state.failed_at = None;
+                }
+            }
+            if events.send(AppEvent::Backup(report)).is_err() {
+                return;
+            }
+        }
+    }
+}
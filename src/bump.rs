@@ -0,0 +1,150 @@
+use crate::send::{write_coin_control, CoinControl};
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::{Amount, Transaction, Txid};
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::wallet;
+
+// Which recovery actions the wallet allows for a transaction
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TxActions {
+    pub can_bump: bool,
+    pub can_abandon: bool,
+}
+
+// An unsigned replacement, shown to the user before it is signed and broadcast
+#[derive(Debug, Clone)]
+pub struct BumpProposal {
+    pub txid: Txid,
+    pub old_fee: Amount,
+    pub new_fee: Amount,
+    pub tx: Transaction,
+}
+
+fn amount(sats: i64) -> Amount {
+    Amount::from_sat(sats.max(0) as u64)
+}
+
+fn errors_to_string(
+    errors: capnp::text_list::Reader,
+    fallback: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut messages = Vec::new();
+    for error in errors {
+        messages.push(error?.to_string()?);
+    }
+    if messages.is_empty() {
+        return Ok(fallback.to_string());
+    }
+    Ok(messages.join("; "))
+}
+
+pub async fn get_tx_actions(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    txid: Txid,
+) -> Result<TxActions, Box<dyn std::error::Error>> {
+    let mut can_bump_request = wallet_client.transaction_can_be_bumped_request();
+    can_bump_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    can_bump_request.get().set_txid(&serialize(&txid));
+    let can_bump_response = can_bump_request.send().promise.await?;
+
+    let mut can_abandon_request = wallet_client.transaction_can_be_abandoned_request();
+    can_abandon_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    can_abandon_request.get().set_txid(&serialize(&txid));
+    let can_abandon_response = can_abandon_request.send().promise.await?;
+
+    Ok(TxActions {
+        can_bump: can_bump_response.get()?.get_result(),
+        can_abandon: can_abandon_response.get()?.get_result(),
+    })
+}
+
+// Mark an unconfirmed transaction as abandoned so its inputs can be spent again
+pub async fn abandon_transaction(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    txid: Txid,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut abandon_request = wallet_client.abandon_transaction_request();
+    abandon_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    abandon_request.get().set_txid(&serialize(&txid));
+    let abandon_response = abandon_request.send().promise.await?;
+    if !abandon_response.get()?.get_result() {
+        return Err("Transaction can not be abandoned".into());
+    }
+    Ok(())
+}
+
+pub async fn create_bump_transaction(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    txid: Txid,
+    coin_control: &CoinControl,
+) -> Result<BumpProposal, Box<dyn std::error::Error>> {
+    let mut bump_request = wallet_client.create_bump_transaction_request();
+    bump_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    bump_request.get().set_txid(&serialize(&txid));
+    write_coin_control(bump_request.get().init_coin_control(), coin_control);
+    let bump_response = bump_request.send().promise.await?;
+    let bump_response = bump_response.get()?;
+    if !bump_response.get_result() {
+        let errors = errors_to_string(bump_response.get_errors()?, "Failed to bump fee")?;
+        return Err(errors.into());
+    }
+    Ok(BumpProposal {
+        txid,
+        old_fee: amount(bump_response.get_old_fee()),
+        new_fee: amount(bump_response.get_new_fee()),
+        tx: deserialize(bump_response.get_mtx()?)?,
+    })
+}
+
+// Sign and broadcast a replacement, returning the new txid
+pub async fn commit_bump_transaction(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    proposal: &BumpProposal,
+) -> Result<Txid, Box<dyn std::error::Error>> {
+    let mut sign_request = wallet_client.sign_bump_transaction_request();
+    sign_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    sign_request.get().set_mtx(&serialize(&proposal.tx));
+    let sign_response = sign_request.send().promise.await?;
+    let sign_response = sign_response.get()?;
+    if !sign_response.get_result() {
+        return Err("Failed to sign the replacement transaction".into());
+    }
+    let signed = sign_response.get_mtx()?;
+
+    let mut commit_request = wallet_client.commit_bump_transaction_request();
+    commit_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    commit_request.get().set_txid(&serialize(&proposal.txid));
+    commit_request.get().set_mtx(signed);
+    let commit_response = commit_request.send().promise.await?;
+    let commit_response = commit_response.get()?;
+    if !commit_response.get_result() {
+        let errors = errors_to_string(
+            commit_response.get_errors()?,
+            "Failed to commit the replacement transaction",
+        )?;
+        return Err(errors.into());
+    }
+    Ok(deserialize(commit_response.get_bumped_txid()?)?)
+}
@@ -0,0 +1,50 @@
+use crate::fee::FeeRate;
+use bitcoin::consensus::serialize;
+use bitcoin::Transaction;
+use bitcoin_ipc::chain_capnp;
+use bitcoin_ipc::init_capnp;
+use bitcoin_ipc::proxy_capnp;
+
+// 0.1 BTC/kvB, Bitcoin Core's DEFAULT_MAX_RAW_TX_FEE_RATE
+const MAX_TX_FEE_RATE: FeeRate = FeeRate::from_sat_per_kvb(10_000_000);
+
+// Create Chain client
+pub async fn create_chain_client(
+    init_client: &init_capnp::init::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<chain_capnp::chain::Client, Box<dyn std::error::Error>> {
+    let mut make_chain_request = init_client.make_chain_request();
+    make_chain_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+
+    let chain_client_response = make_chain_request.send().promise.await?;
+    Ok(chain_client_response.get()?.get_result()?)
+}
+
+// Submit a transaction to the mempool and relay it to peers
+pub async fn broadcast_transaction(
+    chain_client: &chain_capnp::chain::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    tx: &Transaction,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut broadcast_request = chain_client.broadcast_transaction_request();
+    broadcast_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    broadcast_request.get().set_tx(&serialize(tx));
+    // Same cap as Bitcoin Core's sendrawtransaction default maxfeerate
+    let max_tx_fee = MAX_TX_FEE_RATE.fee_for_vsize(tx.vsize() as u64);
+    broadcast_request
+        .get()
+        .set_max_tx_fee(max_tx_fee.to_sat() as i64);
+    broadcast_request.get().set_relay(true);
+    let broadcast_response = broadcast_request.send().promise.await?;
+    let broadcast_response = broadcast_response.get()?;
+    if !broadcast_response.get_result() {
+        return Err(broadcast_response.get_error()?.to_string()?.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use crate::address::parse_address;
+use crate::fee::{FeeEstimates, FeeMode, FeeRate, CONF_TARGETS};
+use crate::psbt::decode_psbt;
+use crate::send::{CoinControl, FeeChoice};
+use crate::settings::Settings;
+use crate::spawner::LocalSpawner;
+#[cfg(feature = "stratum")]
+use crate::stratum::StratumConfig;
+use crate::tasks::Task;
+use anyhow::{anyhow, Result};
+use bitcoin::Txid;
+use clap::Subcommand;
+use std::io::Write;
+#[cfg(feature = "stratum")]
+use std::net::SocketAddr;
+use tokio::sync::oneshot;
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print fee estimates for a range of confirmation targets
+    Fees,
+    /// Print node warnings, exiting with an error if there are any
+    Warnings,
+    /// Replace an unconfirmed transaction with one paying a higher fee
+    BumpFee {
+        /// Wallet holding the transaction
+        #[arg(long)]
+        wallet: String,
+        txid: Txid,
+        /// New fee rate in sat/vB; the wallet's estimate is used if omitted
+        #[arg(long)]
+        fee_rate: Option<f64>,
+        /// Skip the confirmation prompt
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Abandon an unconfirmed transaction so its inputs can be respent
+    Abandon {
+        /// Wallet holding the transaction
+        #[arg(long)]
+        wallet: String,
+        txid: Txid,
+    },
+    /// Wallet utilities
+    Wallet {
+        #[command(subcommand)]
+        command: WalletCommand,
+    },
+    /// External signers speaking the HWI command line protocol
+    Signer {
+        /// Signer command, e.g. "hwi"; defaults to the one saved in settings
+        #[arg(long = "signer")]
+        signer: Option<String>,
+        #[command(subcommand)]
+        command: SignerCommand,
+    },
+    /// Mine blocks on regtest with the local CPU, paying the rewards to an address
+    Mine {
+        /// Number of blocks to mine
+        #[arg(default_value_t = 1)]
+        blocks: u32,
+        #[arg(long)]
+        address: String,
+    },
+    /// Serve stratum v1 miners, submitting the blocks they find to the node
+    #[cfg(feature = "stratum")]
+    Stratum {
+        /// Address and port to listen on
+        #[arg(long, default_value = "127.0.0.1:3333")]
+        listen: SocketAddr,
+        /// Address paid by every block found
+        #[arg(long)]
+        address: String,
+        /// Share difficulty; lowered to the network's if blocks are easier
+        #[arg(long, default_value_t = 1.0)]
+        difficulty: f64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum WalletCommand {
+    /// Sign a message with the key of a legacy address, printing the base64 signature
+    #[command(name = "signmessage")]
+    SignMessage {
+        /// Wallet holding the address's key
+        #[arg(long)]
+        wallet: String,
+        address: String,
+        message: String,
+    },
+    /// Verify a signed message, exiting with an error if the signature is invalid
+    #[command(name = "verifymessage")]
+    VerifyMessage {
+        address: String,
+        signature: String,
+        message: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SignerCommand {
+    /// List connected signers
+    Enumerate,
+    /// Sign a PSBT file with a signer, printing the result as base64
+    #[command(name = "signpsbt")]
+    SignPsbt {
+        /// Fingerprint of the signer to use, as printed by enumerate
+        #[arg(long)]
+        fingerprint: String,
+        psbt: std::path::PathBuf,
+    },
+}
+
+// Run a single command against the node without starting the GUI
+pub async fn run(command: Command, spawner: &LocalSpawner) -> Result<()> {
+    match command {
+        Command::Fees => {
+            let estimates = request(spawner, Task::GetFeeEstimates).await?;
+            print_fee_estimates(&estimates);
+        }
+        Command::Warnings => {
+            let warnings = request(spawner, Task::GetWarnings).await?;
+            for warning in &warnings {
+                println!("{}", warning);
+            }
+            if !warnings.is_empty() {
+                return Err(anyhow!("Node reported {} warning(s)", warnings.len()));
+            }
+            return Ok(());
+        }
+        Command::BumpFee {
+            wallet,
+            txid,
+            fee_rate,
+            yes,
+        } => {
+            open_wallet(spawner, &wallet).await?;
+            let fee = match fee_rate {
+                Some(rate) => FeeChoice::Rate(FeeRate::from_sat_per_vb(rate)),
+                None => FeeChoice::Target(6, FeeMode::Economical),
+            };
+            let coin_control = CoinControl {
+                fee,
+                signal_rbf: true,
+                inputs: Vec::new(),
+            };
+            let proposal = request(spawner, |send| {
+                Task::CreateBumpTransaction(wallet.clone(), txid, coin_control, send)
+            })
+            .await?;
+            println!("Old fee: {}", proposal.old_fee.display_dynamic());
+            println!("New fee: {}", proposal.new_fee.display_dynamic());
+            if !yes && !confirm("Broadcast the replacement?")? {
+                return Err(anyhow!("Aborted"));
+            }
+            let bumped = request(spawner, |send| {
+                Task::CommitBumpTransaction(wallet, proposal, send)
+            })
+            .await?;
+            println!("{}", bumped);
+        }
+        Command::Abandon { wallet, txid } => {
+            open_wallet(spawner, &wallet).await?;
+            request(spawner, |send| Task::AbandonTransaction(wallet, txid, send)).await?;
+            println!("Abandoned {}", txid);
+        }
+        Command::Wallet { command } => run_wallet(command, spawner).await?,
+        Command::Signer { signer, command } => run_signer(signer, command, spawner).await?,
+        Command::Mine { blocks, address } => {
+            let network = request(spawner, Task::GetNetwork).await?;
+            let address = parse_address(&address, network).map_err(|e| anyhow!(e))?;
+            let hashes = request(spawner, |send| Task::MineBlocks(blocks, address, send)).await?;
+            for hash in hashes {
+                println!("{}", hash);
+            }
+        }
+        #[cfg(feature = "stratum")]
+        Command::Stratum {
+            listen,
+            address,
+            difficulty,
+        } => {
+            if difficulty.is_nan() || difficulty <= 0.0 {
+                return Err(anyhow!("Difficulty must be positive"));
+            }
+            let network = request(spawner, Task::GetNetwork).await?;
+            let address = parse_address(&address, network).map_err(|e| anyhow!(e))?;
+            let config = StratumConfig {
+                listen,
+                script_pubkey: address.script_pubkey(),
+                difficulty,
+            };
+            request(spawner, |send| Task::StartStratum(config, send)).await?;
+            println!("Press Ctrl-C to stop");
+            tokio::signal::ctrl_c().await?;
+        }
+    }
+
+    // Other commands only pass warnings on, as their own result is what
+    // decides the exit status
+    if let Ok(warnings) = request(spawner, Task::GetWarnings).await {
+        for warning in warnings {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+    Ok(())
+}
+
+async fn run_wallet(command: WalletCommand, spawner: &LocalSpawner) -> Result<()> {
+    let network = request(spawner, Task::GetNetwork).await?;
+    match command {
+        WalletCommand::SignMessage {
+            wallet,
+            address,
+            message,
+        } => {
+            let address = parse_address(&address, network).map_err(|e| anyhow!(e))?;
+            open_wallet(spawner, &wallet).await?;
+            let signature = request(spawner, |send| {
+                Task::SignMessage(wallet, address, message, send)
+            })
+            .await?;
+            println!("{}", signature);
+        }
+        WalletCommand::VerifyMessage {
+            address,
+            signature,
+            message,
+        } => {
+            let address = parse_address(&address, network).map_err(|e| anyhow!(e))?;
+            let verified = request(spawner, |send| {
+                Task::VerifyMessage(address, signature, message, send)
+            })
+            .await?;
+            if !verified {
+                return Err(anyhow!("Signature is not valid"));
+            }
+            println!("Signature is valid");
+        }
+    }
+    Ok(())
+}
+
+async fn run_signer(
+    signer: Option<String>,
+    command: SignerCommand,
+    spawner: &LocalSpawner,
+) -> Result<()> {
+    let signer = signer
+        .or(Settings::load().signer_command)
+        .ok_or_else(|| anyhow!("No signer command, pass --signer"))?;
+    let signers = request(spawner, |send| Task::EnumerateSigners(signer, send)).await?;
+    match command {
+        SignerCommand::Enumerate => {
+            for signer in &signers {
+                println!("{}  {}", signer.fingerprint, signer.name);
+            }
+        }
+        SignerCommand::SignPsbt { fingerprint, psbt } => {
+            let signer = signers
+                .into_iter()
+                .find(|signer| signer.fingerprint.eq_ignore_ascii_case(&fingerprint))
+                .ok_or_else(|| {
+                    anyhow!("No signer with fingerprint {} is connected", fingerprint)
+                })?;
+            let data = std::fs::read(&psbt)
+                .map_err(|e| anyhow!("Failed to read {}: {}", psbt.display(), e))?;
+            let psbt = decode_psbt(&data).map_err(|e| anyhow!(e))?;
+            let signed =
+                request(spawner, |send| Task::SignPsbtWithSigner(signer, psbt, send)).await?;
+            println!("{}", signed);
+        }
+    }
+    Ok(())
+}
+
+// Spawn a task and wait for its response
+async fn request<T>(
+    spawner: &LocalSpawner,
+    task: impl FnOnce(oneshot::Sender<Result<T>>) -> Task,
+) -> Result<T> {
+    let (send, response) = oneshot::channel();
+    spawner.spawn(task(send));
+    response.await.map_err(|_| anyhow!("The sender dropped"))?
+}
+
+// Make sure `name` is loaded, picking it up if another client already loaded it
+async fn open_wallet(spawner: &LocalSpawner, name: &str) -> Result<()> {
+    let wallets = request(spawner, Task::ListWallets).await?;
+    if wallets.loaded.iter().any(|loaded| loaded == name) {
+        return Ok(());
+    }
+    let name = name.to_string();
+    for warning in request(spawner, |send| Task::LoadWallet(name, send)).await? {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(())
+}
+
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn print_fee_estimates(estimates: &FeeEstimates) {
+    println!("Tip: {}", estimates.tip);
+    println!("Relay min fee: {}", estimates.relay_min_fee);
+    println!("Dust relay fee: {}", estimates.dust_relay_fee);
+    println!();
+    println!(
+        "{:>7}  {:>20}  {:>20}",
+        "target", "economical", "conservative"
+    );
+    for target in CONF_TARGETS {
+        let column = |mode| match estimates.get(target, mode).and_then(|e| e.fee_rate) {
+            Some(rate) => rate.to_string(),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:>7}  {:>20}  {:>20}",
+            target,
+            column(FeeMode::Economical),
+            column(FeeMode::Conservative)
+        );
+    }
+}
@@ -1,9 +1,16 @@
+use crate::fee::FeeEstimates;
+use crate::registry::WalletRegistry;
+use bitcoin::Network;
+use bitcoin_ipc::chain_capnp::chain;
 use bitcoin_ipc::echo_capnp::echo;
+use bitcoin_ipc::handler_capnp::handler;
 use bitcoin_ipc::init_capnp::init;
+use bitcoin_ipc::mining_capnp::mining;
 use bitcoin_ipc::node_capnp::node;
 use bitcoin_ipc::proxy_capnp::thread;
 use bitcoin_ipc::wallet_capnp::wallet_loader;
 use std::sync::RwLock;
+use tokio::task::AbortHandle;
 
 pub struct Clients {
     pub init_client: RwLock<Option<init::Client>>,
@@ -11,6 +18,17 @@ pub struct Clients {
     pub echo_client: RwLock<Option<echo::Client>>,
     pub node_client: RwLock<Option<node::Client>>,
     pub wallet_loader_client: RwLock<Option<wallet_loader::Client>>,
+    pub chain_client: RwLock<Option<chain::Client>>,
+    pub mempool_handler: RwLock<Option<handler::Client>>,
+    pub fee_estimates: RwLock<Option<FeeEstimates>>,
+    pub node_handlers: RwLock<Vec<handler::Client>>,
+    pub wallets: RwLock<WalletRegistry>,
+    pub network: RwLock<Option<Network>>,
+    pub backup_schedule: RwLock<Option<AbortHandle>>,
+    // Runs rescans, so they don't hold up calls on the main thread
+    pub rescan_thread: RwLock<Option<thread::Client>>,
+    pub mining_client: RwLock<Option<mining::Client>>,
+    pub stratum_server: RwLock<Option<AbortHandle>>,
 }
 
 impl Clients {
@@ -21,6 +39,16 @@ impl Clients {
             echo_client: RwLock::new(None),
             node_client: RwLock::new(None),
             wallet_loader_client: RwLock::new(None),
+            chain_client: RwLock::new(None),
+            mempool_handler: RwLock::new(None),
+            fee_estimates: RwLock::new(None),
+            node_handlers: RwLock::new(Vec::new()),
+            wallets: RwLock::new(WalletRegistry::default()),
+            network: RwLock::new(None),
+            backup_schedule: RwLock::new(None),
+            rescan_thread: RwLock::new(None),
+            mining_client: RwLock::new(None),
+            stratum_server: RwLock::new(None),
         }
     }
 }
@@ -0,0 +1,164 @@
+use crate::address::read_destination;
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::{Amount, OutPoint, ScriptBuf, TxOut};
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::{wallet, wallet_tx_out};
+use std::collections::HashSet;
+
+// A wallet output with its status, mirroring interfaces::WalletTxOut
+#[derive(Debug, Clone)]
+pub struct Coin {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+    // Unix time in seconds
+    pub time: i64,
+    pub depth: i32,
+    pub is_spent: bool,
+    pub locked: bool,
+}
+
+// Coins paying to the same address
+#[derive(Debug, Clone)]
+pub struct CoinGroup {
+    pub script_pubkey: ScriptBuf,
+    pub coins: Vec<Coin>,
+}
+
+impl CoinGroup {
+    pub fn total(&self) -> Amount {
+        self.coins.iter().map(|coin| coin.txout.value).sum()
+    }
+}
+
+fn read_coin(
+    outpoint: OutPoint,
+    txout: wallet_tx_out::Reader,
+) -> Result<Coin, Box<dyn std::error::Error>> {
+    Ok(Coin {
+        outpoint,
+        txout: deserialize(txout.get_txout()?)?,
+        time: txout.get_time(),
+        depth: txout.get_depth_in_main_chain(),
+        is_spent: txout.get_is_spent(),
+        locked: false,
+    })
+}
+
+// Spendable coins grouped by address, including locked ones
+pub async fn list_coins(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Vec<CoinGroup>, Box<dyn std::error::Error>> {
+    let locked: HashSet<OutPoint> = list_locked_coins(wallet_client, thread_client)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut list_coins_request = wallet_client.list_coins_request();
+    list_coins_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let list_coins_response = list_coins_request.send().promise.await?;
+
+    let mut groups = Vec::new();
+    for group in list_coins_response.get()?.get_result()? {
+        let mut coins = Vec::new();
+        for entry in group.get_value()? {
+            let outpoint: OutPoint = deserialize(entry.get_key()?)?;
+            let mut coin = read_coin(outpoint, entry.get_value()?)?;
+            coin.locked = locked.contains(&outpoint);
+            coins.push(coin);
+        }
+        groups.push(CoinGroup {
+            script_pubkey: read_destination(group.get_key()?)?,
+            coins,
+        });
+    }
+    groups.sort_by_key(|group| std::cmp::Reverse(group.total()));
+    Ok(groups)
+}
+
+// Current state of specific outputs, e.g. to check a selection is still unspent
+pub async fn get_coins(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    outpoints: &[OutPoint],
+) -> Result<Vec<Coin>, Box<dyn std::error::Error>> {
+    let mut get_coins_request = wallet_client.get_coins_request();
+    get_coins_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    {
+        let mut outputs = get_coins_request.get().init_outputs(outpoints.len() as u32);
+        for (i, outpoint) in outpoints.iter().enumerate() {
+            outputs.set(i as u32, &serialize(outpoint));
+        }
+    }
+    let get_coins_response = get_coins_request.send().promise.await?;
+
+    let mut coins = Vec::new();
+    for (outpoint, txout) in outpoints
+        .iter()
+        .zip(get_coins_response.get()?.get_result()?)
+    {
+        coins.push(read_coin(*outpoint, txout)?);
+    }
+    Ok(coins)
+}
+
+// Lock or unlock a coin, returning whether the wallet now reports it locked
+pub async fn set_coin_locked(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    outpoint: OutPoint,
+    lock: bool,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let outpoint_bytes = serialize(&outpoint);
+    if lock {
+        let mut lock_request = wallet_client.lock_coin_request();
+        lock_request
+            .get()
+            .get_context()?
+            .set_thread(thread_client.clone());
+        lock_request.get().set_output(&outpoint_bytes);
+        // Persist the lock so it survives a wallet reload
+        lock_request.get().set_write_to_db(true);
+        lock_request.send().promise.await?;
+    } else {
+        let mut unlock_request = wallet_client.unlock_coin_request();
+        unlock_request
+            .get()
+            .get_context()?
+            .set_thread(thread_client.clone());
+        unlock_request.get().set_output(&outpoint_bytes);
+        unlock_request.send().promise.await?;
+    }
+
+    let mut is_locked_request = wallet_client.is_locked_coin_request();
+    is_locked_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    is_locked_request.get().set_output(&outpoint_bytes);
+    let is_locked_response = is_locked_request.send().promise.await?;
+    Ok(is_locked_response.get()?.get_result())
+}
+
+pub async fn list_locked_coins(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Vec<OutPoint>, Box<dyn std::error::Error>> {
+    let mut locked_request = wallet_client.list_locked_coins_request();
+    locked_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let locked_response = locked_request.send().promise.await?;
+    let mut outpoints = Vec::new();
+    for outpoint in locked_response.get()?.get_outputs()? {
+        outpoints.push(deserialize(outpoint?)?);
+    }
+    Ok(outpoints)
+}
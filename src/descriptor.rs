@@ -0,0 +1,346 @@
+use crate::rpc::execute_wallet_rpc;
+use bitcoin_ipc::node_capnp::node;
+use bitcoin_ipc::proxy_capnp;
+use serde_json::{json, Value};
+
+// Checksum alphabets and generator from BIP 380
+const INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u64; 5] = [
+    0xf5dee51989,
+    0xa9fdca3312,
+    0x1bab10e32d,
+    0x3706b1677a,
+    0x644d626ffd,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescriptorKind {
+    Pk,
+    Pkh,
+    Wpkh,
+    ShWpkh,
+    Sh,
+    Wsh,
+    ShWsh,
+    Tr,
+    RawTr,
+    Combo,
+    Addr,
+    Raw,
+}
+
+impl DescriptorKind {
+    fn from_function(outer: &str, inner: &str) -> Option<Self> {
+        Some(match (outer, inner) {
+            ("sh", "wpkh") => DescriptorKind::ShWpkh,
+            ("sh", "wsh") => DescriptorKind::ShWsh,
+            ("sh", _) => DescriptorKind::Sh,
+            ("pk", _) => DescriptorKind::Pk,
+            ("pkh", _) => DescriptorKind::Pkh,
+            ("wpkh", _) => DescriptorKind::Wpkh,
+            ("wsh", _) => DescriptorKind::Wsh,
+            ("tr", _) => DescriptorKind::Tr,
+            ("rawtr", _) => DescriptorKind::RawTr,
+            ("combo", _) => DescriptorKind::Combo,
+            ("addr", _) => DescriptorKind::Addr,
+            ("raw", _) => DescriptorKind::Raw,
+            _ => return None,
+        })
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            DescriptorKind::Pk => "Pay to public key",
+            DescriptorKind::Pkh => "Legacy (P2PKH)",
+            DescriptorKind::Wpkh => "Native segwit (P2WPKH)",
+            DescriptorKind::ShWpkh => "Nested segwit (P2SH-P2WPKH)",
+            DescriptorKind::Sh => "Script hash (P2SH)",
+            DescriptorKind::Wsh => "Segwit script (P2WSH)",
+            DescriptorKind::ShWsh => "Nested segwit script (P2SH-P2WSH)",
+            DescriptorKind::Tr => "Taproot (P2TR)",
+            DescriptorKind::RawTr => "Raw taproot output key",
+            DescriptorKind::Combo => "Combo (P2PK, P2PKH, P2WPKH and P2SH-P2WPKH)",
+            DescriptorKind::Addr => "Address",
+            DescriptorKind::Raw => "Raw script",
+        }
+    }
+}
+
+// A descriptor which passed local validation
+#[derive(Debug, Clone)]
+pub struct ParsedDescriptor {
+    // Always carries a checksum, which importdescriptors requires
+    pub descriptor: String,
+    pub kind: DescriptorKind,
+    // Ranged descriptors derive a key per index, e.g. .../0/*
+    pub ranged: bool,
+}
+
+// One request of an importdescriptors call
+#[derive(Debug, Clone)]
+pub struct DescriptorImport {
+    pub descriptor: String,
+    // Unix time to rescan from; None imports as of now, without a rescan
+    pub timestamp: Option<i64>,
+    // Inclusive index range for ranged descriptors
+    pub range: Option<(u32, u32)>,
+    pub active: bool,
+    pub internal: bool,
+    pub label: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportResult {
+    pub success: bool,
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+}
+
+fn polymod(c: u64, value: u64) -> u64 {
+    let top = c >> 35;
+    let mut c = ((c & 0x7ffffffff) << 5) ^ value;
+    for (i, generator) in GENERATOR.iter().enumerate() {
+        if (top >> i) & 1 == 1 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+pub fn descriptor_checksum(descriptor: &str) -> Result<String, String> {
+    let mut c = 1;
+    let mut class = 0;
+    let mut class_count = 0;
+    for ch in descriptor.chars() {
+        let position = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| format!("Invalid character {:?} in descriptor", ch))?
+            as u64;
+        c = polymod(c, position & 31);
+        // Every three characters also feed in their groups of the charset
+        class = class * 3 + (position >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..8)
+        .map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+        .collect())
+}
+
+// Name of the function a descriptor starts with, and the rest after its "("
+fn split_function(descriptor: &str) -> Option<(&str, &str)> {
+    let open = descriptor.find('(')?;
+    Some((&descriptor[..open], &descriptor[open + 1..]))
+}
+
+// Check a descriptor's checksum and structure without asking the node. A
+// missing checksum is added, a wrong one is rejected.
+pub fn parse_descriptor(input: &str) -> Result<ParsedDescriptor, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Enter a descriptor".to_string());
+    }
+    let (descriptor, checksum) = match input.split_once('#') {
+        Some((descriptor, checksum)) => (descriptor, Some(checksum)),
+        None => (input, None),
+    };
+    let expected = descriptor_checksum(descriptor)?;
+    if let Some(checksum) = checksum {
+        if checksum.len() != 8 {
+            return Err(format!("Checksum {} should be 8 characters long", checksum));
+        }
+        if checksum != expected {
+            return Err(format!(
+                "Checksum {} does not match, expected {}",
+                checksum, expected
+            ));
+        }
+    }
+
+    let mut depth = 0i32;
+    for ch in descriptor.chars() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return Err("Unbalanced parentheses in descriptor".to_string());
+        }
+    }
+    if depth != 0 || !descriptor.ends_with(')') {
+        return Err("Unbalanced parentheses in descriptor".to_string());
+    }
+
+    let (outer, rest) =
+        split_function(descriptor).ok_or("A descriptor looks like wpkh(...)".to_string())?;
+    let inner = split_function(rest).map_or("", |(inner, _)| inner);
+    let kind = DescriptorKind::from_function(outer, inner)
+        .ok_or_else(|| format!("Unknown descriptor type {}()", outer))?;
+
+    Ok(ParsedDescriptor {
+        descriptor: format!("{}#{}", descriptor, expected),
+        kind,
+        ranged: descriptor.contains('*'),
+    })
+}
+
+impl DescriptorImport {
+    fn to_json(&self) -> Value {
+        let mut request = json!({
+            "desc": self.descriptor,
+            "active": self.active,
+            "internal": self.internal,
+        });
+        request["timestamp"] = match self.timestamp {
+            Some(timestamp) => json!(timestamp),
+            None => json!("now"),
+        };
+        if let Some((start, end)) = self.range {
+            request["range"] = json!([start, end]);
+        }
+        if !self.label.is_empty() {
+            request["label"] = json!(self.label);
+        }
+        request
+    }
+}
+
+// Import descriptors through the RPC interface, as there is no IPC method for
+// it yet. Timestamps in the past make the node rescan before this returns.
+pub async fn import_descriptors(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    wallet: &str,
+    imports: &[DescriptorImport],
+) -> Result<Vec<ImportResult>, Box<dyn std::error::Error>> {
+    let requests: Vec<Value> = imports.iter().map(DescriptorImport::to_json).collect();
+    let result = execute_wallet_rpc(
+        node_client,
+        thread_client,
+        wallet,
+        "importdescriptors",
+        json!([requests]),
+    )
+    .await?;
+    let results = result
+        .as_array()
+        .ok_or("Unexpected importdescriptors result")?;
+    Ok(results
+        .iter()
+        .map(|result| ImportResult {
+            success: result["success"].as_bool().unwrap_or(false),
+            warnings: result["warnings"]
+                .as_array()
+                .map(|warnings| {
+                    warnings
+                        .iter()
+                        .filter_map(|warning| warning.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            error: result["error"]["message"].as_str().map(str::to_string),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9";
+
+    #[test]
+    fn checksum_matches_bip380_vectors() {
+        assert_eq!(descriptor_checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        assert_eq!(
+            descriptor_checksum(&format!("wpkh({})", KEY)).unwrap(),
+            "8zl0zxma"
+        );
+        assert_eq!(
+            descriptor_checksum(&format!("sh(wpkh({}))", KEY)).unwrap(),
+            "hyahcv3t"
+        );
+        assert!(descriptor_checksum("raw(Ü)").is_err());
+    }
+
+    #[test]
+    fn parse_accepts_valid_checksum() {
+        let parsed = parse_descriptor(" raw(deadbeef)#89f8spxm ").unwrap();
+        assert_eq!(parsed.descriptor, "raw(deadbeef)#89f8spxm");
+        assert_eq!(parsed.kind, DescriptorKind::Raw);
+        assert!(!parsed.ranged);
+    }
+
+    #[test]
+    fn parse_adds_missing_checksum() {
+        let parsed = parse_descriptor(&format!("sh(wpkh({}))", KEY)).unwrap();
+        assert_eq!(parsed.descriptor, format!("sh(wpkh({}))#hyahcv3t", KEY));
+        assert_eq!(parsed.kind, DescriptorKind::ShWpkh);
+
+        let parsed = parse_descriptor(
+            "wpkh([d34db33f/84h/1h/0h]tpubD6NzVbkrYhZ4WaWSyoBvQwbpLkojyoTZPRsgXELWz3Popb3qkjcJyJUGLnL4qHHoQvao8ESaAstxYSnhyswJ76uZPStJRJCTKvosUCJZL5B/0/*)",
+        )
+        .unwrap();
+        assert!(parsed.descriptor.ends_with("#86xgux3k"));
+        assert_eq!(parsed.kind, DescriptorKind::Wpkh);
+        assert!(parsed.ranged);
+    }
+
+    #[test]
+    fn parse_rejects_wrong_checksum() {
+        // An error in the checksum and one in the payload
+        for input in ["raw(deadbeef)#9f8spxma", "raw(deedbeef)#89f8spxm"] {
+            let error = parse_descriptor(input).unwrap_err();
+            assert!(error.contains("does not match"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_wrong_checksum_length() {
+        for input in [
+            "raw(deadbeef)#",
+            "raw(deadbeef)#8f8spxm",
+            "raw(deadbeef)#89f8spxmx",
+        ] {
+            let error = parse_descriptor(input).unwrap_err();
+            assert!(error.contains("8 characters long"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_unbalanced_parentheses() {
+        for input in [
+            format!("wpkh(({})", KEY),
+            format!("wpkh({}))", KEY),
+            format!("sh(wpkh({})", KEY),
+        ] {
+            assert_eq!(
+                parse_descriptor(&input).unwrap_err(),
+                "Unbalanced parentheses in descriptor"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_rejects_unknown_function() {
+        assert_eq!(
+            parse_descriptor("foo(deadbeef)").unwrap_err(),
+            "Unknown descriptor type foo()"
+        );
+        assert_eq!(parse_descriptor("  ").unwrap_err(), "Enter a descriptor");
+    }
+}
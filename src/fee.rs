@@ -0,0 +1,318 @@
+use bitcoin::hashes::Hash;
+use bitcoin::{Amount, BlockHash};
+use bitcoin_ipc::chain_capnp::chain;
+use bitcoin_ipc::node_capnp::node;
+use bitcoin_ipc::proxy_capnp;
+use std::fmt;
+
+// Confirmation targets (in blocks) queried for each estimate mode
+pub const CONF_TARGETS: [u32; 7] = [1, 2, 6, 12, 24, 144, 1008];
+
+// A fee rate as used by Bitcoin Core's CFeeRate, in satoshis per 1000 virtual bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct FeeRate {
+    sat_per_kvb: u64,
+}
+
+impl FeeRate {
+    pub const ZERO: FeeRate = FeeRate { sat_per_kvb: 0 };
+
+    pub const fn from_sat_per_kvb(sat_per_kvb: u64) -> Self {
+        Self { sat_per_kvb }
+    }
+
+    pub fn from_sat_per_vb(sat_per_vb: f64) -> Self {
+        Self {
+            sat_per_kvb: (sat_per_vb * 1000.0).round().max(0.0) as u64,
+        }
+    }
+
+    // Decode a serialized CFeeRate, which is a single little-endian int64
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let bytes: [u8; 8] = bytes
+            .try_into()
+            .map_err(|_| format!("Invalid CFeeRate length: {}", bytes.len()))?;
+        Ok(Self {
+            sat_per_kvb: i64::from_le_bytes(bytes).max(0) as u64,
+        })
+    }
+
+    pub fn to_bytes(self) -> [u8; 8] {
+        (self.sat_per_kvb as i64).to_le_bytes()
+    }
+
+    pub fn sat_per_kvb(self) -> u64 {
+        self.sat_per_kvb
+    }
+
+    pub fn sat_per_vb(self) -> f64 {
+        self.sat_per_kvb as f64 / 1000.0
+    }
+
+    pub fn fee_for_vsize(self, vsize: u64) -> Amount {
+        Amount::from_sat(self.sat_per_kvb * vsize / 1000)
+    }
+}
+
+impl fmt::Display for FeeRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3} sat/vB", self.sat_per_vb())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeMode {
+    Economical,
+    Conservative,
+}
+
+impl fmt::Display for FeeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeMode::Economical => write!(f, "economical"),
+            FeeMode::Conservative => write!(f, "conservative"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FeeEstimate {
+    pub target: u32,
+    pub mode: FeeMode,
+    // None when the node does not have enough data for this target
+    pub fee_rate: Option<FeeRate>,
+    pub returned_target: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeeEstimates {
+    pub tip: BlockHash,
+    pub estimates: Vec<FeeEstimate>,
+    pub dust_relay_fee: FeeRate,
+    pub relay_min_fee: FeeRate,
+}
+
+impl FeeEstimates {
+    pub fn get(&self, target: u32, mode: FeeMode) -> Option<&FeeEstimate> {
+        self.estimates
+            .iter()
+            .find(|e| e.target == target && e.mode == mode)
+    }
+
+    // Estimate for the largest queried target up to `target` that has one, so
+    // targets between the queried ones confirm at least as fast as asked
+    pub fn for_target(&self, target: u32, mode: FeeMode) -> Option<FeeRate> {
+        self.estimates
+            .iter()
+            .filter(|e| e.mode == mode && e.target <= target)
+            .filter_map(|e| Some((e.target, e.fee_rate?)))
+            .max_by_key(|(target, _)| *target)
+            .map(|(_, rate)| rate.max(self.relay_min_fee))
+    }
+}
+
+pub async fn get_best_block_hash(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<BlockHash, Box<dyn std::error::Error>> {
+    let mut best_block_request = node_client.get_best_block_hash_request();
+    best_block_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let best_block_response = best_block_request.send().promise.await?;
+    let hash: [u8; 32] = best_block_response.get()?.get_result()?.try_into()?;
+    Ok(BlockHash::from_byte_array(hash))
+}
+
+pub async fn estimate_smart_fee(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    target: u32,
+    mode: FeeMode,
+) -> Result<FeeEstimate, Box<dyn std::error::Error>> {
+    let mut estimate_request = node_client.estimate_smart_fee_request();
+    estimate_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    estimate_request.get().set_num_blocks(target as i32);
+    estimate_request
+        .get()
+        .set_conservative(mode == FeeMode::Conservative);
+    estimate_request.get().set_want_returned_target(true);
+    let estimate_response = estimate_request.send().promise.await?;
+    let estimate = estimate_response.get()?;
+
+    // An estimate of zero means no estimate is available
+    let fee_rate = FeeRate::from_bytes(estimate.get_result()?)?;
+    Ok(FeeEstimate {
+        target,
+        mode,
+        fee_rate: (fee_rate != FeeRate::ZERO).then_some(fee_rate),
+        returned_target: estimate.get_returned_target().max(0) as u32,
+    })
+}
+
+pub async fn get_dust_relay_fee(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<FeeRate, Box<dyn std::error::Error>> {
+    let mut dust_relay_fee_request = node_client.get_dust_relay_fee_request();
+    dust_relay_fee_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let dust_relay_fee_response = dust_relay_fee_request.send().promise.await?;
+    FeeRate::from_bytes(dust_relay_fee_response.get()?.get_result()?)
+}
+
+pub async fn get_relay_min_fee(
+    chain_client: &chain::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<FeeRate, Box<dyn std::error::Error>> {
+    let mut relay_min_fee_request = chain_client.relay_min_fee_request();
+    relay_min_fee_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let relay_min_fee_response = relay_min_fee_request.send().promise.await?;
+    FeeRate::from_bytes(relay_min_fee_response.get()?.get_result()?)
+}
+
+// Query estimates for every target in both modes, reusing `cached` if the tip has not moved
+pub async fn get_fee_estimates(
+    node_client: &node::Client,
+    chain_client: &chain::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    cached: Option<FeeEstimates>,
+) -> Result<FeeEstimates, Box<dyn std::error::Error>> {
+    let tip = get_best_block_hash(node_client, thread_client).await?;
+    if let Some(cached) = cached.filter(|c| c.tip == tip) {
+        return Ok(cached);
+    }
+
+    let mut estimates = Vec::with_capacity(CONF_TARGETS.len() * 2);
+    for mode in [FeeMode::Economical, FeeMode::Conservative] {
+        for target in CONF_TARGETS {
+            estimates.push(estimate_smart_fee(node_client, thread_client, target, mode).await?);
+        }
+    }
+
+    Ok(FeeEstimates {
+        tip,
+        estimates,
+        dust_relay_fee: get_dust_relay_fee(node_client, thread_client).await?,
+        relay_min_fee: get_relay_min_fee(chain_client, thread_client).await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimate(target: u32, mode: FeeMode, sat_per_kvb: Option<u64>) -> FeeEstimate {
+        FeeEstimate {
+            target,
+            mode,
+            fee_rate: sat_per_kvb.map(FeeRate::from_sat_per_kvb),
+            returned_target: target,
+        }
+    }
+
+    #[test]
+    fn fee_rate_round_trips_cfeerate() {
+        let rate = FeeRate::from_sat_per_kvb(12_345);
+        assert_eq!(rate.to_bytes(), 12_345i64.to_le_bytes());
+        assert_eq!(FeeRate::from_bytes(&rate.to_bytes()).unwrap(), rate);
+        assert_eq!(
+            FeeRate::from_bytes(&[0xe8, 0x03, 0, 0, 0, 0, 0, 0]).unwrap(),
+            FeeRate::from_sat_per_kvb(1000)
+        );
+    }
+
+    #[test]
+    fn fee_rate_clamps_negative_to_zero() {
+        let bytes = (-1000i64).to_le_bytes();
+        assert_eq!(FeeRate::from_bytes(&bytes).unwrap(), FeeRate::ZERO);
+    }
+
+    #[test]
+    fn fee_rate_rejects_wrong_length() {
+        for bytes in [&[][..], &[0; 4][..], &[0; 9][..]] {
+            let error = FeeRate::from_bytes(bytes).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("Invalid CFeeRate length: {}", bytes.len())
+            );
+        }
+    }
+
+    #[test]
+    fn fee_rate_units() {
+        let rate = FeeRate::from_sat_per_vb(2.5);
+        assert_eq!(rate.sat_per_kvb(), 2500);
+        assert_eq!(rate.sat_per_vb(), 2.5);
+        assert_eq!(rate.fee_for_vsize(141), Amount::from_sat(352));
+        assert_eq!(rate.to_string(), "2.500 sat/vB");
+        assert_eq!(FeeRate::from_sat_per_vb(-1.0), FeeRate::ZERO);
+    }
+
+    #[test]
+    fn conf_targets_are_ascending_and_estimable() {
+        assert!(CONF_TARGETS.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(CONF_TARGETS[0], 1);
+        // The node's fee estimator tracks targets up to 1008 blocks
+        assert_eq!(CONF_TARGETS[CONF_TARGETS.len() - 1], 1008);
+    }
+
+    #[test]
+    fn for_target_uses_largest_estimated_target_within_target() {
+        let estimates = FeeEstimates {
+            tip: BlockHash::all_zeros(),
+            estimates: vec![
+                estimate(1, FeeMode::Economical, Some(20_000)),
+                estimate(2, FeeMode::Economical, None),
+                estimate(6, FeeMode::Economical, Some(5_000)),
+                estimate(12, FeeMode::Economical, Some(500)),
+                estimate(6, FeeMode::Conservative, Some(8_000)),
+                // Estimates need not fall with the target
+                estimate(12, FeeMode::Conservative, Some(9_000)),
+            ],
+            dust_relay_fee: FeeRate::from_sat_per_kvb(3000),
+            relay_min_fee: FeeRate::from_sat_per_kvb(1000),
+        };
+        assert_eq!(
+            estimates.for_target(1, FeeMode::Economical),
+            Some(FeeRate::from_sat_per_kvb(20_000))
+        );
+        assert_eq!(
+            estimates.for_target(10, FeeMode::Economical),
+            Some(FeeRate::from_sat_per_kvb(5_000))
+        );
+        // Estimates below the relay minimum are raised to it
+        assert_eq!(
+            estimates.for_target(144, FeeMode::Economical),
+            Some(FeeRate::from_sat_per_kvb(1000))
+        );
+        assert_eq!(
+            estimates.for_target(6, FeeMode::Conservative),
+            Some(FeeRate::from_sat_per_kvb(8_000))
+        );
+        assert_eq!(
+            estimates.for_target(20, FeeMode::Conservative),
+            Some(FeeRate::from_sat_per_kvb(9_000))
+        );
+        assert_eq!(estimates.for_target(2, FeeMode::Conservative), None);
+        // Skips targets without an estimate, down to the next one
+        assert_eq!(
+            estimates.for_target(2, FeeMode::Economical),
+            Some(FeeRate::from_sat_per_kvb(20_000))
+        );
+        assert!(estimates
+            .get(2, FeeMode::Economical)
+            .unwrap()
+            .fee_rate
+            .is_none());
+    }
+}
@@ -1,15 +1,155 @@
+mod address_book;
+mod coins;
+mod descriptors;
+mod fees;
+mod history;
+mod mempool;
+mod message;
+mod migrate;
+mod mining;
+mod psbt;
+mod receive;
+mod rescan;
+mod send;
+mod settings;
+mod signer;
+mod state;
+mod status;
+mod template;
+mod wallet;
+mod warnings;
+
+use crate::address_book::AddressBookEvent;
+use crate::backup::BackupReport;
+use crate::fee::FeeEstimates;
+use crate::mempool::{MempoolEvent, MempoolInfo};
+use crate::node::{NodeStatus, Progress};
 use crate::spawner::LocalSpawner;
+use crate::store::StateChange;
+use crate::tasks::Task;
+use address_book::AddressBookPanel;
+use coins::CoinsPanel;
+use descriptors::DescriptorPanel;
 use eframe::egui;
-use tokio::sync::mpsc;
+use fees::FeeSelector;
+use history::HistoryPanel;
+use mempool::MempoolPanel;
+use mining::MiningPanel;
+use psbt::PsbtPanel;
+use receive::ReceivePanel;
+use rescan::RescanPanel;
+use send::SendPanel;
+use settings::SettingsPanel;
+use signer::SignerPanel;
+use state::WalletStates;
+use status::StatusPanel;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use wallet::WalletPanel;
+use warnings::WarningBanner;
 
 #[derive(Debug, Clone)]
 pub enum WalletMessage {
-    CreateNewWallet,
+    CreateNewWallet(String),
+}
+
+// Updates pushed from the node connection to the GUI
+#[derive(Debug, Clone)]
+pub enum AppEvent {
+    MempoolInfo(MempoolInfo),
+    Mempool(MempoolEvent),
+    FeeEstimates(FeeEstimates),
+    NodeStatus(NodeStatus),
+    InitMessage(String),
+    Progress(Progress),
+    Warnings(Vec<String>),
+    // A wallet created from the GUI, with the node's warnings about it
+    WalletLoaded(String, Vec<String>),
+    AddressBook(String, AddressBookEvent),
+    Backup(BackupReport),
+    WalletProgress(String, Progress),
+    WalletState(String, StateChange),
+}
+
+// A task response the GUI is waiting on, checked once per frame
+pub(crate) struct Pending<T> {
+    receiver: Option<oneshot::Receiver<anyhow::Result<T>>>,
+}
+
+impl<T> Default for Pending<T> {
+    fn default() -> Self {
+        Self { receiver: None }
+    }
+}
+
+impl<T> Pending<T> {
+    pub(crate) fn spawn(
+        spawner: &LocalSpawner,
+        task: impl FnOnce(oneshot::Sender<anyhow::Result<T>>) -> Task,
+    ) -> Self {
+        let (send, receiver) = oneshot::channel();
+        spawner.spawn(task(send));
+        Self {
+            receiver: Some(receiver),
+        }
+    }
+
+    pub(crate) fn is_waiting(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    pub(crate) fn poll(&mut self) -> Option<anyhow::Result<T>> {
+        let receiver = self.receiver.as_mut()?;
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(oneshot::error::TryRecvError::Empty) => return None,
+            Err(oneshot::error::TryRecvError::Closed) => Err(anyhow::anyhow!("The sender dropped")),
+        };
+        self.receiver = None;
+        Some(result)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    Wallet,
+    Receive,
+    Send,
+    Transactions,
+    Coins,
+    AddressBook,
+    Descriptors,
+    Signers,
+    Psbt,
+    Mempool,
+    Mining,
+    Settings,
 }
 
 pub struct App {
     spawner: LocalSpawner,
     tx: mpsc::UnboundedSender<WalletMessage>,
+    // Handed to tasks which subscribe to notifications on behalf of a page
+    notify: mpsc::UnboundedSender<AppEvent>,
+    events: mpsc::UnboundedReceiver<AppEvent>,
+    view: View,
+    states: WalletStates,
+    mempool: MempoolPanel,
+    mining: MiningPanel,
+    fees: FeeSelector,
+    status: StatusPanel,
+    warnings: WarningBanner,
+    settings: SettingsPanel,
+    wallet: WalletPanel,
+    receive: ReceivePanel,
+    send: SendPanel,
+    history: HistoryPanel,
+    coins: CoinsPanel,
+    psbt: PsbtPanel,
+    address_book: AddressBookPanel,
+    rescan: RescanPanel,
+    descriptors: DescriptorPanel,
+    signer: SignerPanel,
 }
 
 impl App {
@@ -17,17 +157,187 @@ impl App {
         _cc: &eframe::CreationContext<'_>,
         spawner: LocalSpawner,
         tx: mpsc::UnboundedSender<WalletMessage>,
+        notify: mpsc::UnboundedSender<AppEvent>,
+        events: mpsc::UnboundedReceiver<AppEvent>,
     ) -> Self {
-        Self { spawner, tx }
+        Self {
+            spawner,
+            tx,
+            notify,
+            events,
+            view: View::Wallet,
+            states: WalletStates::default(),
+            mempool: MempoolPanel::default(),
+            mining: MiningPanel::default(),
+            fees: FeeSelector::default(),
+            status: StatusPanel::default(),
+            warnings: WarningBanner::default(),
+            settings: SettingsPanel::default(),
+            wallet: WalletPanel::default(),
+            receive: ReceivePanel::default(),
+            send: SendPanel::default(),
+            history: HistoryPanel::default(),
+            coins: CoinsPanel::default(),
+            psbt: PsbtPanel::default(),
+            address_book: AddressBookPanel::default(),
+            rescan: RescanPanel::default(),
+            descriptors: DescriptorPanel::default(),
+            signer: SignerPanel::default(),
+        }
+    }
+
+    fn handle_events(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                AppEvent::MempoolInfo(info) => self.mempool.set_info(info),
+                AppEvent::Mempool(event) => self.mempool.push(event),
+                AppEvent::FeeEstimates(estimates) => self.fees.set_estimates(estimates),
+                AppEvent::NodeStatus(status) => self.status.set_status(status),
+                AppEvent::InitMessage(message) => self.status.set_init_message(message),
+                AppEvent::Progress(progress) => self.status.set_progress(progress),
+                AppEvent::Warnings(warnings) => self.warnings.set_warnings(warnings),
+                AppEvent::WalletLoaded(name, warnings) => self.wallet.opened(name, warnings),
+                AppEvent::AddressBook(wallet, event) => self.address_book.apply(&wallet, event),
+                AppEvent::Backup(report) => self.status.set_backup(report),
+                AppEvent::WalletProgress(wallet, progress) => {
+                    self.rescan.set_progress(&wallet, progress)
+                }
+                AppEvent::WalletState(wallet, change) => {
+                    match &change {
+                        StateChange::Transaction(_) => self.history.transaction_changed(&wallet),
+                        // Unloaded elsewhere, so the wallet list is out of date
+                        StateChange::Unloaded => self.wallet.refresh(),
+                        _ => {}
+                    }
+                    self.states.apply(&wallet, change);
+                }
+            }
+        }
     }
 }
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            if ui.button("Create new wallet").clicked() {
-                let _ = self.tx.send(WalletMessage::CreateNewWallet);
+        self.handle_events();
+        self.states
+            .subscribe(&self.spawner, self.wallet.loaded(), &self.notify);
+
+        egui::TopBottomPanel::top("views").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.view, View::Wallet, "Wallet");
+                ui.selectable_value(&mut self.view, View::Receive, "Receive");
+                ui.selectable_value(&mut self.view, View::Send, "Send");
+                ui.selectable_value(&mut self.view, View::Transactions, "Transactions");
+                ui.selectable_value(&mut self.view, View::Coins, "Coins");
+                ui.selectable_value(&mut self.view, View::AddressBook, "Address book");
+                ui.selectable_value(&mut self.view, View::Descriptors, "Descriptors");
+                ui.selectable_value(&mut self.view, View::Signers, "Signers");
+                ui.selectable_value(&mut self.view, View::Psbt, "PSBT");
+                ui.selectable_value(&mut self.view, View::Mempool, "Mempool");
+                ui.selectable_value(&mut self.view, View::Mining, "Mining");
+                ui.selectable_value(&mut self.view, View::Settings, "Settings");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    self.wallet.show_switcher(ui);
+                });
+            });
+        });
+
+        self.warnings.show(ctx);
+
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            self.status.show_status_bar(ui);
+        });
+
+        let ready = self.status.is_ready();
+        egui::CentralPanel::default().show(ctx, |ui| match self.view {
+            View::Wallet => {
+                if !ready {
+                    self.status.show_splash(ui);
+                }
+                self.wallet
+                    .show(ui, &self.spawner, &self.tx, ready, &self.states);
+                if ready {
+                    let wallet = self.wallet.active();
+                    let tip = self.status.tip_height();
+                    self.rescan
+                        .show(ui, &self.spawner, wallet, &self.notify, tip);
+                }
+            }
+            // The other wallet pages wait for the node as a whole
+            View::Receive
+            | View::Send
+            | View::Transactions
+            | View::Coins
+            | View::AddressBook
+            | View::Descriptors
+            | View::Signers
+            | View::Psbt
+                if !ready =>
+            {
+                self.status.show_splash(ui)
+            }
+            View::Receive => {
+                let wallet = self.wallet.active();
+                let state = wallet.and_then(|wallet| self.states.get(wallet));
+                self.receive.show(ui, &self.spawner, wallet, state);
+            }
+            View::Send => {
+                let wallet = self.wallet.active();
+                self.send
+                    .show(ui, &self.spawner, wallet, &mut self.fees, &mut self.coins);
+                if let Some(psbt) = self.send.take_psbt() {
+                    self.psbt.open(psbt);
+                    self.view = View::Psbt;
+                }
+            }
+            View::Transactions => {
+                let wallet = self.wallet.active();
+                let state = wallet.and_then(|wallet| self.states.get(wallet));
+                let tip = self.status.tip_height();
+                self.history.show(ui, &self.spawner, wallet, state, tip);
+            }
+            View::Coins => {
+                let wallet = self.wallet.active();
+                self.coins.show(ui, &self.spawner, wallet);
+            }
+            View::AddressBook => {
+                let wallet = self.wallet.active();
+                self.address_book
+                    .show(ui, &self.spawner, wallet, &self.notify);
+                if let Some(address) = self.address_book.take_pay_to() {
+                    self.send.pay_to(address);
+                    self.view = View::Send;
+                }
+            }
+            View::Descriptors => {
+                let wallet = self.wallet.active();
+                self.descriptors.show(ui, &self.spawner, wallet);
+            }
+            View::Signers => {
+                let wallet = self.wallet.active();
+                self.signer.show(ui, &self.spawner, wallet);
+                if let Some(name) = self.signer.take_created() {
+                    self.wallet.select(name);
+                }
+            }
+            View::Psbt => {
+                let wallet = self.wallet.active();
+                let signer = self.signer.selected();
+                self.psbt.show(ui, &self.spawner, wallet, signer);
+            }
+            View::Mempool => self.mempool.show(ui),
+            View::Mining => {
+                let tip = self.status.tip_height();
+                self.mining.show(ui, &self.spawner, tip);
+            }
+            View::Settings => {
+                let wallet = self.wallet.active();
+                self.settings
+                    .show(ui, &self.spawner, wallet, ready, &self.notify);
             }
         });
+
+        // Events arrive from outside the egui loop, so keep polling for them
+        ctx.request_repaint_after(Duration::from_millis(250));
     }
 }
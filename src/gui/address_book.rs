@@ -0,0 +1,437 @@
+use super::message::{SignDialog, VerifyDialog};
+use super::{AppEvent, Pending};
+use crate::address::{parse_address, AddressPurpose};
+use crate::address_book::{parse_csv, to_csv, AddressBookEvent, AddressEntry};
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use bitcoin::{Address, Network, ScriptBuf};
+use eframe::egui;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Tab {
+    #[default]
+    Sending,
+    Receiving,
+}
+
+struct EditDialog {
+    script_pubkey: ScriptBuf,
+    address: String,
+    label: String,
+    purpose: AddressPurpose,
+}
+
+// Labelled addresses, kept in sync through the wallet's address book notifications
+#[derive(Default)]
+pub struct AddressBookPanel {
+    wallet: Option<String>,
+    network: Option<Network>,
+    network_pending: Pending<Network>,
+    entries: Option<Vec<AddressEntry>>,
+    entries_pending: Pending<Vec<AddressEntry>>,
+    subscribe: Pending<()>,
+    tab: Tab,
+    search: String,
+    new_address: String,
+    new_label: String,
+    edit: Option<EditDialog>,
+    action: Pending<()>,
+    import: Pending<usize>,
+    // Address picked with "Pay to", handed over to the send page
+    pay_to: Option<String>,
+    sign: Option<SignDialog>,
+    verify: Option<VerifyDialog>,
+    message: Option<String>,
+    error: Option<String>,
+}
+
+impl AddressBookPanel {
+    // Apply a change pushed by the wallet's address book notifications
+    pub fn apply(&mut self, wallet: &str, event: AddressBookEvent) {
+        if self.wallet.as_deref() != Some(wallet) {
+            return;
+        }
+        let Some(entries) = &mut self.entries else {
+            return;
+        };
+        match event {
+            AddressBookEvent::Updated(entry) => {
+                match entries
+                    .iter_mut()
+                    .find(|e| e.script_pubkey == entry.script_pubkey)
+                {
+                    Some(existing) => *existing = entry,
+                    None => entries.push(entry),
+                }
+                entries.sort_by(|a, b| a.label.cmp(&b.label));
+            }
+            AddressBookEvent::Deleted(script_pubkey) => {
+                entries.retain(|e| e.script_pubkey != script_pubkey)
+            }
+        }
+    }
+
+    pub fn take_pay_to(&mut self) -> Option<String> {
+        self.pay_to.take()
+    }
+
+    fn poll(
+        &mut self,
+        spawner: &LocalSpawner,
+        wallet: &str,
+        events: &mpsc::UnboundedSender<AppEvent>,
+    ) {
+        if self.wallet.as_deref() != Some(wallet) {
+            self.wallet = Some(wallet.to_string());
+            self.entries = None;
+            self.edit = None;
+            self.sign = None;
+            self.message = None;
+            self.error = None;
+            // Subscribe before the initial fetch so no change is missed
+            let wallet = wallet.to_string();
+            let events = events.clone();
+            self.subscribe = Pending::spawn(spawner, |send| {
+                Task::SubscribeAddressBook(wallet, events, send)
+            });
+        }
+
+        match self.network_pending.poll() {
+            Some(Ok(network)) => self.network = Some(network),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.subscribe.poll() {
+            Some(Ok(())) => {
+                let wallet = wallet.to_string();
+                self.entries_pending =
+                    Pending::spawn(spawner, |send| Task::ListAddresses(wallet, send));
+            }
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.entries = Some(Vec::new());
+            }
+            None => {}
+        }
+
+        match self.entries_pending.poll() {
+            Some(Ok(entries)) => self.entries = Some(entries),
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.entries = Some(Vec::new());
+            }
+            None => {}
+        }
+
+        match self.action.poll() {
+            Some(Ok(())) => self.edit = None,
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.import.poll() {
+            Some(Ok(count)) => self.message = Some(format!("Imported {} addresses", count)),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        if self.network.is_none() && !self.network_pending.is_waiting() && self.error.is_none() {
+            self.network_pending = Pending::spawn(spawner, Task::GetNetwork);
+        }
+    }
+
+    fn busy(&self) -> bool {
+        self.action.is_waiting() || self.import.is_waiting()
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        wallet: Option<&str>,
+        events: &mpsc::UnboundedSender<AppEvent>,
+    ) {
+        let Some(wallet) = wallet else {
+            ui.label("Open a wallet to see its address book");
+            return;
+        };
+        self.poll(spawner, wallet, events);
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.tab, Tab::Sending, "Sending addresses");
+            ui.selectable_value(&mut self.tab, Tab::Receiving, "Receiving addresses");
+            ui.separator();
+            if ui.button("Sign message...").clicked() {
+                self.sign = Some(SignDialog::default());
+            }
+            if ui.button("Verify message...").clicked() {
+                self.verify = Some(VerifyDialog::default());
+            }
+        });
+        ui.separator();
+
+        ui.add_enabled_ui(!self.busy(), |ui| {
+            if self.tab == Tab::Sending {
+                self.show_new_address(ui, spawner, wallet);
+            }
+            self.show_file_actions(ui, spawner, wallet);
+        });
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Search");
+            ui.text_edit_singleline(&mut self.search);
+        });
+        self.show_entries(ui, spawner, wallet);
+        self.show_edit(ui.ctx(), spawner, wallet);
+
+        let network = self.network;
+        if let Some(sign) = &mut self.sign {
+            if !sign.show(ui.ctx(), spawner, wallet, network) {
+                self.sign = None;
+            }
+        }
+        if let Some(verify) = &mut self.verify {
+            if !verify.show(ui.ctx(), spawner, network) {
+                self.verify = None;
+            }
+        }
+    }
+
+    // Entries of the current tab matching the search
+    fn visible_entries(&self) -> Vec<&AddressEntry> {
+        let Some(entries) = &self.entries else {
+            return Vec::new();
+        };
+        let search = self.search.to_lowercase();
+        entries
+            .iter()
+            .filter(|entry| entry.is_receiving() == (self.tab == Tab::Receiving))
+            .filter(|entry| {
+                search.is_empty()
+                    || entry.label.to_lowercase().contains(&search)
+                    || self
+                        .address_string(&entry.script_pubkey)
+                        .to_lowercase()
+                        .contains(&search)
+            })
+            .collect()
+    }
+
+    fn address_string(&self, script_pubkey: &ScriptBuf) -> String {
+        self.network
+            .and_then(|network| Address::from_script(script_pubkey, network).ok())
+            .map(|address| address.to_string())
+            .unwrap_or_else(|| script_pubkey.to_hex_string())
+    }
+
+    fn show_new_address(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, wallet: &str) {
+        ui.horizontal(|ui| {
+            ui.label("Address");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_address)
+                    .desired_width(400.0)
+                    .font(egui::TextStyle::Monospace),
+            );
+            ui.label("Label");
+            ui.text_edit_singleline(&mut self.new_label);
+            if ui.button("Add").clicked() {
+                let Some(network) = self.network else {
+                    self.error = Some("Waiting for the node's network".to_string());
+                    return;
+                };
+                match parse_address(&self.new_address, network) {
+                    Ok(address) => {
+                        self.error = None;
+                        let wallet = wallet.to_string();
+                        let label = std::mem::take(&mut self.new_label);
+                        self.new_address.clear();
+                        self.action = Pending::spawn(spawner, |send| {
+                            Task::SetAddressLabel(
+                                wallet,
+                                address.script_pubkey(),
+                                label,
+                                AddressPurpose::Send,
+                                send,
+                            )
+                        });
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        });
+    }
+
+    fn show_file_actions(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, wallet: &str) {
+        let Some(network) = self.network else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            if ui.button("Export CSV...").clicked() {
+                let file_name = match self.tab {
+                    Tab::Sending => "sending-addresses.csv",
+                    Tab::Receiving => "receiving-addresses.csv",
+                };
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .set_file_name(file_name)
+                    .save_file()
+                {
+                    let entries: Vec<AddressEntry> =
+                        self.visible_entries().into_iter().cloned().collect();
+                    match std::fs::write(&path, to_csv(&entries, network)) {
+                        Ok(()) => {
+                            self.message = Some(format!(
+                                "Exported {} addresses to {}",
+                                entries.len(),
+                                path.display()
+                            ))
+                        }
+                        Err(e) => self.error = Some(format!("Failed to export: {}", e)),
+                    }
+                }
+            }
+            // Imported entries are someone else's addresses, so they go to the sending list
+            if self.tab == Tab::Sending && ui.button("Import CSV...").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .pick_file()
+                {
+                    let rows = std::fs::read_to_string(&path)
+                        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+                        .and_then(|csv| parse_csv(&csv, network));
+                    match rows {
+                        Ok(rows) => {
+                            self.error = None;
+                            let wallet = wallet.to_string();
+                            self.import = Pending::spawn(spawner, |send| {
+                                Task::ImportAddresses(wallet, rows, send)
+                            });
+                        }
+                        Err(e) => self.error = Some(e),
+                    }
+                }
+            }
+        });
+    }
+
+    fn show_entries(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, wallet: &str) {
+        if self.entries.is_none() {
+            ui.spinner();
+            return;
+        }
+        let entries: Vec<AddressEntry> = self.visible_entries().into_iter().cloned().collect();
+        if entries.is_empty() {
+            ui.label("No addresses");
+            return;
+        }
+
+        let sending = self.tab == Tab::Sending;
+        let mut edit = None;
+        let mut delete = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("address_book")
+                .striped(true)
+                .num_columns(3)
+                .show(ui, |ui| {
+                    ui.strong("Label");
+                    ui.strong("Address");
+                    ui.end_row();
+                    for entry in &entries {
+                        let address = self.address_string(&entry.script_pubkey);
+                        if entry.label.is_empty() {
+                            ui.weak("(no label)");
+                        } else {
+                            ui.label(&entry.label);
+                        }
+                        ui.monospace(&address);
+                        ui.horizontal(|ui| {
+                            if ui.button("Copy").clicked() {
+                                ui.output_mut(|o| o.copied_text = address.clone());
+                            }
+                            if ui.button("Edit").clicked() {
+                                edit = Some((entry.clone(), address.clone()));
+                            }
+                            if !sending && ui.button("Sign message").clicked() {
+                                self.sign = Some(SignDialog::new(address.clone()));
+                            }
+                            if sending {
+                                if ui.button("Pay to").clicked() {
+                                    self.pay_to = Some(address.clone());
+                                }
+                                if ui.button("Delete").clicked() {
+                                    delete = Some(entry.script_pubkey.clone());
+                                }
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+        });
+
+        if let Some((entry, address)) = edit {
+            let purpose = entry.purpose.unwrap_or(if sending {
+                AddressPurpose::Send
+            } else {
+                AddressPurpose::Receive
+            });
+            self.edit = Some(EditDialog {
+                script_pubkey: entry.script_pubkey,
+                address,
+                label: entry.label,
+                purpose,
+            });
+        }
+        if let Some(script_pubkey) = delete {
+            self.error = None;
+            let wallet = wallet.to_string();
+            self.action = Pending::spawn(spawner, |send| {
+                Task::DeleteAddress(wallet, script_pubkey, send)
+            });
+        }
+    }
+
+    fn show_edit(&mut self, ctx: &egui::Context, spawner: &LocalSpawner, wallet: &str) {
+        let waiting = self.action.is_waiting();
+        let Some(edit) = &mut self.edit else {
+            return;
+        };
+
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new("Edit label")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.monospace(&edit.address);
+                ui.text_edit_singleline(&mut edit.label);
+                if ui
+                    .add_enabled(!waiting, egui::Button::new("Save"))
+                    .clicked()
+                {
+                    save = true;
+                }
+            });
+
+        if save {
+            self.error = None;
+            let wallet = wallet.to_string();
+            let script_pubkey = edit.script_pubkey.clone();
+            let label = edit.label.clone();
+            let purpose = edit.purpose;
+            self.action = Pending::spawn(spawner, |send| {
+                Task::SetAddressLabel(wallet, script_pubkey, label, purpose, send)
+            });
+        } else if !open {
+            self.edit = None;
+        }
+    }
+}
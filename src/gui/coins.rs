@@ -0,0 +1,207 @@
+use super::Pending;
+use crate::coins::CoinGroup;
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use bitcoin::{Address, Amount, Network, OutPoint};
+use eframe::egui;
+use std::collections::BTreeSet;
+
+// Coin control: browse coins by address, lock them and pick inputs for the send page
+#[derive(Default)]
+pub struct CoinsPanel {
+    wallet: Option<String>,
+    network: Option<Network>,
+    network_pending: Pending<Network>,
+    groups: Option<Vec<CoinGroup>>,
+    groups_pending: Pending<Vec<CoinGroup>>,
+    lock: Pending<bool>,
+    selected: BTreeSet<OutPoint>,
+    error: Option<String>,
+}
+
+impl CoinsPanel {
+    // Manually selected inputs, only if they belong to `wallet`
+    pub fn selection(&self, wallet: &str) -> Vec<OutPoint> {
+        if self.wallet.as_deref() != Some(wallet) {
+            return Vec::new();
+        }
+        self.selected.iter().copied().collect()
+    }
+
+    pub fn selected_total(&self) -> Amount {
+        let Some(groups) = &self.groups else {
+            return Amount::ZERO;
+        };
+        groups
+            .iter()
+            .flat_map(|group| &group.coins)
+            .filter(|coin| self.selected.contains(&coin.outpoint))
+            .map(|coin| coin.txout.value)
+            .sum()
+    }
+
+    // Forget the selection and refetch, e.g. after the selected coins were spent
+    pub fn clear_selection(&mut self) {
+        self.selected.clear();
+        self.groups = None;
+    }
+
+    fn poll(&mut self, spawner: &LocalSpawner, wallet: &str) {
+        if self.wallet.as_deref() != Some(wallet) {
+            self.wallet = Some(wallet.to_string());
+            self.groups = None;
+            self.selected.clear();
+            self.error = None;
+        }
+
+        match self.network_pending.poll() {
+            Some(Ok(network)) => self.network = Some(network),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.groups_pending.poll() {
+            Some(Ok(groups)) => {
+                // Drop selected coins which were spent or locked in the meantime
+                self.selected.retain(|outpoint| {
+                    groups
+                        .iter()
+                        .flat_map(|group| &group.coins)
+                        .any(|coin| coin.outpoint == *outpoint && !coin.locked)
+                });
+                self.groups = Some(groups);
+            }
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.groups = Some(Vec::new());
+            }
+            None => {}
+        }
+
+        match self.lock.poll() {
+            Some(Ok(_)) => self.groups = None,
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.groups = None;
+            }
+            None => {}
+        }
+
+        if self.network.is_none() && !self.network_pending.is_waiting() && self.error.is_none() {
+            self.network_pending = Pending::spawn(spawner, Task::GetNetwork);
+        }
+        if self.groups.is_none() && !self.groups_pending.is_waiting() && !self.lock.is_waiting() {
+            let wallet = wallet.to_string();
+            self.groups_pending = Pending::spawn(spawner, |send| Task::ListCoins(wallet, send));
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, wallet: Option<&str>) {
+        let Some(wallet) = wallet else {
+            ui.label("Open a wallet to manage its coins");
+            return;
+        };
+        self.poll(spawner, wallet);
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Selected: {} coins, {}",
+                self.selected.len(),
+                self.selected_total().display_dynamic()
+            ));
+            if ui.button("Clear selection").clicked() {
+                self.selected.clear();
+            }
+            if ui.button("Refresh").clicked() {
+                self.error = None;
+                self.groups = None;
+            }
+        });
+        ui.label(
+            "Selected coins are spent as the only inputs of the next transaction on the Send page.",
+        );
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+
+        let Some(groups) = &self.groups else {
+            ui.spinner();
+            return;
+        };
+        if groups.is_empty() {
+            ui.label("No spendable coins");
+            return;
+        }
+
+        let mut toggle_lock = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for group in groups {
+                let address = self
+                    .network
+                    .and_then(|network| Address::from_script(&group.script_pubkey, network).ok())
+                    .map(|address| address.to_string())
+                    .unwrap_or_else(|| group.script_pubkey.to_hex_string());
+                let title = format!(
+                    "{}  {}  ({} coins)",
+                    address,
+                    group.total().display_dynamic(),
+                    group.coins.len()
+                );
+                egui::CollapsingHeader::new(title)
+                    .id_source(&group.script_pubkey)
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        egui::Grid::new(("coins", &group.script_pubkey))
+                            .striped(true)
+                            .num_columns(5)
+                            .show(ui, |ui| {
+                                for coin in &group.coins {
+                                    let mut checked = self.selected.contains(&coin.outpoint);
+                                    // Locked coins are never spent, so they can not be selected
+                                    let changed = ui
+                                        .add_enabled(
+                                            !coin.locked,
+                                            egui::Checkbox::without_text(&mut checked),
+                                        )
+                                        .changed();
+                                    if changed && checked {
+                                        self.selected.insert(coin.outpoint);
+                                    } else if changed {
+                                        self.selected.remove(&coin.outpoint);
+                                    }
+                                    ui.monospace(coin.txout.value.display_dynamic().to_string());
+                                    if coin.depth > 0 {
+                                        ui.label(format!("{} conf.", coin.depth));
+                                    } else {
+                                        ui.label("Unconfirmed");
+                                    }
+                                    ui.monospace(coin.outpoint.to_string());
+                                    let label = if coin.locked { "🔒 Unlock" } else { "Lock" };
+                                    if ui
+                                        .add_enabled(
+                                            !self.lock.is_waiting(),
+                                            egui::Button::new(label),
+                                        )
+                                        .clicked()
+                                    {
+                                        toggle_lock = Some((coin.outpoint, !coin.locked));
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            }
+        });
+
+        if let Some((outpoint, lock)) = toggle_lock {
+            self.error = None;
+            if lock {
+                self.selected.remove(&outpoint);
+            }
+            let wallet = wallet.to_string();
+            self.lock = Pending::spawn(spawner, |send| {
+                Task::SetCoinLocked(wallet, outpoint, lock, send)
+            });
+        }
+    }
+}
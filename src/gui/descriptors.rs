@@ -0,0 +1,271 @@
+use super::Pending;
+use crate::descriptor::{parse_descriptor, DescriptorImport, ImportResult};
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use chrono::{Local, NaiveDate};
+use eframe::egui;
+
+// Default number of addresses derived from a ranged descriptor, as in Bitcoin Core
+const DEFAULT_RANGE_END: u32 = 999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Birthday {
+    // No rescan, for descriptors with no history yet
+    Now,
+    Date,
+    // Rescan the whole chain
+    Genesis,
+}
+
+pub struct DescriptorPanel {
+    wallet: Option<String>,
+    descriptor: String,
+    range_start: u32,
+    range_end: u32,
+    birthday: Birthday,
+    date: String,
+    active: bool,
+    internal: bool,
+    label: String,
+    // Descriptors queued for the next import
+    queue: Vec<DescriptorImport>,
+    import: Pending<Vec<ImportResult>>,
+    // Imported descriptors with their outcome
+    results: Vec<(DescriptorImport, ImportResult)>,
+    importing: Vec<DescriptorImport>,
+    error: Option<String>,
+}
+
+impl Default for DescriptorPanel {
+    fn default() -> Self {
+        Self {
+            wallet: None,
+            descriptor: String::new(),
+            range_start: 0,
+            range_end: DEFAULT_RANGE_END,
+            birthday: Birthday::Now,
+            date: String::new(),
+            active: false,
+            internal: false,
+            label: String::new(),
+            queue: Vec::new(),
+            import: Pending::default(),
+            results: Vec::new(),
+            importing: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+impl DescriptorPanel {
+    fn poll(&mut self, wallet: &str) {
+        if self.wallet.as_deref() != Some(wallet) {
+            self.wallet = Some(wallet.to_string());
+            self.queue.clear();
+            self.results.clear();
+            self.error = None;
+        }
+
+        match self.import.poll() {
+            Some(Ok(results)) => {
+                let importing = std::mem::take(&mut self.importing);
+                self.results = importing.into_iter().zip(results).collect();
+            }
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                // Let the user retry without entering them again
+                self.queue = std::mem::take(&mut self.importing);
+            }
+            None => {}
+        }
+    }
+
+    fn timestamp(&self) -> Result<Option<i64>, String> {
+        match self.birthday {
+            Birthday::Now => Ok(None),
+            Birthday::Genesis => Ok(Some(0)),
+            Birthday::Date => {
+                let date = NaiveDate::parse_from_str(self.date.trim(), "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid date {}, expected YYYY-MM-DD", self.date))?;
+                Ok(date
+                    .and_hms_opt(0, 0, 0)
+                    .and_then(|time| time.and_local_timezone(Local).earliest())
+                    .map(|time| time.timestamp()))
+            }
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, wallet: Option<&str>) {
+        let Some(wallet) = wallet else {
+            ui.label("Open a wallet to import descriptors");
+            return;
+        };
+        self.poll(wallet);
+
+        ui.heading("Import descriptors");
+        ui.label(
+            "Descriptors are imported with importdescriptors. Watch-only descriptors \
+             need a wallet created without private keys.",
+        );
+        ui.add(
+            egui::TextEdit::multiline(&mut self.descriptor)
+                .desired_rows(2)
+                .desired_width(f32::INFINITY)
+                .font(egui::TextStyle::Monospace)
+                .hint_text("wpkh([fingerprint/84h/0h/0h]xpub.../0/*)#checksum"),
+        );
+
+        let parsed = parse_descriptor(&self.descriptor);
+        match &parsed {
+            Ok(parsed) => {
+                ui.horizontal(|ui| {
+                    ui.colored_label(egui::Color32::LIGHT_GREEN, "✔");
+                    ui.label(parsed.kind.description());
+                    if parsed.ranged {
+                        ui.label("· ranged");
+                    }
+                });
+            }
+            Err(e) if !self.descriptor.trim().is_empty() => {
+                ui.colored_label(egui::Color32::LIGHT_RED, e);
+            }
+            Err(_) => {}
+        }
+        let ranged = parsed.as_ref().is_ok_and(|parsed| parsed.ranged);
+
+        egui::Grid::new("descriptor_options")
+            .num_columns(2)
+            .show(ui, |ui| {
+                if ranged {
+                    ui.label("Range");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut self.range_start));
+                        ui.label("to");
+                        ui.add(
+                            egui::DragValue::new(&mut self.range_end)
+                                .range(self.range_start..=u32::MAX),
+                        );
+                    });
+                    ui.end_row();
+                }
+
+                ui.label("Rescan from");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.birthday, Birthday::Now, "Now (no rescan)");
+                    ui.radio_value(&mut self.birthday, Birthday::Date, "Date");
+                    ui.radio_value(&mut self.birthday, Birthday::Genesis, "Genesis");
+                    if self.birthday == Birthday::Date {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.date)
+                                .hint_text("YYYY-MM-DD")
+                                .desired_width(100.0),
+                        );
+                    }
+                });
+                ui.end_row();
+
+                // Only ranged descriptors can hand out new addresses
+                if ranged {
+                    ui.label("Use for");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.active, "New addresses");
+                        ui.add_enabled(
+                            self.active,
+                            egui::Checkbox::new(&mut self.internal, "Change"),
+                        );
+                    });
+                    ui.end_row();
+                } else {
+                    ui.label("Label");
+                    ui.text_edit_singleline(&mut self.label);
+                    ui.end_row();
+                }
+            });
+
+        if ui
+            .add_enabled(parsed.is_ok(), egui::Button::new("Add to import"))
+            .clicked()
+        {
+            self.error = None;
+            match (parsed, self.timestamp()) {
+                (Ok(parsed), Ok(timestamp)) => {
+                    self.queue.push(DescriptorImport {
+                        descriptor: parsed.descriptor,
+                        timestamp,
+                        range: parsed.ranged.then_some((self.range_start, self.range_end)),
+                        active: parsed.ranged && self.active,
+                        internal: parsed.ranged && self.active && self.internal,
+                        label: if parsed.ranged {
+                            String::new()
+                        } else {
+                            self.label.clone()
+                        },
+                    });
+                    self.descriptor.clear();
+                    self.label.clear();
+                }
+                (_, Err(e)) | (Err(e), _) => self.error = Some(e),
+            }
+        }
+
+        if !self.queue.is_empty() {
+            ui.separator();
+            ui.strong("To import");
+            let mut remove = None;
+            for (i, import) in self.queue.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.monospace(&import.descriptor);
+                });
+            }
+            if let Some(i) = remove {
+                self.queue.remove(i);
+            }
+            ui.horizontal(|ui| {
+                let busy = self.import.is_waiting();
+                if ui.add_enabled(!busy, egui::Button::new("Import")).clicked() {
+                    self.error = None;
+                    self.results.clear();
+                    self.importing = std::mem::take(&mut self.queue);
+                    let wallet = wallet.to_string();
+                    let imports = self.importing.clone();
+                    self.import = Pending::spawn(spawner, |send| {
+                        Task::ImportDescriptors(wallet, imports, send)
+                    });
+                }
+            });
+        }
+        if self.import.is_waiting() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Importing, a rescan can take a while");
+            });
+        }
+
+        if !self.results.is_empty() {
+            ui.separator();
+            for (import, result) in &self.results {
+                ui.horizontal(|ui| {
+                    if result.success {
+                        ui.colored_label(egui::Color32::LIGHT_GREEN, "Imported");
+                    } else {
+                        ui.colored_label(egui::Color32::LIGHT_RED, "Failed");
+                    }
+                    ui.monospace(&import.descriptor);
+                });
+                if let Some(error) = &result.error {
+                    ui.colored_label(egui::Color32::LIGHT_RED, error);
+                }
+                for warning in &result.warnings {
+                    ui.colored_label(egui::Color32::YELLOW, warning);
+                }
+            }
+        }
+
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::fee::{FeeEstimates, FeeMode, FeeRate, CONF_TARGETS};
+use crate::send::FeeChoice;
+use eframe::egui;
+
+// Fee guidance for the send page, backed by the node's smart fee estimates
+pub struct FeeSelector {
+    estimates: Option<FeeEstimates>,
+    mode: FeeMode,
+    target: u32,
+}
+
+impl Default for FeeSelector {
+    fn default() -> Self {
+        Self {
+            estimates: None,
+            mode: FeeMode::Economical,
+            target: 6,
+        }
+    }
+}
+
+impl FeeSelector {
+    pub fn set_estimates(&mut self, estimates: FeeEstimates) {
+        self.estimates = Some(estimates);
+    }
+
+    // Fee rate for the currently selected target and mode
+    pub fn selected(&self) -> Option<FeeRate> {
+        self.estimates.as_ref()?.for_target(self.target, self.mode)
+    }
+
+    // The lowest fee rate the node relays, once estimates have arrived
+    pub fn relay_min_fee(&self) -> Option<FeeRate> {
+        Some(self.estimates.as_ref()?.relay_min_fee)
+    }
+
+    // Let the wallet estimate with the selected target and mode when building a transaction
+    pub fn fee_choice(&self) -> FeeChoice {
+        FeeChoice::Target(self.target, self.mode)
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let Some(estimates) = &self.estimates else {
+            ui.label("Waiting for fee estimates...");
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Confirm within");
+            egui::ComboBox::from_id_source("conf_target")
+                .selected_text(format!("{} blocks", self.target))
+                .show_ui(ui, |ui| {
+                    for target in CONF_TARGETS {
+                        ui.selectable_value(&mut self.target, target, format!("{} blocks", target));
+                    }
+                });
+            ui.radio_value(&mut self.mode, FeeMode::Economical, "Economical");
+            ui.radio_value(&mut self.mode, FeeMode::Conservative, "Conservative");
+        });
+
+        egui::Grid::new("fee_estimates")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Target");
+                ui.strong("Economical");
+                ui.strong("Conservative");
+                ui.end_row();
+                for target in CONF_TARGETS {
+                    ui.label(target.to_string());
+                    for mode in [FeeMode::Economical, FeeMode::Conservative] {
+                        match estimates.get(target, mode).and_then(|e| e.fee_rate) {
+                            Some(rate) => ui.label(rate.to_string()),
+                            None => ui.label("-"),
+                        };
+                    }
+                    ui.end_row();
+                }
+            });
+
+        ui.label(format!("Relay min fee: {}", estimates.relay_min_fee));
+        ui.label(format!("Dust relay fee: {}", estimates.dust_relay_fee));
+        match self.selected() {
+            Some(rate) => ui.strong(format!("Selected fee rate: {}", rate)),
+            None => ui.strong("No estimate available for this target"),
+        };
+    }
+}
@@ -0,0 +1,535 @@
+use super::Pending;
+use crate::bump::{BumpProposal, TxActions};
+use crate::fee::FeeRate;
+use crate::history::{TxKind, TxRecord};
+use crate::send::{CoinControl, FeeChoice};
+use crate::spawner::LocalSpawner;
+use crate::store::WalletState;
+use crate::tasks::Task;
+use bitcoin::{Address, Amount, Denomination, Network, Txid};
+use chrono::{DateTime, Local, NaiveDate};
+use eframe::egui;
+
+const PAGE_SIZE: usize = 25;
+
+fn format_time(time: i64) -> String {
+    DateTime::from_timestamp(time, 0)
+        .map(|time| {
+            time.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+// Start of the given local day as a unix timestamp
+fn parse_date(date: &str) -> Result<Option<i64>, String> {
+    let date = date.trim();
+    if date.is_empty() {
+        return Ok(None);
+    }
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date {}, expected YYYY-MM-DD", date))?;
+    Ok(date
+        .and_hms_opt(0, 0, 0)
+        .and_then(|time| time.and_local_timezone(Local).earliest())
+        .map(|time| time.timestamp()))
+}
+
+#[derive(Default)]
+struct Filter {
+    kind: Option<TxKind>,
+    from: String,
+    to: String,
+    min_amount: String,
+}
+
+impl Filter {
+    fn matcher(&self) -> Result<impl Fn(&TxRecord) -> bool, String> {
+        let kind = self.kind;
+        let from = parse_date(&self.from)?;
+        // Inclusive of the whole end day
+        let to = parse_date(&self.to)?.map(|to| to + 24 * 60 * 60);
+        let min_amount = match self.min_amount.trim() {
+            "" => Amount::ZERO,
+            amount => Amount::from_str_in(amount, Denomination::Bitcoin)
+                .map_err(|e| format!("Invalid minimum amount: {}", e))?,
+        };
+        Ok(move |record: &TxRecord| {
+            kind.map_or(true, |kind| record.kind == kind)
+                && from.map_or(true, |from| record.time >= from)
+                && to.map_or(true, |to| record.time < to)
+                && record.amount().unsigned_abs() >= min_amount
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RowAction {
+    Bump,
+    Abandon,
+}
+
+struct BumpDialog {
+    txid: Txid,
+    old_fee_rate: Option<FeeRate>,
+    // sat/vB
+    fee_rate: f64,
+    proposal: Option<BumpProposal>,
+}
+
+#[derive(Default)]
+pub struct HistoryPanel {
+    wallet: Option<String>,
+    network: Option<Network>,
+    network_pending: Pending<Network>,
+    filter: Filter,
+    page: usize,
+    selected: Option<Txid>,
+    // Bump and abandon availability for the selected transaction
+    actions: Option<(Txid, TxActions)>,
+    actions_pending: Pending<TxActions>,
+    actions_txid: Option<Txid>,
+    abandon: Pending<()>,
+    bump: Option<BumpDialog>,
+    bump_pending: Pending<BumpProposal>,
+    bump_commit: Pending<Txid>,
+    message: Option<String>,
+    error: Option<String>,
+}
+
+impl HistoryPanel {
+    // Called when the wallet's store reports a changed transaction
+    pub fn transaction_changed(&mut self, wallet: &str) {
+        if self.wallet.as_deref() == Some(wallet) {
+            // A changed transaction may no longer be bumpable or abandonable
+            self.actions = None;
+        }
+    }
+
+    fn poll(&mut self, spawner: &LocalSpawner, wallet: &str) {
+        if self.wallet.as_deref() != Some(wallet) {
+            self.wallet = Some(wallet.to_string());
+            self.selected = None;
+            self.actions = None;
+            self.bump = None;
+            self.page = 0;
+            self.message = None;
+            self.error = None;
+        }
+
+        match self.network_pending.poll() {
+            Some(Ok(network)) => self.network = Some(network),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.actions_pending.poll() {
+            Some(Ok(actions)) => {
+                self.actions = self.actions_txid.take().map(|txid| (txid, actions));
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.abandon.poll() {
+            Some(Ok(())) => {
+                self.message = Some("Transaction abandoned".to_string());
+                self.actions = None;
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.bump_pending.poll() {
+            Some(Ok(proposal)) => {
+                if let Some(bump) = &mut self.bump {
+                    bump.proposal = Some(proposal);
+                }
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.bump_commit.poll() {
+            Some(Ok(txid)) => {
+                self.message = Some(format!("Replaced by transaction {}", txid));
+                self.bump = None;
+                self.actions = None;
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        if self.network.is_none() && !self.network_pending.is_waiting() && self.error.is_none() {
+            self.network_pending = Pending::spawn(spawner, Task::GetNetwork);
+        }
+        if let Some(txid) = self.selected {
+            let known = self.actions.is_some_and(|(known, _)| known == txid);
+            if !known && !self.actions_pending.is_waiting() {
+                let wallet = wallet.to_string();
+                self.actions_txid = Some(txid);
+                self.actions_pending =
+                    Pending::spawn(spawner, |send| Task::GetTxActions(wallet, txid, send));
+            }
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        wallet: Option<&str>,
+        state: Option<&WalletState>,
+        tip: Option<i32>,
+    ) {
+        let Some(wallet) = wallet else {
+            ui.label("Open a wallet to see its transactions");
+            return;
+        };
+        self.poll(spawner, wallet);
+
+        self.show_filter(ui);
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+        self.show_bump_dialog(ui.ctx(), spawner, wallet);
+
+        let Some(records) = state.map(|state| &state.transactions) else {
+            ui.spinner();
+            return;
+        };
+        let matcher = match self.filter.matcher() {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                ui.colored_label(egui::Color32::LIGHT_RED, e);
+                return;
+            }
+        };
+        let filtered: Vec<&TxRecord> = records.iter().filter(|r| matcher(r)).collect();
+        let tip = tip.unwrap_or(0);
+
+        let pages = filtered.len().div_ceil(PAGE_SIZE).max(1);
+        self.page = self.page.min(pages - 1);
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.page > 0, egui::Button::new("◀"))
+                .clicked()
+            {
+                self.page -= 1;
+            }
+            ui.label(format!("Page {} of {}", self.page + 1, pages));
+            if ui
+                .add_enabled(self.page + 1 < pages, egui::Button::new("▶"))
+                .clicked()
+            {
+                self.page += 1;
+            }
+            ui.label(format!("{} transactions", filtered.len()));
+        });
+
+        let mut clicked = None;
+        let mut context_clicked = None;
+        let mut requested = None;
+        egui::Grid::new("transactions")
+            .striped(true)
+            .num_columns(5)
+            .show(ui, |ui| {
+                ui.strong("Date");
+                ui.strong("Type");
+                ui.strong("Label");
+                ui.strong("Amount");
+                ui.strong("Confirmations");
+                ui.end_row();
+                for record in filtered.iter().skip(self.page * PAGE_SIZE).take(PAGE_SIZE) {
+                    let selected = self.selected == Some(record.txid);
+                    let response = ui.selectable_label(selected, format_time(record.time));
+                    if response.clicked() {
+                        clicked = Some(record.txid);
+                    }
+                    if response.secondary_clicked() {
+                        context_clicked = Some(record.txid);
+                    }
+                    let actions = self
+                        .actions
+                        .filter(|(txid, _)| *txid == record.txid)
+                        .map(|(_, actions)| actions);
+                    response.context_menu(|ui| {
+                        let Some(actions) = actions else {
+                            ui.spinner();
+                            return;
+                        };
+                        if ui
+                            .add_enabled(actions.can_bump, egui::Button::new("Bump fee..."))
+                            .clicked()
+                        {
+                            requested = Some((record.txid, RowAction::Bump));
+                            ui.close_menu();
+                        }
+                        if ui
+                            .add_enabled(actions.can_abandon, egui::Button::new("Abandon"))
+                            .clicked()
+                        {
+                            requested = Some((record.txid, RowAction::Abandon));
+                            ui.close_menu();
+                        }
+                    });
+                    ui.label(record.kind.to_string());
+                    ui.label(&record.label);
+                    ui.monospace(record.amount().display_dynamic().to_string());
+                    ui.label(status_text(record, tip));
+                    ui.end_row();
+                }
+            });
+        if let Some(txid) = clicked {
+            self.selected = (self.selected != Some(txid)).then_some(txid);
+        }
+        // Opening the context menu selects the row so its actions get fetched
+        if let Some(txid) = context_clicked {
+            self.selected = Some(txid);
+        }
+        match requested {
+            Some((txid, RowAction::Bump)) => {
+                let old_fee_rate = records.iter().find(|r| r.txid == txid).and_then(|record| {
+                    let vsize = record.tx.vsize() as u64;
+                    let fee = record.fee()?;
+                    Some(FeeRate::from_sat_per_kvb(
+                        fee.to_sat() * 1000 / vsize.max(1),
+                    ))
+                });
+                self.message = None;
+                self.error = None;
+                self.bump = Some(BumpDialog {
+                    txid,
+                    old_fee_rate,
+                    fee_rate: old_fee_rate.map_or(1.0, |rate| rate.sat_per_vb()) + 1.0,
+                    proposal: None,
+                });
+            }
+            Some((txid, RowAction::Abandon)) => {
+                self.message = None;
+                self.error = None;
+                let wallet = wallet.to_string();
+                self.abandon =
+                    Pending::spawn(spawner, |send| Task::AbandonTransaction(wallet, txid, send));
+            }
+            None => {}
+        }
+
+        let selected = self
+            .selected
+            .and_then(|txid| records.iter().find(|r| r.txid == txid));
+        if let Some(record) = selected {
+            ui.separator();
+            show_details(ui, record, tip, self.network);
+        }
+    }
+
+    // Preview the replacement's fee before it is signed and broadcast
+    fn show_bump_dialog(&mut self, ctx: &egui::Context, spawner: &LocalSpawner, wallet: &str) {
+        let Some(bump) = &mut self.bump else {
+            return;
+        };
+        let busy = self.bump_pending.is_waiting() || self.bump_commit.is_waiting();
+
+        let mut open = true;
+        let mut preview = false;
+        let mut confirm = None;
+        egui::Window::new("Bump fee")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.monospace(bump.txid.to_string());
+                if let Some(rate) = bump.old_fee_rate {
+                    ui.label(format!("Current fee rate: {}", rate));
+                }
+                ui.horizontal(|ui| {
+                    ui.label("New fee rate");
+                    let changed = ui
+                        .add(
+                            egui::DragValue::new(&mut bump.fee_rate)
+                                .range(0.0..=10_000.0)
+                                .speed(0.1)
+                                .suffix(" sat/vB"),
+                        )
+                        .changed();
+                    if changed {
+                        bump.proposal = None;
+                    }
+                });
+
+                match &bump.proposal {
+                    Some(proposal) => {
+                        ui.label(format!("Old fee: {}", proposal.old_fee.display_dynamic()));
+                        ui.strong(format!("New fee: {}", proposal.new_fee.display_dynamic()));
+                        if ui.add_enabled(!busy, egui::Button::new("Bump")).clicked() {
+                            confirm = Some(proposal.clone());
+                        }
+                    }
+                    None => {
+                        if ui
+                            .add_enabled(!busy, egui::Button::new("Preview"))
+                            .clicked()
+                        {
+                            preview = true;
+                        }
+                    }
+                }
+                if busy {
+                    ui.spinner();
+                }
+            });
+
+        if preview {
+            self.error = None;
+            let wallet = wallet.to_string();
+            let txid = bump.txid;
+            let coin_control = CoinControl {
+                fee: FeeChoice::Rate(FeeRate::from_sat_per_vb(bump.fee_rate)),
+                signal_rbf: true,
+                inputs: Vec::new(),
+            };
+            self.bump_pending = Pending::spawn(spawner, |send| {
+                Task::CreateBumpTransaction(wallet, txid, coin_control, send)
+            });
+        }
+        if let Some(proposal) = confirm {
+            self.error = None;
+            let wallet = wallet.to_string();
+            self.bump_commit = Pending::spawn(spawner, |send| {
+                Task::CommitBumpTransaction(wallet, proposal, send)
+            });
+        }
+        if !open {
+            self.bump = None;
+        }
+    }
+
+    fn show_filter(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("tx_kind")
+                .selected_text(
+                    self.filter
+                        .kind
+                        .map_or("All types".to_string(), |kind| kind.to_string()),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.filter.kind, None, "All types");
+                    for kind in TxKind::ALL {
+                        ui.selectable_value(&mut self.filter.kind, Some(kind), kind.to_string());
+                    }
+                });
+            ui.label("From");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter.from)
+                    .hint_text("YYYY-MM-DD")
+                    .desired_width(90.0),
+            );
+            ui.label("To");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter.to)
+                    .hint_text("YYYY-MM-DD")
+                    .desired_width(90.0),
+            );
+            ui.label("Min amount");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter.min_amount)
+                    .hint_text("BTC")
+                    .desired_width(90.0),
+            );
+        });
+    }
+}
+
+fn status_text(record: &TxRecord, tip: i32) -> String {
+    if record.abandoned {
+        return "Abandoned".to_string();
+    }
+    match record.confirmations(tip) {
+        depth if depth < 0 => "Conflicted".to_string(),
+        0 if record.in_mempool => "Unconfirmed (in mempool)".to_string(),
+        0 => "Unconfirmed".to_string(),
+        depth => depth.to_string(),
+    }
+}
+
+fn show_details(ui: &mut egui::Ui, record: &TxRecord, tip: i32, network: Option<Network>) {
+    ui.heading("Transaction details");
+    egui::Grid::new("transaction_details")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Transaction ID");
+            ui.horizontal(|ui| {
+                ui.monospace(record.txid.to_string());
+                if ui.button("Copy").clicked() {
+                    ui.output_mut(|o| o.copied_text = record.txid.to_string());
+                }
+            });
+            ui.end_row();
+            ui.label("Status");
+            ui.label(status_text(record, tip));
+            ui.end_row();
+            if let Some(height) = record.block_height {
+                ui.label("Block");
+                ui.label(height.to_string());
+                ui.end_row();
+            }
+            ui.label("Date");
+            ui.label(format_time(record.time));
+            ui.end_row();
+            ui.label("Type");
+            ui.label(record.kind.to_string());
+            ui.end_row();
+            if !record.label.is_empty() {
+                ui.label("Label");
+                ui.label(&record.label);
+                ui.end_row();
+            }
+            if let Some(comment) = &record.comment {
+                ui.label("Comment");
+                ui.label(comment);
+                ui.end_row();
+            }
+            ui.label("Net amount");
+            ui.label(record.amount().display_dynamic().to_string());
+            ui.end_row();
+            if let Some(fee) = record.fee() {
+                ui.label("Fee");
+                ui.label(fee.display_dynamic().to_string());
+                ui.end_row();
+            }
+            ui.label("Size");
+            ui.label(format!("{} vB", record.tx.vsize()));
+            ui.end_row();
+        });
+
+    ui.strong("Inputs");
+    for input in &record.tx.input {
+        ui.monospace(input.previous_output.to_string());
+    }
+    ui.strong("Outputs");
+    for (i, output) in record.tx.output.iter().enumerate() {
+        let destination = network
+            .and_then(|network| Address::from_script(&output.script_pubkey, network).ok())
+            .map(|address| address.to_string())
+            .unwrap_or_else(|| output.script_pubkey.to_hex_string());
+        let marker = match (
+            record.output_is_change.get(i).copied().unwrap_or(false),
+            record.output_is_mine.get(i).copied().unwrap_or(false),
+        ) {
+            (true, _) => " (change)",
+            (false, true) => " (mine)",
+            (false, false) => "",
+        };
+        ui.monospace(format!(
+            "{}{}: {}",
+            destination,
+            marker,
+            output.value.display_dynamic()
+        ));
+    }
+}
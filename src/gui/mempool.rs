@@ -0,0 +1,79 @@
+use crate::mempool::{MempoolEvent, MempoolInfo};
+use eframe::egui;
+use std::collections::VecDeque;
+
+// Number of entries kept in the transaction feed
+const FEED_LENGTH: usize = 200;
+
+#[derive(Default)]
+pub struct MempoolPanel {
+    info: Option<MempoolInfo>,
+    feed: VecDeque<MempoolEvent>,
+}
+
+impl MempoolPanel {
+    pub fn set_info(&mut self, info: MempoolInfo) {
+        self.info = Some(info);
+    }
+
+    pub fn push(&mut self, event: MempoolEvent) {
+        self.feed.push_front(event);
+        self.feed.truncate(FEED_LENGTH);
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let Some(info) = &self.info else {
+            ui.label("Waiting for mempool data...");
+            return;
+        };
+
+        ui.heading("Mempool");
+        ui.label(format!("Transactions: {}", info.size));
+        ui.label(format!(
+            "Memory usage: {:.2} MB",
+            info.dynamic_usage as f64 / 1_000_000.0
+        ));
+
+        ui.separator();
+        ui.label("Fee rate histogram (sat/vB)");
+        let max_vsize = info.histogram.iter().map(|b| b.vsize).max().unwrap_or(0);
+        for (i, bucket) in info.histogram.iter().enumerate() {
+            let range = match info.histogram.get(i + 1) {
+                Some(next) => format!("{}-{}", bucket.min_fee_rate, next.min_fee_rate),
+                None => format!("{}+", bucket.min_fee_rate),
+            };
+            let fraction = if max_vsize == 0 {
+                0.0
+            } else {
+                bucket.vsize as f32 / max_vsize as f32
+            };
+            ui.horizontal(|ui| {
+                ui.add_sized([60.0, 18.0], egui::Label::new(range));
+                ui.add(
+                    egui::ProgressBar::new(fraction)
+                        .text(format!("{} txs, {} vB", bucket.count, bucket.vsize)),
+                );
+            });
+        }
+
+        ui.separator();
+        ui.label("Recent activity");
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for event in &self.feed {
+                match event {
+                    MempoolEvent::Added(tx) => ui.colored_label(
+                        egui::Color32::LIGHT_GREEN,
+                        format!(
+                            "+ {} ({} vB, {} sat out)",
+                            tx.txid, tx.vsize, tx.output_value
+                        ),
+                    ),
+                    MempoolEvent::Removed(tx, reason) => ui.colored_label(
+                        egui::Color32::LIGHT_RED,
+                        format!("- {} ({})", tx.txid, reason),
+                    ),
+                };
+            }
+        });
+    }
+}
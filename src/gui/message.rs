@@ -0,0 +1,186 @@
+use super::Pending;
+use crate::address::parse_address;
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use bitcoin::Network;
+use eframe::egui;
+
+// Prove ownership of an address by signing a message with its key
+#[derive(Default)]
+pub struct SignDialog {
+    address: String,
+    message: String,
+    signature: Option<String>,
+    pending: Pending<String>,
+    error: Option<String>,
+}
+
+impl SignDialog {
+    pub fn new(address: String) -> Self {
+        Self {
+            address,
+            ..Self::default()
+        }
+    }
+
+    // Returns false once the dialog is closed
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        spawner: &LocalSpawner,
+        wallet: &str,
+        network: Option<Network>,
+    ) -> bool {
+        match self.pending.poll() {
+            Some(Ok(signature)) => self.signature = Some(signature),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        let mut open = true;
+        egui::Window::new("Sign message")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label("Only legacy (P2PKH) addresses can sign messages.");
+                ui.horizontal(|ui| {
+                    ui.label("Address");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.address)
+                            .desired_width(360.0)
+                            .font(egui::TextStyle::Monospace),
+                    );
+                });
+                ui.label("Message");
+                ui.text_edit_multiline(&mut self.message);
+
+                let sign = ui
+                    .add_enabled(!self.pending.is_waiting(), egui::Button::new("Sign"))
+                    .clicked();
+                if sign {
+                    self.signature = None;
+                    self.error = None;
+                    match network.ok_or("Waiting for the node's network".to_string()) {
+                        Ok(network) => match parse_address(&self.address, network) {
+                            Ok(address) => {
+                                let wallet = wallet.to_string();
+                                let message = self.message.clone();
+                                self.pending = Pending::spawn(spawner, |send| {
+                                    Task::SignMessage(wallet, address, message, send)
+                                });
+                            }
+                            Err(e) => self.error = Some(e),
+                        },
+                        Err(e) => self.error = Some(e),
+                    }
+                }
+
+                if let Some(signature) = &self.signature {
+                    ui.separator();
+                    ui.label("Signature");
+                    ui.horizontal(|ui| {
+                        ui.monospace(signature);
+                        if ui.button("Copy").clicked() {
+                            ui.output_mut(|o| o.copied_text = signature.clone());
+                        }
+                    });
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::LIGHT_RED, error);
+                }
+            });
+        open
+    }
+}
+
+// Check a signed message against an address, using the node's verifymessage
+#[derive(Default)]
+pub struct VerifyDialog {
+    address: String,
+    signature: String,
+    message: String,
+    verified: Option<bool>,
+    pending: Pending<bool>,
+    error: Option<String>,
+}
+
+impl VerifyDialog {
+    // Returns false once the dialog is closed
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        spawner: &LocalSpawner,
+        network: Option<Network>,
+    ) -> bool {
+        match self.pending.poll() {
+            Some(Ok(verified)) => self.verified = Some(verified),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        let mut open = true;
+        egui::Window::new("Verify message")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("verify_message")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Address");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.address)
+                                .desired_width(360.0)
+                                .font(egui::TextStyle::Monospace),
+                        );
+                        ui.end_row();
+                        ui.label("Signature");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.signature)
+                                .desired_width(360.0)
+                                .font(egui::TextStyle::Monospace),
+                        );
+                        ui.end_row();
+                    });
+                ui.label("Message");
+                ui.text_edit_multiline(&mut self.message);
+
+                let verify = ui
+                    .add_enabled(!self.pending.is_waiting(), egui::Button::new("Verify"))
+                    .clicked();
+                if verify {
+                    self.verified = None;
+                    self.error = None;
+                    match network.ok_or("Waiting for the node's network".to_string()) {
+                        Ok(network) => match parse_address(&self.address, network) {
+                            Ok(address) => {
+                                let signature = self.signature.clone();
+                                let message = self.message.clone();
+                                self.pending = Pending::spawn(spawner, |send| {
+                                    Task::VerifyMessage(address, signature, message, send)
+                                });
+                            }
+                            Err(e) => self.error = Some(e),
+                        },
+                        Err(e) => self.error = Some(e),
+                    }
+                }
+
+                match self.verified {
+                    Some(true) => {
+                        ui.colored_label(egui::Color32::LIGHT_GREEN, "Message verified");
+                    }
+                    Some(false) => {
+                        ui.colored_label(
+                            egui::Color32::LIGHT_RED,
+                            "The signature does not match the address and message",
+                        );
+                    }
+                    None => {}
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::LIGHT_RED, error);
+                }
+            });
+        open
+    }
+}
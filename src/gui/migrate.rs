@@ -0,0 +1,221 @@
+use super::wallet::{passphrase_buffer, take, PASSPHRASE_MAX_CHARS};
+use super::Pending;
+use crate::migrate::{LegacyScan, MigrationReport};
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use eframe::egui;
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+// Walks through converting a legacy wallet to a descriptor wallet
+pub struct MigrationWizard {
+    legacy: Option<Vec<String>>,
+    legacy_pending: Pending<LegacyScan>,
+    // Wallets whose format could not be checked, with the reason
+    unchecked: Vec<(String, String)>,
+    selected: Option<String>,
+    backup: Option<PathBuf>,
+    passphrase: Zeroizing<String>,
+    migration: Pending<MigrationReport>,
+    report: Option<MigrationReport>,
+    // Set once a migration succeeds, for the wallet page to switch to
+    migrated: Option<String>,
+    error: Option<String>,
+}
+
+impl MigrationWizard {
+    pub fn new(spawner: &LocalSpawner) -> Self {
+        Self {
+            legacy: None,
+            legacy_pending: Pending::spawn(spawner, Task::FindLegacyWallets),
+            unchecked: Vec::new(),
+            selected: None,
+            backup: None,
+            passphrase: passphrase_buffer(),
+            migration: Pending::default(),
+            report: None,
+            migrated: None,
+            error: None,
+        }
+    }
+
+    pub fn take_migrated(&mut self) -> Option<String> {
+        self.migrated.take()
+    }
+
+    fn poll(&mut self) {
+        match self.legacy_pending.poll() {
+            Some(Ok(scan)) => {
+                self.selected = scan.legacy.first().cloned();
+                self.legacy = Some(scan.legacy);
+                self.unchecked = scan.failed;
+            }
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.legacy = Some(Vec::new());
+            }
+            None => {}
+        }
+
+        match self.migration.poll() {
+            Some(Ok(report)) => {
+                self.migrated = Some(report.wallet.clone());
+                self.report = Some(report);
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+    }
+
+    // Returns false once the wizard is closed
+    pub fn show(&mut self, ctx: &egui::Context, spawner: &LocalSpawner) -> bool {
+        self.poll();
+
+        let mut open = true;
+        egui::Window::new("Migrate legacy wallet")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if let Some(report) = &self.report {
+                    show_report(ui, report, self.backup.as_ref());
+                    return;
+                }
+                let Some(legacy) = &self.legacy else {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Looking for legacy wallets");
+                    });
+                    return;
+                };
+                for (name, error) in &self.unchecked {
+                    let name = if name.is_empty() { "default" } else { name };
+                    ui.colored_label(
+                        egui::Color32::LIGHT_RED,
+                        format!("Could not check wallet {}: {}", name, error),
+                    );
+                }
+                if legacy.is_empty() {
+                    ui.label("There are no legacy wallets in the wallet directory.");
+                    if let Some(error) = &self.error {
+                        ui.colored_label(egui::Color32::LIGHT_RED, error);
+                    }
+                    return;
+                }
+                let legacy = legacy.clone();
+                self.show_form(ui, spawner, &legacy);
+            });
+        // Keep the wizard open while the migration runs, so its outcome is seen
+        open || self.migration.is_waiting()
+    }
+
+    fn show_form(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, legacy: &[String]) {
+        ui.label(
+            "Legacy wallets use the old Berkeley DB format, which Bitcoin Core is \
+             phasing out. Migration converts the wallet in place to a descriptor wallet \
+             with the same name, keys and transactions.",
+        );
+        ui.label(
+            "Scripts the wallet only watched, or could sign for without considering \
+             them its own, move into separate watch-only and solvables wallets.",
+        );
+        ui.label(
+            "The wallet is backed up first, and migration does not start if the \
+             backup fails. An encrypted wallet needs its passphrase.",
+        );
+        ui.separator();
+
+        let busy = self.migration.is_waiting();
+        ui.add_enabled_ui(!busy, |ui| {
+            egui::Grid::new("migrate_wallet")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Wallet");
+                    egui::ComboBox::from_id_source("legacy_wallet")
+                        .selected_text(self.selected.as_deref().unwrap_or("Select wallet"))
+                        .show_ui(ui, |ui| {
+                            for name in legacy {
+                                ui.selectable_value(&mut self.selected, Some(name.clone()), name);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Backup");
+                    ui.horizontal(|ui| {
+                        if ui.button("Choose file...").clicked() {
+                            let name = match self.selected.as_deref() {
+                                Some("") | None => "default",
+                                Some(name) => name,
+                            };
+                            if let Some(path) = rfd::FileDialog::new()
+                                .set_file_name(format!("{}-legacy.bak", name))
+                                .save_file()
+                            {
+                                self.backup = Some(path);
+                            }
+                        }
+                        if let Some(path) = &self.backup {
+                            ui.label(path.display().to_string());
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Passphrase");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut *self.passphrase)
+                            .password(true)
+                            .char_limit(PASSPHRASE_MAX_CHARS)
+                            .hint_text("Only for encrypted wallets"),
+                    );
+                    ui.end_row();
+                });
+        });
+
+        ui.horizontal(|ui| {
+            let ready = self.selected.is_some() && self.backup.is_some() && !busy;
+            if ui
+                .add_enabled(ready, egui::Button::new("Back up and migrate"))
+                .clicked()
+            {
+                if let (Some(wallet), Some(backup)) = (self.selected.clone(), self.backup.clone()) {
+                    self.error = None;
+                    let passphrase = take(&mut self.passphrase);
+                    self.migration = Pending::spawn(spawner, |send| {
+                        Task::MigrateWallet(wallet, backup, passphrase, send)
+                    });
+                }
+            }
+            if busy {
+                ui.spinner();
+                ui.label("Migrating, this can take a while for large wallets");
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+    }
+}
+
+fn show_report(ui: &mut egui::Ui, report: &MigrationReport, backup: Option<&PathBuf>) {
+    ui.colored_label(
+        egui::Color32::LIGHT_GREEN,
+        format!("Migrated {} to a descriptor wallet", report.wallet),
+    );
+    egui::Grid::new("migration_report")
+        .num_columns(2)
+        .show(ui, |ui| {
+            if let Some(backup) = backup {
+                ui.label("Backup");
+                ui.label(backup.display().to_string());
+                ui.end_row();
+            }
+            ui.label("Node backup");
+            ui.label(report.node_backup.display().to_string());
+            ui.end_row();
+            ui.label("Watch-only wallet");
+            ui.label(report.watch_only_wallet.as_deref().unwrap_or("None"));
+            ui.end_row();
+            ui.label("Solvables wallet");
+            ui.label(report.solvables_wallet.as_deref().unwrap_or("None"));
+            ui.end_row();
+        });
+}
@@ -0,0 +1,160 @@
+use super::template::TemplatePanel;
+use super::Pending;
+use crate::address::parse_address;
+use crate::mining::BlockTip;
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use bitcoin::{BlockHash, Network};
+use eframe::egui;
+
+// Inspect the node's next block template, and on regtest mine blocks through the
+// Mining interface with the proof of work done locally
+pub struct MiningPanel {
+    network: Option<Network>,
+    network_pending: Pending<Network>,
+    tip: Option<BlockTip>,
+    tip_pending: Pending<Option<BlockTip>>,
+    // Node status height the tip was fetched at
+    tip_height: Option<i32>,
+    blocks: u32,
+    address: String,
+    mine: Pending<Vec<BlockHash>>,
+    mined: Vec<BlockHash>,
+    template: TemplatePanel,
+    error: Option<String>,
+}
+
+impl Default for MiningPanel {
+    fn default() -> Self {
+        Self {
+            network: None,
+            network_pending: Pending::default(),
+            tip: None,
+            tip_pending: Pending::default(),
+            tip_height: None,
+            blocks: 1,
+            address: String::new(),
+            mine: Pending::default(),
+            mined: Vec::new(),
+            template: TemplatePanel::default(),
+            error: None,
+        }
+    }
+}
+
+impl MiningPanel {
+    fn poll(&mut self, spawner: &LocalSpawner, tip_height: Option<i32>) {
+        match self.network_pending.poll() {
+            Some(Ok(network)) => self.network = Some(network),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.tip_pending.poll() {
+            Some(Ok(tip)) => self.tip = tip,
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.mine.poll() {
+            Some(Ok(hashes)) => self.mined = hashes,
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        if self.network.is_none() && !self.network_pending.is_waiting() && self.error.is_none() {
+            self.network_pending = Pending::spawn(spawner, Task::GetNetwork);
+        }
+        // The status bar's height is pushed on every block, so follow it
+        if self.network == Some(Network::Regtest)
+            && self.tip_height != tip_height
+            && !self.tip_pending.is_waiting()
+        {
+            self.tip_height = tip_height;
+            self.tip_pending = Pending::spawn(spawner, Task::GetMiningTip);
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, tip_height: Option<i32>) {
+        self.poll(spawner, tip_height);
+
+        ui.heading("Mining");
+        match self.network {
+            Some(Network::Regtest) => self.show_mine(ui, spawner),
+            Some(network) => {
+                ui.label(format!(
+                    "Mining blocks is only available on regtest, the node is on {}",
+                    network
+                ));
+            }
+            None => {
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::LIGHT_RED, error);
+                } else {
+                    ui.spinner();
+                }
+                return;
+            }
+        }
+
+        ui.separator();
+        self.template.show(ui, spawner, tip_height);
+    }
+
+    fn show_mine(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner) {
+        if let Some(tip) = &self.tip {
+            ui.label(format!("Tip: {} at height {}", tip.hash, tip.height));
+        }
+
+        ui.add_enabled_ui(!self.mine.is_waiting(), |ui| {
+            egui::Grid::new("mine_form").num_columns(2).show(ui, |ui| {
+                ui.label("Blocks");
+                ui.add(egui::DragValue::new(&mut self.blocks).range(1..=1000));
+                ui.end_row();
+
+                ui.label("Pay rewards to");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.address)
+                        .hint_text("Address")
+                        .desired_width(360.0)
+                        .font(egui::TextStyle::Monospace),
+                );
+                ui.end_row();
+            });
+
+            if ui.button("Mine").clicked() {
+                self.error = None;
+                self.mined.clear();
+                match parse_address(&self.address, Network::Regtest) {
+                    Ok(address) => {
+                        let blocks = self.blocks;
+                        self.mine =
+                            Pending::spawn(spawner, |send| Task::MineBlocks(blocks, address, send));
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        });
+        if self.mine.is_waiting() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Mining {} blocks", self.blocks));
+            });
+        }
+
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+        if !self.mined.is_empty() {
+            ui.separator();
+            ui.label(format!("Mined {} blocks", self.mined.len()));
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for hash in &self.mined {
+                        ui.monospace(hash.to_string());
+                    }
+                });
+        }
+    }
+}
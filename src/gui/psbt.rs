@@ -0,0 +1,355 @@
+use super::Pending;
+use crate::psbt::{
+    combine_psbts, decode_psbt, extract_transaction, input_status, spent_output, FilledPsbt,
+};
+use crate::signer::ExternalSigner;
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use bitcoin::psbt::Psbt;
+use bitcoin::{Address, Network, Txid};
+use eframe::egui;
+
+// Import, sign, combine and broadcast partially signed transactions
+#[derive(Default)]
+pub struct PsbtPanel {
+    network: Option<Network>,
+    network_pending: Pending<Network>,
+    psbt: Option<Psbt>,
+    // Base64 pasted by the user
+    import_text: String,
+    fill: Pending<FilledPsbt>,
+    device_sign: Pending<Psbt>,
+    broadcast: Pending<Txid>,
+    status: Option<String>,
+    error: Option<String>,
+}
+
+impl PsbtPanel {
+    // Show a PSBT created elsewhere in the app, replacing the current one
+    pub fn open(&mut self, psbt: Psbt) {
+        self.psbt = Some(psbt);
+        self.status = Some("Created unsigned PSBT".to_string());
+        self.error = None;
+    }
+
+    fn poll(&mut self, spawner: &LocalSpawner) {
+        match self.network_pending.poll() {
+            Some(Ok(network)) => self.network = Some(network),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.fill.poll() {
+            Some(Ok(filled)) => {
+                self.status = Some(if filled.complete {
+                    format!(
+                        "Signed {} inputs, the transaction is ready to broadcast",
+                        filled.signed
+                    )
+                } else {
+                    format!(
+                        "Signed {} inputs, more signatures are needed",
+                        filled.signed
+                    )
+                });
+                self.psbt = Some(filled.psbt);
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.device_sign.poll() {
+            Some(Ok(psbt)) => {
+                let finalized = psbt
+                    .inputs
+                    .iter()
+                    .filter(|input| input_status(input).finalized)
+                    .count();
+                self.status = Some(format!(
+                    "Signed with the device, {} of {} inputs finalized",
+                    finalized,
+                    psbt.inputs.len()
+                ));
+                self.psbt = Some(psbt);
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.broadcast.poll() {
+            Some(Ok(txid)) => self.status = Some(format!("Broadcast transaction {}", txid)),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        if self.network.is_none() && !self.network_pending.is_waiting() && self.error.is_none() {
+            self.network_pending = Pending::spawn(spawner, Task::GetNetwork);
+        }
+    }
+
+    fn busy(&self) -> bool {
+        self.fill.is_waiting() || self.device_sign.is_waiting() || self.broadcast.is_waiting()
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        wallet: Option<&str>,
+        signer: Option<&ExternalSigner>,
+    ) {
+        self.poll(spawner);
+
+        ui.add_enabled_ui(!self.busy(), |ui| {
+            self.show_import(ui);
+            ui.separator();
+            self.show_actions(ui, spawner, wallet, signer);
+        });
+        if self.device_sign.is_waiting() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Confirm the transaction on the device");
+            });
+        }
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+
+        if let Some(psbt) = &self.psbt {
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| show_psbt(ui, psbt, self.network));
+        }
+    }
+
+    fn show_import(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Load PSBT");
+        ui.horizontal(|ui| {
+            if ui.button("Open file...").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("PSBT", &["psbt", "txt"])
+                    .pick_file()
+                {
+                    let result = std::fs::read(&path)
+                        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+                        .and_then(|data| decode_psbt(&data));
+                    self.load(result);
+                }
+            }
+            ui.add(
+                egui::TextEdit::singleline(&mut self.import_text)
+                    .hint_text("Paste a base64 PSBT")
+                    .desired_width(400.0)
+                    .font(egui::TextStyle::Monospace),
+            );
+            if ui.button("Load").clicked() {
+                let result = decode_psbt(self.import_text.as_bytes());
+                if result.is_ok() {
+                    self.import_text.clear();
+                }
+                self.load(result);
+            }
+        });
+    }
+
+    fn load(&mut self, result: Result<Psbt, String>) {
+        match result {
+            Ok(psbt) => {
+                self.psbt = Some(psbt);
+                self.status = None;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    fn show_actions(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        wallet: Option<&str>,
+        signer: Option<&ExternalSigner>,
+    ) {
+        let Some(psbt) = &self.psbt else {
+            return;
+        };
+        let finalized = psbt
+            .inputs
+            .iter()
+            .all(|input| input_status(input).finalized);
+
+        let mut fill = None;
+        let mut device_sign = false;
+        let mut combine = false;
+        let mut broadcast = false;
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(wallet.is_some(), |ui| {
+                if ui
+                    .button("Sign")
+                    .on_hover_text("Add signatures from the open wallet")
+                    .clicked()
+                {
+                    fill = Some(true);
+                }
+                if ui
+                    .button("Update")
+                    .on_hover_text("Add the open wallet's input details without signing")
+                    .clicked()
+                {
+                    fill = Some(false);
+                }
+            });
+            if let Some(signer) = signer {
+                if ui
+                    .button("Sign with device")
+                    .on_hover_text(format!(
+                        "Sign with {} ({})",
+                        signer.name, signer.fingerprint
+                    ))
+                    .clicked()
+                {
+                    device_sign = true;
+                }
+            }
+            if ui.button("Combine with files...").clicked() {
+                combine = true;
+            }
+            if ui
+                .add_enabled(finalized, egui::Button::new("Broadcast"))
+                .clicked()
+            {
+                broadcast = true;
+            }
+            ui.separator();
+            if ui.button("Copy").clicked() {
+                let text = psbt.to_string();
+                ui.output_mut(|o| o.copied_text = text);
+            }
+            if ui.button("Save...").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("PSBT", &["psbt"])
+                    .set_file_name("transaction.psbt")
+                    .save_file()
+                {
+                    match std::fs::write(&path, psbt.serialize()) {
+                        Ok(()) => self.status = Some(format!("Saved to {}", path.display())),
+                        Err(e) => self.error = Some(format!("Failed to save PSBT: {}", e)),
+                    }
+                }
+            }
+        });
+
+        if let (Some(sign), Some(wallet)) = (fill, wallet) {
+            self.error = None;
+            let wallet = wallet.to_string();
+            let psbt = psbt.clone();
+            self.fill = Pending::spawn(spawner, |send| Task::FillPsbt(wallet, psbt, sign, send));
+        } else if let (true, Some(signer)) = (device_sign, signer) {
+            self.error = None;
+            let signer = signer.clone();
+            let psbt = psbt.clone();
+            self.device_sign =
+                Pending::spawn(spawner, |send| Task::SignPsbtWithSigner(signer, psbt, send));
+        } else if combine {
+            self.combine_files();
+        } else if broadcast {
+            self.error = None;
+            match extract_transaction(psbt) {
+                Ok(tx) => {
+                    self.broadcast =
+                        Pending::spawn(spawner, |send| Task::BroadcastTransaction(tx, send));
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+    }
+
+    fn combine_files(&mut self) {
+        let Some(paths) = rfd::FileDialog::new()
+            .add_filter("PSBT", &["psbt", "txt"])
+            .pick_files()
+        else {
+            return;
+        };
+        let Some(psbt) = self.psbt.clone() else {
+            return;
+        };
+        let mut others = Vec::new();
+        for path in paths {
+            let result = std::fs::read(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+                .and_then(|data| decode_psbt(&data));
+            match result {
+                Ok(other) => others.push(other),
+                Err(e) => {
+                    self.error = Some(e);
+                    return;
+                }
+            }
+        }
+        let count = others.len();
+        match combine_psbts(psbt, others) {
+            Ok(combined) => {
+                self.psbt = Some(combined);
+                self.status = Some(format!("Combined {} PSBTs", count + 1));
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+}
+
+fn show_psbt(ui: &mut egui::Ui, psbt: &Psbt, network: Option<Network>) {
+    ui.label(format!("Transaction {}", psbt.unsigned_tx.compute_txid()));
+
+    ui.strong("Inputs");
+    egui::Grid::new("psbt_inputs")
+        .striped(true)
+        .num_columns(3)
+        .show(ui, |ui| {
+            for (i, (txin, input)) in psbt.unsigned_tx.input.iter().zip(&psbt.inputs).enumerate() {
+                ui.monospace(txin.previous_output.to_string());
+                match spent_output(psbt, i) {
+                    Some(output) => ui.label(output.value.display_dynamic().to_string()),
+                    None => ui.label("Unknown amount"),
+                };
+                let status = input_status(input);
+                if status.finalized {
+                    ui.label("Finalized");
+                } else {
+                    match status.required {
+                        Some(required) => {
+                            ui.label(format!("{} of {} signatures", status.signatures, required))
+                        }
+                        None => ui.label(format!("{} signatures", status.signatures)),
+                    };
+                }
+                ui.end_row();
+            }
+        });
+
+    ui.separator();
+    ui.strong("Outputs");
+    egui::Grid::new("psbt_outputs")
+        .striped(true)
+        .num_columns(2)
+        .show(ui, |ui| {
+            for output in &psbt.unsigned_tx.output {
+                let destination = network
+                    .and_then(|network| Address::from_script(&output.script_pubkey, network).ok())
+                    .map(|address| address.to_string())
+                    .unwrap_or_else(|| output.script_pubkey.to_hex_string());
+                ui.monospace(destination);
+                ui.label(output.value.display_dynamic().to_string());
+                ui.end_row();
+            }
+        });
+
+    ui.separator();
+    match psbt.fee() {
+        Ok(fee) => ui.label(format!("Fee: {}", fee.display_dynamic())),
+        Err(_) => ui.label("Fee: unknown, some inputs are missing their previous outputs"),
+    };
+}
@@ -0,0 +1,316 @@
+use super::Pending;
+use crate::address::OutputType;
+use crate::receive::ReceiveRequest;
+use crate::spawner::LocalSpawner;
+use crate::store::WalletState;
+use crate::tasks::Task;
+use bitcoin::{Amount, Denomination, Network};
+use eframe::egui;
+use qrcode::QrCode;
+
+// Blank modules around the code, as required by the QR spec
+const QR_QUIET_ZONE: usize = 4;
+const QR_SIZE: f32 = 240.0;
+
+pub struct ReceivePanel {
+    // Wallet the request history below belongs to
+    wallet: Option<String>,
+    network: Option<Network>,
+    network_pending: Pending<Network>,
+    output_type: OutputType,
+    label: String,
+    amount: String,
+    message: String,
+    requests: Option<Vec<ReceiveRequest>>,
+    requests_pending: Pending<Vec<ReceiveRequest>>,
+    action: Pending<ReceiveRequest>,
+    selected: Option<ReceiveRequest>,
+    new_label: String,
+    error: Option<String>,
+}
+
+impl Default for ReceivePanel {
+    fn default() -> Self {
+        Self {
+            wallet: None,
+            network: None,
+            network_pending: Pending::default(),
+            output_type: OutputType::Bech32,
+            label: String::new(),
+            amount: String::new(),
+            message: String::new(),
+            requests: None,
+            requests_pending: Pending::default(),
+            action: Pending::default(),
+            selected: None,
+            new_label: String::new(),
+            error: None,
+        }
+    }
+}
+
+impl ReceivePanel {
+    fn poll(&mut self, spawner: &LocalSpawner, wallet: &str) {
+        if self.wallet.as_deref() != Some(wallet) {
+            self.wallet = Some(wallet.to_string());
+            self.requests = None;
+            self.selected = None;
+            self.error = None;
+        }
+
+        match self.network_pending.poll() {
+            Some(Ok(network)) => self.network = Some(network),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.requests_pending.poll() {
+            Some(Ok(requests)) => self.requests = Some(requests),
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.requests = Some(Vec::new());
+            }
+            None => {}
+        }
+
+        match self.action.poll() {
+            Some(Ok(request)) => {
+                self.new_label = request.label.clone();
+                self.selected = Some(request);
+                self.requests = None;
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        if self.network.is_none() && !self.network_pending.is_waiting() && self.error.is_none() {
+            self.network_pending = Pending::spawn(spawner, Task::GetNetwork);
+        }
+        if self.requests.is_none() && !self.requests_pending.is_waiting() {
+            let wallet = wallet.to_string();
+            self.requests_pending =
+                Pending::spawn(spawner, |send| Task::ListReceiveRequests(wallet, send));
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        wallet: Option<&str>,
+        state: Option<&WalletState>,
+    ) {
+        let Some(wallet) = wallet else {
+            ui.label("Open a wallet to receive payments");
+            return;
+        };
+        self.poll(spawner, wallet);
+
+        ui.heading("Request payment");
+        // E.g. a blank wallet before any descriptors are imported
+        let can_get_addresses = state.map_or(true, |state| state.can_get_addresses);
+        if !can_get_addresses {
+            ui.label("This wallet has no keys or descriptors to derive new addresses from");
+        }
+        ui.add_enabled_ui(can_get_addresses && !self.action.is_waiting(), |ui| {
+            self.show_form(ui, spawner, wallet);
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+
+        if self.selected.is_some() {
+            ui.separator();
+            self.show_request(ui, spawner, wallet);
+        }
+
+        ui.separator();
+        ui.heading("Requested payments");
+        self.show_history(ui);
+    }
+
+    fn show_form(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, wallet: &str) {
+        egui::Grid::new("receive_form")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Address type");
+                egui::ComboBox::from_id_source("output_type")
+                    .selected_text(self.output_type.to_string())
+                    .show_ui(ui, |ui| {
+                        for output_type in OutputType::ALL {
+                            ui.selectable_value(
+                                &mut self.output_type,
+                                output_type,
+                                output_type.to_string(),
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Label");
+                ui.text_edit_singleline(&mut self.label);
+                ui.end_row();
+
+                ui.label("Amount (BTC)");
+                ui.add(egui::TextEdit::singleline(&mut self.amount).hint_text("Optional"));
+                ui.end_row();
+
+                ui.label("Message");
+                ui.add(egui::TextEdit::singleline(&mut self.message).hint_text("Optional"));
+                ui.end_row();
+            });
+
+        if ui.button("Create request").clicked() {
+            let amount = match self.amount.trim() {
+                "" => None,
+                amount => match Amount::from_str_in(amount, Denomination::Bitcoin) {
+                    Ok(amount) => Some(amount),
+                    Err(e) => {
+                        self.error = Some(format!("Invalid amount: {}", e));
+                        return;
+                    }
+                },
+            };
+            self.error = None;
+            let wallet = wallet.to_string();
+            let output_type = self.output_type;
+            let label = std::mem::take(&mut self.label);
+            let message = std::mem::take(&mut self.message);
+            self.amount.clear();
+            self.action = Pending::spawn(spawner, |send| {
+                Task::CreateReceiveRequest(wallet, output_type, label, amount, message, send)
+            });
+        }
+    }
+
+    // The selected request's address, URI and QR code
+    fn show_request(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, wallet: &str) {
+        let Some(request) = self.selected.clone() else {
+            return;
+        };
+        let uri = self.network.and_then(|network| request.uri(network));
+
+        ui.horizontal(|ui| {
+            if let Some(uri) = &uri {
+                show_qr_code(ui, uri);
+            }
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Address:");
+                    ui.monospace(&request.address);
+                    if ui.button("Copy").clicked() {
+                        ui.output_mut(|o| o.copied_text = request.address.clone());
+                    }
+                });
+                if let Some(uri) = &uri {
+                    ui.horizontal(|ui| {
+                        ui.label("URI:");
+                        ui.monospace(uri);
+                        if ui.button("Copy").clicked() {
+                            ui.output_mut(|o| o.copied_text = uri.clone());
+                        }
+                    });
+                }
+                if let Some(amount) = request.amount() {
+                    ui.label(format!("Amount: {}", amount.display_dynamic()));
+                }
+                if !request.message.is_empty() {
+                    ui.label(format!("Message: {}", request.message));
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Label:");
+                    ui.text_edit_singleline(&mut self.new_label);
+                    let changed = self.new_label != request.label;
+                    if ui
+                        .add_enabled(
+                            changed && !self.action.is_waiting(),
+                            egui::Button::new("Save label"),
+                        )
+                        .clicked()
+                    {
+                        self.error = None;
+                        let wallet = wallet.to_string();
+                        let request = request.clone();
+                        let label = self.new_label.clone();
+                        self.action = Pending::spawn(spawner, |send| {
+                            Task::RelabelReceiveRequest(wallet, request, label, send)
+                        });
+                    }
+                });
+            });
+        });
+    }
+
+    fn show_history(&mut self, ui: &mut egui::Ui) {
+        let Some(requests) = &self.requests else {
+            ui.spinner();
+            return;
+        };
+        if requests.is_empty() {
+            ui.label("No payment requests yet");
+            return;
+        }
+
+        let mut clicked = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("receive_requests")
+                .striped(true)
+                .num_columns(4)
+                .show(ui, |ui| {
+                    ui.strong("Label");
+                    ui.strong("Address");
+                    ui.strong("Amount");
+                    ui.strong("Message");
+                    ui.end_row();
+                    for request in requests {
+                        let selected = self.selected.as_ref().map(|r| &r.id) == Some(&request.id);
+                        let label = if request.label.is_empty() {
+                            "(no label)"
+                        } else {
+                            &request.label
+                        };
+                        if ui.selectable_label(selected, label).clicked() {
+                            clicked = Some(request.clone());
+                        }
+                        ui.monospace(&request.address);
+                        match request.amount() {
+                            Some(amount) => ui.label(amount.display_dynamic().to_string()),
+                            None => ui.label("-"),
+                        };
+                        ui.label(&request.message);
+                        ui.end_row();
+                    }
+                });
+        });
+
+        if let Some(request) = clicked {
+            self.new_label = request.label.clone();
+            self.selected = Some(request);
+        }
+    }
+}
+
+fn show_qr_code(ui: &mut egui::Ui, data: &str) {
+    let Ok(code) = QrCode::new(data.as_bytes()) else {
+        ui.label("URI too long for a QR code");
+        return;
+    };
+    let width = code.width();
+    let colors = code.to_colors();
+    let modules = width + 2 * QR_QUIET_ZONE;
+    let scale = QR_SIZE / modules as f32;
+
+    let (response, painter) = ui.allocate_painter(egui::Vec2::splat(QR_SIZE), egui::Sense::hover());
+    let origin = response.rect.min;
+    painter.rect_filled(response.rect, 0.0, egui::Color32::WHITE);
+    for (i, color) in colors.iter().enumerate() {
+        if *color != qrcode::Color::Dark {
+            continue;
+        }
+        let x = (i % width + QR_QUIET_ZONE) as f32 * scale;
+        let y = (i / width + QR_QUIET_ZONE) as f32 * scale;
+        let rect = egui::Rect::from_min_size(origin + egui::vec2(x, y), egui::Vec2::splat(scale));
+        painter.rect_filled(rect, 0.0, egui::Color32::BLACK);
+    }
+}
@@ -0,0 +1,152 @@
+use super::{AppEvent, Pending};
+use crate::node::Progress;
+use crate::rescan::RescanRange;
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use eframe::egui;
+use tokio::sync::mpsc;
+
+// Rescan the active wallet, following the wallet's ShowProgress notifications
+#[derive(Default)]
+pub struct RescanPanel {
+    wallet: Option<String>,
+    subscribe: Pending<()>,
+    start_height: u32,
+    // Wallet the running rescan belongs to, which may no longer be the active one
+    rescanning: Option<String>,
+    rescan: Pending<RescanRange>,
+    abort: Pending<bool>,
+    progress: Option<Progress>,
+    message: Option<String>,
+    error: Option<String>,
+}
+
+impl RescanPanel {
+    pub fn set_progress(&mut self, wallet: &str, progress: Progress) {
+        if self.rescanning.as_deref() != Some(wallet) {
+            return;
+        }
+        self.progress = if progress.is_finished() {
+            None
+        } else {
+            Some(progress)
+        };
+    }
+
+    fn poll(
+        &mut self,
+        spawner: &LocalSpawner,
+        wallet: &str,
+        events: &mpsc::UnboundedSender<AppEvent>,
+    ) {
+        if self.wallet.as_deref() != Some(wallet) {
+            self.wallet = Some(wallet.to_string());
+            self.start_height = 0;
+            self.message = None;
+            self.error = None;
+            let wallet = wallet.to_string();
+            let events = events.clone();
+            self.subscribe = Pending::spawn(spawner, |send| {
+                Task::SubscribeWalletProgress(wallet, events, send)
+            });
+        }
+
+        if let Some(Err(e)) = self.subscribe.poll() {
+            self.error = Some(e.to_string());
+        }
+
+        match self.rescan.poll() {
+            Some(Ok(range)) => {
+                self.message = Some(match range.stop_height {
+                    Some(stop_height) => {
+                        format!("Rescanned blocks {} to {}", range.start_height, stop_height)
+                    }
+                    None => format!(
+                        "Rescan stopped early, after starting at block {}",
+                        range.start_height
+                    ),
+                });
+                self.rescanning = None;
+                self.progress = None;
+            }
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.rescanning = None;
+                self.progress = None;
+            }
+            None => {}
+        }
+
+        match self.abort.poll() {
+            Some(Ok(false)) => self.error = Some("No rescan is running".to_string()),
+            Some(Ok(true)) => {}
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        wallet: Option<&str>,
+        events: &mpsc::UnboundedSender<AppEvent>,
+        tip_height: Option<i32>,
+    ) {
+        let Some(wallet) = wallet else {
+            return;
+        };
+        self.poll(spawner, wallet, events);
+
+        ui.separator();
+        ui.heading("Rescan");
+        ui.label("Search the chain again for transactions belonging to this wallet.");
+
+        if let Some(rescanning) = self.rescanning.clone() {
+            if rescanning != wallet {
+                ui.label(format!("Rescanning wallet {}", rescanning));
+            }
+            ui.horizontal(|ui| {
+                let (title, fraction) = match &self.progress {
+                    Some(progress) => (progress.title.clone(), progress.percent as f32 / 100.0),
+                    None => ("Starting rescan".to_string(), 0.0),
+                };
+                ui.add(
+                    egui::ProgressBar::new(fraction)
+                        .text(format!("{} {:.0}%", title, fraction * 100.0))
+                        .desired_width(360.0),
+                );
+                let cancel = ui
+                    .add_enabled(!self.abort.is_waiting(), egui::Button::new("Cancel"))
+                    .clicked();
+                if cancel {
+                    self.abort =
+                        Pending::spawn(spawner, |send| Task::AbortRescan(rescanning, send));
+                }
+            });
+        } else {
+            ui.horizontal(|ui| {
+                ui.label("From block");
+                let max_height = tip_height.unwrap_or(0).max(0) as u32;
+                ui.add(egui::DragValue::new(&mut self.start_height).range(0..=max_height));
+                if ui.button("Rescan").clicked() {
+                    self.message = None;
+                    self.error = None;
+                    self.rescanning = Some(wallet.to_string());
+                    let wallet = wallet.to_string();
+                    let start_height = Some(self.start_height);
+                    self.rescan = Pending::spawn(spawner, |send| {
+                        Task::RescanWallet(wallet, start_height, send)
+                    });
+                }
+            });
+        }
+
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use super::coins::CoinsPanel;
+use super::fees::FeeSelector;
+use super::Pending;
+use crate::address::parse_address;
+use crate::fee::FeeRate;
+use crate::send::{CoinControl, FeeChoice, Recipient, TxProposal};
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use bitcoin::psbt::Psbt;
+use bitcoin::{Address, Amount, Denomination, Network, OutPoint, Txid};
+use eframe::egui;
+
+#[derive(Default)]
+struct RecipientRow {
+    address: String,
+    amount: String,
+    subtract_fee: bool,
+}
+
+pub struct SendPanel {
+    wallet: Option<String>,
+    network: Option<Network>,
+    network_pending: Pending<Network>,
+    recipients: Vec<RecipientRow>,
+    custom_fee: bool,
+    // sat/vB
+    fee_rate: f64,
+    signal_rbf: bool,
+    proposal: Pending<TxProposal>,
+    review: Option<TxProposal>,
+    psbt: Pending<Psbt>,
+    // Handed over to the PSBT page
+    created_psbt: Option<Psbt>,
+    commit: Pending<Txid>,
+    sent: Option<Txid>,
+    error: Option<String>,
+}
+
+impl Default for SendPanel {
+    fn default() -> Self {
+        Self {
+            wallet: None,
+            network: None,
+            network_pending: Pending::default(),
+            recipients: vec![RecipientRow::default()],
+            custom_fee: false,
+            fee_rate: 1.0,
+            signal_rbf: true,
+            proposal: Pending::default(),
+            review: None,
+            psbt: Pending::default(),
+            created_psbt: None,
+            commit: Pending::default(),
+            sent: None,
+            error: None,
+        }
+    }
+}
+
+impl SendPanel {
+    fn poll(&mut self, spawner: &LocalSpawner, wallet: &str, coins: &mut CoinsPanel) {
+        if self.wallet.as_deref() != Some(wallet) {
+            self.wallet = Some(wallet.to_string());
+            self.review = None;
+            self.sent = None;
+            self.error = None;
+        }
+
+        match self.network_pending.poll() {
+            Some(Ok(network)) => self.network = Some(network),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.proposal.poll() {
+            Some(Ok(proposal)) => self.review = Some(proposal),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.psbt.poll() {
+            Some(Ok(psbt)) => {
+                self.created_psbt = Some(psbt);
+                self.recipients = vec![RecipientRow::default()];
+                coins.clear_selection();
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.commit.poll() {
+            Some(Ok(txid)) => {
+                self.sent = Some(txid);
+                self.recipients = vec![RecipientRow::default()];
+                coins.clear_selection();
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        if self.network.is_none() && !self.network_pending.is_waiting() && self.error.is_none() {
+            self.network_pending = Pending::spawn(spawner, Task::GetNetwork);
+        }
+    }
+
+    fn busy(&self) -> bool {
+        self.proposal.is_waiting()
+            || self.psbt.is_waiting()
+            || self.commit.is_waiting()
+            || self.review.is_some()
+    }
+
+    // Fill in an address picked elsewhere, e.g. from the address book
+    pub fn pay_to(&mut self, address: String) {
+        self.sent = None;
+        match self
+            .recipients
+            .iter_mut()
+            .find(|row| row.address.is_empty())
+        {
+            Some(row) => row.address = address,
+            None => self.recipients.push(RecipientRow {
+                address,
+                ..RecipientRow::default()
+            }),
+        }
+    }
+
+    // An unsigned PSBT created since the last call, to be opened on the PSBT page
+    pub fn take_psbt(&mut self) -> Option<Psbt> {
+        self.created_psbt.take()
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        wallet: Option<&str>,
+        fees: &mut FeeSelector,
+        coins: &mut CoinsPanel,
+    ) {
+        let Some(wallet) = wallet else {
+            ui.label("Open a wallet to send payments");
+            return;
+        };
+        self.poll(spawner, wallet, coins);
+
+        ui.add_enabled_ui(!self.busy(), |ui| {
+            self.show_recipients(ui);
+            ui.separator();
+            show_inputs(ui, wallet, coins);
+            ui.separator();
+            self.show_fee(ui, fees);
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Review transaction").clicked() {
+                    let inputs = coins.selection(wallet);
+                    if let Some((recipients, coin_control)) = self.build(fees, inputs) {
+                        let wallet = wallet.to_string();
+                        self.proposal = Pending::spawn(spawner, |send| {
+                            Task::CreateTransaction(wallet, recipients, coin_control, send)
+                        });
+                    }
+                }
+                if ui
+                    .button("Create unsigned PSBT")
+                    .on_hover_text("Fund the transaction without signing it, for signing elsewhere")
+                    .clicked()
+                {
+                    let inputs = coins.selection(wallet);
+                    if let Some((recipients, coin_control)) = self.build(fees, inputs) {
+                        let wallet = wallet.to_string();
+                        self.psbt = Pending::spawn(spawner, |send| {
+                            Task::CreatePsbt(wallet, recipients, coin_control, send)
+                        });
+                    }
+                }
+            });
+        });
+
+        if let Some(txid) = &self.sent {
+            ui.label(format!("Sent transaction {}", txid));
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+        self.show_review(ui.ctx(), spawner, wallet);
+    }
+
+    fn show_recipients(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Pay to");
+        let mut remove = None;
+        let removable = self.recipients.len() > 1;
+        egui::Grid::new("recipients").num_columns(4).show(ui, |ui| {
+            ui.strong("Address");
+            ui.strong("Amount (BTC)");
+            ui.strong("Subtract fee");
+            ui.end_row();
+            for (i, row) in self.recipients.iter_mut().enumerate() {
+                ui.add(
+                    egui::TextEdit::singleline(&mut row.address)
+                        .desired_width(400.0)
+                        .font(egui::TextStyle::Monospace),
+                );
+                ui.text_edit_singleline(&mut row.amount);
+                ui.checkbox(&mut row.subtract_fee, "");
+                if removable && ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.recipients.remove(i);
+        }
+        if ui.button("Add recipient").clicked() {
+            self.recipients.push(RecipientRow::default());
+        }
+    }
+
+    fn show_fee(&mut self, ui: &mut egui::Ui, fees: &mut FeeSelector) {
+        ui.heading("Fee");
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.custom_fee, false, "Estimate");
+            ui.radio_value(&mut self.custom_fee, true, "Custom fee rate");
+            if self.custom_fee {
+                let min = fees.relay_min_fee().map_or(0.0, |rate| rate.sat_per_vb());
+                ui.add(
+                    egui::DragValue::new(&mut self.fee_rate)
+                        .range(min..=10_000.0)
+                        .speed(0.1)
+                        .suffix(" sat/vB"),
+                );
+            }
+        });
+        if !self.custom_fee {
+            fees.show(ui);
+        }
+        ui.checkbox(
+            &mut self.signal_rbf,
+            "Allow the fee to be bumped later (replace-by-fee)",
+        );
+    }
+
+    // Validate the form locally before asking the wallet to build the transaction
+    fn build(
+        &mut self,
+        fees: &FeeSelector,
+        inputs: Vec<OutPoint>,
+    ) -> Option<(Vec<Recipient>, CoinControl)> {
+        self.sent = None;
+        let Some(network) = self.network else {
+            self.error = Some("Waiting for the node's network".to_string());
+            return None;
+        };
+        let recipients = match parse_recipients(&self.recipients, network) {
+            Ok(recipients) => recipients,
+            Err(e) => {
+                self.error = Some(e);
+                return None;
+            }
+        };
+
+        let fee = if self.custom_fee {
+            // The node would not relay a transaction paying less
+            let Some(min) = fees.relay_min_fee() else {
+                self.error = Some("Waiting for the node's minimum relay fee".to_string());
+                return None;
+            };
+            let rate = FeeRate::from_sat_per_vb(self.fee_rate);
+            if rate < min {
+                self.error = Some(format!("The fee rate must be at least {}", min));
+                return None;
+            }
+            FeeChoice::Rate(rate)
+        } else {
+            fees.fee_choice()
+        };
+        let coin_control = CoinControl {
+            fee,
+            signal_rbf: self.signal_rbf,
+            inputs,
+        };
+        self.error = None;
+        Some((recipients, coin_control))
+    }
+
+    fn show_review(&mut self, ctx: &egui::Context, spawner: &LocalSpawner, wallet: &str) {
+        let Some(proposal) = &self.review else {
+            return;
+        };
+        let network = self.network;
+
+        let mut open = true;
+        let mut confirmed = false;
+        let mut cancelled = false;
+        egui::Window::new("Confirm send")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.strong("Inputs");
+                for input in &proposal.tx.input {
+                    ui.monospace(input.previous_output.to_string());
+                }
+
+                ui.separator();
+                ui.strong("Outputs");
+                egui::Grid::new("review_outputs")
+                    .striped(true)
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (i, output) in proposal.tx.output.iter().enumerate() {
+                            let destination = network
+                                .and_then(|network| {
+                                    Address::from_script(&output.script_pubkey, network).ok()
+                                })
+                                .map(|address| address.to_string())
+                                .unwrap_or_else(|| output.script_pubkey.to_hex_string());
+                            if proposal.change_pos == Some(i) {
+                                ui.monospace(format!("{} (change)", destination));
+                            } else {
+                                ui.monospace(destination);
+                            }
+                            ui.label(output.value.display_dynamic().to_string());
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.label(format!("Sending: {}", proposal.sent().display_dynamic()));
+                if let Some(change) = proposal.change() {
+                    ui.label(format!("Change: {}", change.value.display_dynamic()));
+                }
+                ui.label(format!(
+                    "Fee: {} ({})",
+                    proposal.fee.display_dynamic(),
+                    proposal.fee_rate()
+                ));
+                ui.strong(format!(
+                    "Total: {}",
+                    (proposal.sent() + proposal.fee).display_dynamic()
+                ));
+                if proposal.tx.is_explicitly_rbf() {
+                    ui.label("Replaceable (RBF)");
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Send").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+
+        if confirmed {
+            if let Some(proposal) = self.review.take() {
+                let wallet = wallet.to_string();
+                self.commit = Pending::spawn(spawner, |send| {
+                    Task::CommitTransaction(wallet, proposal.tx, send)
+                });
+            }
+        } else if cancelled || !open {
+            self.review = None;
+        }
+    }
+}
+
+fn show_inputs(ui: &mut egui::Ui, wallet: &str, coins: &mut CoinsPanel) {
+    ui.heading("Inputs");
+    let selection = coins.selection(wallet);
+    if selection.is_empty() {
+        ui.label("Chosen automatically by the wallet. Select coins on the Coins page to pick them by hand.");
+        return;
+    }
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "Spending {} selected coins ({})",
+            selection.len(),
+            coins.selected_total().display_dynamic()
+        ));
+        if ui.button("Clear").clicked() {
+            coins.clear_selection();
+        }
+    });
+}
+
+fn parse_recipients(rows: &[RecipientRow], network: Network) -> Result<Vec<Recipient>, String> {
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let address = parse_address(&row.address, network)
+                .map_err(|e| format!("Recipient {}: {}", i + 1, e))?;
+            let amount = Amount::from_str_in(row.amount.trim(), Denomination::Bitcoin)
+                .map_err(|e| format!("Recipient {}: invalid amount: {}", i + 1, e))?;
+            if amount == Amount::ZERO {
+                return Err(format!("Recipient {}: amount must be positive", i + 1));
+            }
+            Ok(Recipient {
+                address,
+                amount,
+                subtract_fee: row.subtract_fee,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,277 @@
+use super::{AppEvent, Pending};
+use crate::backup::BackupSchedule;
+use crate::logging::LogCategories;
+use crate::settings::Settings;
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use eframe::egui;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+pub struct SettingsPanel {
+    categories: Option<LogCategories>,
+    pending: Pending<LogCategories>,
+    // A change made here, the only kind saved for future spawns
+    update: Pending<LogCategories>,
+    // The category that change toggled, and whether it is now enabled
+    toggled: Option<(String, bool)>,
+    error: Option<String>,
+    // Backup schedule form, applied with "Save schedule"
+    schedule_enabled: bool,
+    schedule_directory: Option<PathBuf>,
+    every_transactions: u32,
+    daily: bool,
+    keep: usize,
+    schedule_pending: Pending<()>,
+    backup_now: Pending<()>,
+    backup_message: Option<String>,
+    backup_error: Option<String>,
+}
+
+impl Default for SettingsPanel {
+    fn default() -> Self {
+        let schedule = Settings::load().backup_schedule;
+        Self {
+            schedule_enabled: schedule.is_some(),
+            schedule_directory: schedule.as_ref().map(|s| s.directory.clone()),
+            every_transactions: schedule.as_ref().map_or(10, |s| s.every_transactions),
+            daily: schedule.as_ref().map_or(true, |s| s.daily),
+            keep: schedule.as_ref().map_or(7, |s| s.keep),
+            categories: None,
+            pending: Pending::default(),
+            update: Pending::default(),
+            toggled: None,
+            error: None,
+            schedule_pending: Pending::default(),
+            backup_now: Pending::default(),
+            backup_message: None,
+            backup_error: None,
+        }
+    }
+}
+
+impl SettingsPanel {
+    fn poll(&mut self) {
+        // The node's own selection is only shown, it may come from its -debug flags
+        match self.pending.poll() {
+            Some(Ok(categories)) => self.categories = Some(categories),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.update.poll() {
+            Some(Ok(categories)) => {
+                // Remember the user's change for the next spawned node, leaving
+                // the rest of the saved selection as it was
+                if let Some((name, enabled)) = self.toggled.take() {
+                    let saved = Settings::update(|settings| {
+                        settings
+                            .debug_categories
+                            .retain(|category| *category != name);
+                        if enabled {
+                            settings.debug_categories.push(name);
+                        }
+                    });
+                    if let Err(e) = saved {
+                        self.error = Some(format!("Failed to save settings: {}", e));
+                    }
+                }
+                self.categories = Some(categories);
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.schedule_pending.poll() {
+            Some(Ok(())) => self.backup_message = Some("Backup schedule saved".to_string()),
+            Some(Err(e)) => self.backup_error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.backup_now.poll() {
+            Some(Ok(())) => {
+                self.backup_message = Some("Backup written and verified".to_string());
+            }
+            Some(Err(e)) => self.backup_error = Some(e.to_string()),
+            None => {}
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        wallet: Option<&str>,
+        ready: bool,
+        events: &mpsc::UnboundedSender<AppEvent>,
+    ) {
+        self.poll();
+        self.show_backups(ui, spawner, wallet, ready, events);
+        ui.separator();
+        self.show_logging(ui, spawner);
+    }
+
+    fn show_backups(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        wallet: Option<&str>,
+        ready: bool,
+        events: &mpsc::UnboundedSender<AppEvent>,
+    ) {
+        ui.heading("Wallet backups");
+        ui.label("Each backup is verified by restoring it under a temporary name.");
+        if !ready {
+            ui.label("Wallet actions are disabled until the node is ready.");
+        }
+
+        let busy = self.backup_now.is_waiting() || self.schedule_pending.is_waiting();
+        ui.add_enabled_ui(ready && !busy, |ui| {
+            let label = match wallet {
+                Some(wallet) => format!("Back up {} now...", wallet),
+                None => "Back up now...".to_string(),
+            };
+            let clicked = ui
+                .add_enabled(wallet.is_some(), egui::Button::new(label))
+                .clicked();
+            if let (true, Some(wallet)) = (clicked, wallet) {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Wallet backup", &["bak"])
+                    .set_file_name(format!("{}.bak", wallet))
+                    .save_file()
+                {
+                    self.backup_message = None;
+                    self.backup_error = None;
+                    let wallet = wallet.to_string();
+                    self.backup_now =
+                        Pending::spawn(spawner, |send| Task::BackupWallet(wallet, path, send));
+                }
+            }
+
+            ui.checkbox(
+                &mut self.schedule_enabled,
+                "Back up loaded wallets automatically",
+            );
+            ui.add_enabled_ui(self.schedule_enabled, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Choose folder...").clicked() {
+                        if let Some(directory) = rfd::FileDialog::new().pick_folder() {
+                            self.schedule_directory = Some(directory);
+                        }
+                    }
+                    match &self.schedule_directory {
+                        Some(directory) => ui.monospace(directory.display().to_string()),
+                        None => ui.label("No folder chosen"),
+                    };
+                });
+                ui.horizontal(|ui| {
+                    ui.label("After every");
+                    ui.add(egui::DragValue::new(&mut self.every_transactions).range(0..=1000));
+                    ui.label("new transactions (0 to disable)");
+                });
+                ui.checkbox(&mut self.daily, "At least once a day");
+                ui.horizontal(|ui| {
+                    ui.label("Keep the newest");
+                    ui.add(egui::DragValue::new(&mut self.keep).range(1..=1000));
+                    ui.label("backups of each wallet");
+                });
+            });
+            if ui.button("Save schedule").clicked() {
+                self.save_schedule(spawner, events);
+            }
+        });
+
+        if let Some(message) = &self.backup_message {
+            ui.label(message);
+        }
+        if let Some(error) = &self.backup_error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+    }
+
+    fn save_schedule(&mut self, spawner: &LocalSpawner, events: &mpsc::UnboundedSender<AppEvent>) {
+        self.backup_message = None;
+        self.backup_error = None;
+        let schedule = if self.schedule_enabled {
+            let Some(directory) = self.schedule_directory.clone() else {
+                self.backup_error = Some("Choose a folder for the backups".to_string());
+                return;
+            };
+            Some(BackupSchedule {
+                directory,
+                every_transactions: self.every_transactions,
+                daily: self.daily,
+                keep: self.keep,
+            })
+        } else {
+            None
+        };
+
+        let saved = schedule.clone();
+        if let Err(e) = Settings::update(|settings| settings.backup_schedule = saved) {
+            self.backup_error = Some(format!("Failed to save settings: {}", e));
+            return;
+        }
+        let events = events.clone();
+        self.schedule_pending = Pending::spawn(spawner, |send| {
+            Task::SetBackupSchedule(schedule, events, send)
+        });
+    }
+
+    fn show_logging(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner) {
+        if self.categories.is_none() && !self.pending.is_waiting() && self.error.is_none() {
+            self.pending = Pending::spawn(spawner, Task::GetLogCategories);
+        }
+
+        ui.heading("Debug logging");
+        ui.label("Changes apply to the connected node immediately and are used for future spawns.");
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+
+        let Some(categories) = &self.categories else {
+            ui.spinner();
+            return;
+        };
+
+        let mut toggled = None;
+        let busy = self.pending.is_waiting() || self.update.is_waiting();
+        ui.add_enabled_ui(!busy, |ui| {
+            egui::Grid::new("log_categories")
+                .num_columns(4)
+                .show(ui, |ui| {
+                    for (i, (name, &enabled)) in categories.categories.iter().enumerate() {
+                        let mut checked = enabled;
+                        if ui.checkbox(&mut checked, name).changed() {
+                            toggled = Some((name.clone(), checked));
+                        }
+                        if i % 4 == 3 {
+                            ui.end_row();
+                        }
+                    }
+                });
+        });
+        ui.label(format!("Category mask: {:#018x}", categories.mask));
+
+        if ui
+            .add_enabled(!busy, egui::Button::new("Refresh"))
+            .clicked()
+        {
+            self.error = None;
+            self.pending = Pending::spawn(spawner, Task::GetLogCategories);
+        }
+
+        if let Some((name, enabled)) = toggled {
+            self.error = None;
+            self.toggled = Some((name.clone(), enabled));
+            let (include, exclude) = if enabled {
+                (vec![name], vec![])
+            } else {
+                (vec![], vec![name])
+            };
+            self.update = Pending::spawn(spawner, |send| {
+                Task::SetLogCategories(include, exclude, send)
+            });
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use super::Pending;
+use crate::address::parse_address;
+use crate::settings::Settings;
+use crate::signer::ExternalSigner;
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use bitcoin::Network;
+use eframe::egui;
+
+// Hardware signers reached through an HWI-compatible command
+pub struct SignerPanel {
+    command: String,
+    signers: Option<Vec<ExternalSigner>>,
+    signers_pending: Pending<Vec<ExternalSigner>>,
+    selected: Option<ExternalSigner>,
+    network: Option<Network>,
+    network_pending: Pending<Network>,
+    // New wallet backed by the selected signer
+    wallet_name: String,
+    account: u32,
+    create: Pending<Vec<String>>,
+    created: Option<String>,
+    // Whether the active wallet was created for an external signer
+    wallet: Option<String>,
+    has_signer: Option<bool>,
+    has_signer_pending: Pending<bool>,
+    address: String,
+    display: Pending<Option<String>>,
+    // Address the device reported, next to the one that was asked for
+    displayed: Option<(String, Option<String>)>,
+    message: Option<String>,
+    error: Option<String>,
+}
+
+impl Default for SignerPanel {
+    fn default() -> Self {
+        Self {
+            command: Settings::load().signer_command.unwrap_or_default(),
+            signers: None,
+            signers_pending: Pending::default(),
+            selected: None,
+            network: None,
+            network_pending: Pending::default(),
+            wallet_name: String::new(),
+            account: 0,
+            create: Pending::default(),
+            created: None,
+            wallet: None,
+            has_signer: None,
+            has_signer_pending: Pending::default(),
+            address: String::new(),
+            display: Pending::default(),
+            displayed: None,
+            message: None,
+            error: None,
+        }
+    }
+}
+
+impl SignerPanel {
+    // The signer chosen for signing PSBTs and showing addresses
+    pub fn selected(&self) -> Option<&ExternalSigner> {
+        self.selected.as_ref()
+    }
+
+    // A wallet created for a signer, for the wallet page to switch to
+    pub fn take_created(&mut self) -> Option<String> {
+        self.created.take()
+    }
+
+    fn poll(&mut self, spawner: &LocalSpawner, wallet: Option<&str>) {
+        if self.wallet.as_deref() != wallet {
+            self.wallet = wallet.map(str::to_string);
+            self.has_signer = None;
+            self.displayed = None;
+            if let Some(wallet) = wallet {
+                let wallet = wallet.to_string();
+                self.has_signer_pending =
+                    Pending::spawn(spawner, |send| Task::HasExternalSigner(wallet, send));
+            }
+        }
+
+        match self.network_pending.poll() {
+            Some(Ok(network)) => self.network = Some(network),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.signers_pending.poll() {
+            Some(Ok(signers)) => {
+                // Keep the selection if the same device is still connected
+                self.selected = self
+                    .selected
+                    .take()
+                    .filter(|selected| signers.contains(selected))
+                    .or_else(|| signers.first().cloned());
+                self.signers = Some(signers);
+            }
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.signers = Some(Vec::new());
+            }
+            None => {}
+        }
+
+        match self.create.poll() {
+            Some(Ok(warnings)) => {
+                let name = std::mem::take(&mut self.wallet_name);
+                let mut message = format!("Created wallet {}", name);
+                for warning in warnings {
+                    message.push_str(&format!("\nWarning: {}", warning));
+                }
+                self.message = Some(message);
+                self.created = Some(name);
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.has_signer_pending.poll() {
+            Some(Ok(has_signer)) => self.has_signer = Some(has_signer),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        match self.display.poll() {
+            Some(Ok(shown)) => self.displayed = Some((self.address.trim().to_string(), shown)),
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        if self.network.is_none() && !self.network_pending.is_waiting() && self.error.is_none() {
+            self.network_pending = Pending::spawn(spawner, Task::GetNetwork);
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, wallet: Option<&str>) {
+        self.poll(spawner, wallet);
+
+        ui.heading("External signers");
+        ui.label(
+            "Hardware wallets are reached through HWI, or any program with the same \
+             command line interface.",
+        );
+        ui.horizontal(|ui| {
+            ui.label("Signer command");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.command)
+                    .hint_text("hwi")
+                    .desired_width(300.0)
+                    .font(egui::TextStyle::Monospace),
+            );
+            let enumerate = ui
+                .add_enabled(
+                    !self.command.trim().is_empty() && !self.signers_pending.is_waiting(),
+                    egui::Button::new("Find signers"),
+                )
+                .clicked();
+            if enumerate {
+                self.error = None;
+                self.message = None;
+                let command = self.command.trim().to_string();
+                let saved = Some(command.clone());
+                if let Err(e) = Settings::update(|settings| settings.signer_command = saved) {
+                    self.error = Some(format!("Failed to save settings: {}", e));
+                }
+                self.signers_pending =
+                    Pending::spawn(spawner, |send| Task::EnumerateSigners(command, send));
+            }
+            if self.signers_pending.is_waiting() {
+                ui.spinner();
+            }
+        });
+
+        match &self.signers {
+            Some(signers) if signers.is_empty() => {
+                ui.label("No signers found");
+            }
+            Some(signers) => {
+                let signers = signers.clone();
+                egui::Grid::new("signers")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for signer in signers {
+                            let label = format!("{} ({})", signer.name, signer.fingerprint);
+                            let selected = Some(signer);
+                            ui.radio_value(&mut self.selected, selected, label);
+                            ui.end_row();
+                        }
+                    });
+            }
+            None => {}
+        }
+
+        if let Some(signer) = self.selected.clone() {
+            ui.separator();
+            self.show_create(ui, spawner, signer);
+        }
+        if let Some(wallet) = wallet {
+            ui.separator();
+            self.show_display(ui, spawner, wallet);
+        }
+
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+    }
+
+    fn show_create(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, signer: ExternalSigner) {
+        ui.strong(format!("New wallet for {}", signer.name));
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.wallet_name).hint_text("Wallet name"));
+            ui.label("Account");
+            ui.add(egui::DragValue::new(&mut self.account));
+            let create = ui
+                .add_enabled(
+                    !self.wallet_name.is_empty() && !self.create.is_waiting(),
+                    egui::Button::new("Create wallet"),
+                )
+                .clicked();
+            if create {
+                self.error = None;
+                self.message = None;
+                let name = self.wallet_name.clone();
+                let account = self.account;
+                self.create = Pending::spawn(spawner, |send| {
+                    Task::CreateSignerWallet(name, signer, account, send)
+                });
+            }
+            if self.create.is_waiting() {
+                ui.spinner();
+            }
+        });
+    }
+
+    // Compare an address on the device's screen, guarding against a compromised computer
+    fn show_display(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, wallet: &str) {
+        ui.strong("Verify an address on the device");
+        match self.has_signer {
+            Some(true) => {
+                ui.label(format!("{} was created for an external signer", wallet));
+            }
+            Some(false) => {
+                ui.label(format!("{} holds its own keys", wallet));
+            }
+            None => {}
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.address)
+                    .hint_text("Address")
+                    .desired_width(360.0)
+                    .font(egui::TextStyle::Monospace),
+            );
+            // Without a signer of our own the node's -signer is asked instead
+            let show = ui
+                .add_enabled(
+                    !self.display.is_waiting() && self.network.is_some(),
+                    egui::Button::new("Show on device"),
+                )
+                .clicked();
+            if show {
+                self.error = None;
+                self.displayed = None;
+                if let Some(network) = self.network {
+                    match parse_address(self.address.trim(), network) {
+                        Ok(address) => {
+                            let wallet = wallet.to_string();
+                            let signer = self.selected.clone();
+                            self.display = Pending::spawn(spawner, |send| {
+                                Task::DisplayAddress(wallet, address, signer, send)
+                            });
+                        }
+                        Err(e) => self.error = Some(e),
+                    }
+                }
+            }
+            if self.display.is_waiting() {
+                ui.spinner();
+                ui.label("Confirm on the device");
+            }
+        });
+
+        match &self.displayed {
+            Some((expected, Some(shown))) if shown == expected => {
+                ui.colored_label(
+                    egui::Color32::LIGHT_GREEN,
+                    "The device shows the same address",
+                );
+            }
+            Some((_, Some(shown))) => {
+                ui.colored_label(
+                    egui::Color32::LIGHT_RED,
+                    format!("The device reported a different address: {}", shown),
+                );
+            }
+            Some((_, None)) => {
+                ui.label("Shown on the node's signer, check it matches");
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use super::{AppEvent, Pending};
+use crate::spawner::LocalSpawner;
+use crate::store::{StateChange, WalletState};
+use crate::tasks::Task;
+use std::collections::BTreeMap;
+use tokio::sync::mpsc;
+
+// The GUI's copy of every loaded wallet's state, kept current by the changes
+// each wallet's store pushes, so pages never have to ask for it
+#[derive(Default)]
+pub struct WalletStates {
+    states: BTreeMap<String, WalletState>,
+    subscriptions: BTreeMap<String, Pending<()>>,
+    errors: BTreeMap<String, String>,
+}
+
+impl WalletStates {
+    pub fn get(&self, wallet: &str) -> Option<&WalletState> {
+        self.states.get(wallet)
+    }
+
+    // Why a wallet has no state, if its store could not be started
+    pub fn error(&self, wallet: &str) -> Option<&str> {
+        self.errors.get(wallet).map(String::as_str)
+    }
+
+    pub fn apply(&mut self, wallet: &str, change: StateChange) {
+        match change {
+            StateChange::Snapshot(state) => {
+                self.states.insert(wallet.to_string(), state);
+            }
+            StateChange::Unloaded => {
+                self.states.remove(wallet);
+                self.subscriptions.remove(wallet);
+            }
+            change => {
+                if let Some(state) = self.states.get_mut(wallet) {
+                    state.apply(change);
+                }
+            }
+        }
+    }
+
+    // Start a store for each newly loaded wallet, and drop closed wallets
+    pub fn subscribe(
+        &mut self,
+        spawner: &LocalSpawner,
+        loaded: &[String],
+        events: &mpsc::UnboundedSender<AppEvent>,
+    ) {
+        self.states.retain(|wallet, _| loaded.contains(wallet));
+        self.subscriptions
+            .retain(|wallet, _| loaded.contains(wallet));
+        self.errors.retain(|wallet, _| loaded.contains(wallet));
+
+        for wallet in loaded {
+            if self.subscriptions.contains_key(wallet) {
+                continue;
+            }
+            let name = wallet.clone();
+            let events = events.clone();
+            let subscription = Pending::spawn(spawner, |send| {
+                Task::SubscribeWalletState(name, events, send)
+            });
+            self.subscriptions.insert(wallet.clone(), subscription);
+        }
+
+        for (wallet, subscription) in &mut self.subscriptions {
+            // Kept after it completes, so the wallet is not subscribed again
+            if let Some(Err(e)) = subscription.poll() {
+                self.errors.insert(wallet.clone(), e.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::backup::BackupReport;
+use crate::node::{NodeStatus, Progress};
+use eframe::egui;
+
+// Node startup and sync state, used to gate wallet actions
+#[derive(Default)]
+pub struct StatusPanel {
+    status: Option<NodeStatus>,
+    init_message: Option<String>,
+    progress: Option<Progress>,
+    // Latest scheduled wallet backup
+    backup: Option<BackupReport>,
+}
+
+impl StatusPanel {
+    pub fn set_status(&mut self, status: NodeStatus) {
+        self.status = Some(status);
+    }
+
+    pub fn set_init_message(&mut self, message: String) {
+        self.init_message = Some(message);
+    }
+
+    pub fn set_progress(&mut self, progress: Progress) {
+        self.progress = (!progress.is_finished()).then_some(progress);
+    }
+
+    pub fn set_backup(&mut self, report: BackupReport) {
+        self.backup = Some(report);
+    }
+
+    pub fn tip_height(&self) -> Option<i32> {
+        self.status.as_ref().map(|status| status.blocks)
+    }
+
+    // Ready once the node is synced and no startup operation is in progress
+    pub fn is_ready(&self) -> bool {
+        self.progress.is_none() && self.status.as_ref().is_some_and(|s| s.is_ready())
+    }
+
+    // Shown in place of wallet views until the node is ready
+    pub fn show_splash(&self, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.add_space(40.0);
+            ui.heading("Starting node...");
+            if let Some(message) = &self.init_message {
+                ui.label(message);
+            }
+
+            if let Some(progress) = &self.progress {
+                ui.label(&progress.title);
+                ui.add(egui::ProgressBar::new(progress.percent as f32 / 100.0).show_percentage());
+            }
+
+            match &self.status {
+                Some(status) if status.initial_block_download => {
+                    ui.label(format!(
+                        "Synchronizing: block {} of {}",
+                        status.blocks, status.headers
+                    ));
+                    ui.add(
+                        egui::ProgressBar::new(status.verification_progress as f32)
+                            .show_percentage(),
+                    );
+                }
+                Some(status) if status.loading_blocks => {
+                    ui.label("Loading blocks...");
+                }
+                Some(_) => {}
+                None => {
+                    ui.spinner();
+                }
+            }
+
+            ui.label("Wallet actions are disabled until the node is ready.");
+        });
+    }
+
+    pub fn show_status_bar(&self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            match &self.status {
+                Some(status) => {
+                    ui.label(format!("Blocks: {}", status.blocks));
+                    ui.label(format!("Headers: {}", status.headers));
+                    ui.label(format!(
+                        "Verification progress: {:.4}%",
+                        status.verification_progress * 100.0
+                    ));
+                    if status.initial_block_download {
+                        ui.label("Initial block download");
+                    }
+                }
+                None => {
+                    ui.label("Connecting...");
+                }
+            }
+
+            if let Some(report) = &self.backup {
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let time = report.time.with_timezone(&chrono::Local).format("%H:%M");
+                    match &report.error {
+                        Some(error) => {
+                            ui.colored_label(
+                                egui::Color32::LIGHT_RED,
+                                format!("Backup of {} failed at {}", report.wallet, time),
+                            )
+                            .on_hover_text(error);
+                        }
+                        None => {
+                            ui.label(format!("Backed up {} at {}", report.wallet, time))
+                                .on_hover_text(report.path.display().to_string());
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
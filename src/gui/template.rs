@@ -0,0 +1,144 @@
+use super::Pending;
+use crate::mining::{BlockTemplate, MAX_BLOCK_SIGOPS_COST};
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use bitcoin::hex::DisplayHex;
+use bitcoin::{Amount, Weight};
+use eframe::egui;
+
+// The block the node would mine next, as chosen from its mempool
+#[derive(Default)]
+pub struct TemplatePanel {
+    template: Option<BlockTemplate>,
+    pending: Pending<BlockTemplate>,
+    // Node status height the template was built at
+    tip_height: Option<i32>,
+    error: Option<String>,
+}
+
+impl TemplatePanel {
+    fn poll(&mut self, spawner: &LocalSpawner, tip_height: Option<i32>) {
+        match self.pending.poll() {
+            Some(Ok(template)) => {
+                self.template = Some(template);
+                self.error = None;
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
+        }
+
+        // A new tip makes the template stale
+        if self.tip_height != tip_height && !self.pending.is_waiting() {
+            self.tip_height = tip_height;
+            self.pending = Pending::spawn(spawner, Task::GetBlockTemplate);
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, spawner: &LocalSpawner, tip_height: Option<i32>) {
+        self.poll(spawner, tip_height);
+
+        ui.horizontal(|ui| {
+            ui.strong("Next block template");
+            if ui
+                .add_enabled(!self.pending.is_waiting(), egui::Button::new("Refresh"))
+                .clicked()
+            {
+                self.pending = Pending::spawn(spawner, Task::GetBlockTemplate);
+            }
+            if self.pending.is_waiting() {
+                ui.spinner();
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+        let Some(template) = &self.template else {
+            return;
+        };
+
+        let header = &template.block.header;
+        let fees = template.total_fees();
+        let coinbase_value = template.coinbase_value();
+        egui::Grid::new("template_summary")
+            .num_columns(2)
+            .show(ui, |ui| {
+                if let Ok(height) = template.block.bip34_block_height() {
+                    ui.label("Height");
+                    ui.label(height.to_string());
+                    ui.end_row();
+                }
+                ui.label("Previous block");
+                ui.monospace(header.prev_blockhash.to_string());
+                ui.end_row();
+                ui.label("Transactions");
+                ui.label(template.fees.len().to_string());
+                ui.end_row();
+                ui.label("Weight");
+                ui.label(format!(
+                    "{} / {} WU",
+                    template.block.weight().to_wu(),
+                    Weight::MAX_BLOCK.to_wu()
+                ));
+                ui.end_row();
+                ui.label("Sigop cost");
+                ui.label(format!(
+                    "{} / {}",
+                    template.total_sigops(),
+                    MAX_BLOCK_SIGOPS_COST
+                ));
+                ui.end_row();
+                ui.label("Total fees");
+                ui.monospace(fees.display_dynamic().to_string());
+                ui.end_row();
+                ui.label("Coinbase value");
+                ui.monospace(format!(
+                    "{} (subsidy {})",
+                    coinbase_value.display_dynamic(),
+                    coinbase_value
+                        .checked_sub(fees)
+                        .unwrap_or(Amount::ZERO)
+                        .display_dynamic()
+                ));
+                ui.end_row();
+                ui.label("Bits");
+                ui.monospace(format!("{:08x}", header.bits.to_consensus()));
+                ui.end_row();
+                ui.label("Target");
+                ui.monospace(header.target().to_be_bytes().to_lower_hex_string());
+                ui.end_row();
+                ui.label("Difficulty");
+                ui.label(format!("{:.3}", header.difficulty_float()));
+                ui.end_row();
+            });
+
+        if template.fees.is_empty() {
+            ui.label("The template has no transactions besides the coinbase");
+            return;
+        }
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .id_source("template_transactions")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                egui::Grid::new("template_transactions")
+                    .striped(true)
+                    .num_columns(5)
+                    .show(ui, |ui| {
+                        ui.strong("Transaction");
+                        ui.strong("Fee");
+                        ui.strong("Size");
+                        ui.strong("Fee rate");
+                        ui.strong("Sigops");
+                        ui.end_row();
+                        for tx in template.transactions() {
+                            ui.monospace(tx.tx.compute_txid().to_string());
+                            ui.monospace(tx.fee.display_dynamic().to_string());
+                            ui.label(format!("{} vB ({} WU)", tx.tx.vsize(), tx.weight().to_wu()));
+                            ui.label(tx.fee_rate().to_string());
+                            ui.label(tx.sigops.to_string());
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
@@ -0,0 +1,612 @@
+use super::migrate::MigrationWizard;
+use super::state::WalletStates;
+use super::{Pending, WalletMessage};
+use crate::spawner::LocalSpawner;
+use crate::tasks::Task;
+use crate::wallet::{Balances, WalletList};
+use eframe::egui;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use zeroize::Zeroizing;
+
+const DEFAULT_UNLOCK_SECONDS: u64 = 300;
+// Sets balances the wallet can not spend apart from spendable ones
+const WATCH_ONLY_COLOR: egui::Color32 = egui::Color32::LIGHT_BLUE;
+
+// Passphrase dialogs; buffers are zeroized when the dialog is dropped
+enum Dialog {
+    Encrypt {
+        passphrase: Zeroizing<String>,
+        confirm: Zeroizing<String>,
+    },
+    Unlock {
+        passphrase: Zeroizing<String>,
+        seconds: u64,
+    },
+    ChangePassphrase {
+        old: Zeroizing<String>,
+        new: Zeroizing<String>,
+        confirm: Zeroizing<String>,
+    },
+}
+
+impl Dialog {
+    fn encrypt() -> Self {
+        Dialog::Encrypt {
+            passphrase: passphrase_buffer(),
+            confirm: passphrase_buffer(),
+        }
+    }
+
+    fn unlock() -> Self {
+        Dialog::Unlock {
+            passphrase: passphrase_buffer(),
+            seconds: DEFAULT_UNLOCK_SECONDS,
+        }
+    }
+
+    fn change_passphrase() -> Self {
+        Dialog::ChangePassphrase {
+            old: passphrase_buffer(),
+            new: passphrase_buffer(),
+            confirm: passphrase_buffer(),
+        }
+    }
+}
+
+// Longest passphrase the dialogs take; buffers hold it at four bytes a char
+pub(super) const PASSPHRASE_MAX_CHARS: usize = 256;
+
+// A String only zeroizes its final allocation, so passphrase buffers are
+// allocated at full size up front and never grow into a new one
+pub(super) fn passphrase_buffer() -> Zeroizing<String> {
+    Zeroizing::new(String::with_capacity(PASSPHRASE_MAX_CHARS * 4))
+}
+
+// Move a passphrase out of an input buffer without leaving a copy behind
+pub(super) fn take(buffer: &mut Zeroizing<String>) -> Zeroizing<String> {
+    let mut taken = passphrase_buffer();
+    std::mem::swap(&mut *taken, &mut **buffer);
+    taken
+}
+
+// egui keeps copies of the text for undo in its own memory, which are not
+// zeroized; only frost_byte's buffer is
+fn passphrase_field(ui: &mut egui::Ui, label: &str, buffer: &mut Zeroizing<String>) {
+    ui.label(label);
+    ui.add(
+        egui::TextEdit::singleline(&mut **buffer)
+            .password(true)
+            .char_limit(PASSPHRASE_MAX_CHARS),
+    );
+    ui.end_row();
+}
+
+#[derive(Default)]
+pub struct WalletPanel {
+    wallets: Option<WalletList>,
+    wallets_pending: Pending<WalletList>,
+    active: Option<String>,
+    // Wallet to switch to once the current action completes
+    select_after: Option<String>,
+    action: Pending<()>,
+    // Opening, creating or restoring a wallet, which the node may warn about
+    open: Pending<Vec<String>>,
+    warnings: Vec<String>,
+    dialog: Option<Dialog>,
+    relock_at: Option<Instant>,
+    error: Option<String>,
+    new_wallet_name: String,
+    // Create the new wallet blank and without private keys
+    new_wallet_watch_only: bool,
+    open_wallet_name: Option<String>,
+    restore_file: Option<PathBuf>,
+    restore_name: String,
+    migration: Option<MigrationWizard>,
+}
+
+impl WalletPanel {
+    // Refetch the wallet list, e.g. after a wallet was created
+    pub fn refresh(&mut self) {
+        self.wallets = None;
+    }
+
+    // Make `name` the active wallet once it shows up in the wallet list
+    pub fn select(&mut self, name: String) {
+        self.select_after = Some(name);
+        self.refresh();
+    }
+
+    // Select a wallet that was just opened, showing what the node warned about
+    pub fn opened(&mut self, name: String, warnings: Vec<String>) {
+        self.warnings = warnings;
+        self.select(name);
+    }
+
+    fn busy(&self) -> bool {
+        self.action.is_waiting() || self.open.is_waiting()
+    }
+
+    // The wallet that wallet-scoped pages operate on
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    pub fn loaded(&self) -> &[String] {
+        self.wallets
+            .as_ref()
+            .map_or(&[], |wallets| wallets.loaded.as_slice())
+    }
+
+    fn set_active(&mut self, name: Option<String>) {
+        if name != self.active {
+            self.active = name;
+            self.relock_at = None;
+        }
+    }
+
+    // Wallet switcher, shown in the top bar so every page shares the selection
+    pub fn show_switcher(&mut self, ui: &mut egui::Ui) {
+        let Some(wallets) = &self.wallets else {
+            return;
+        };
+        let mut selected = self.active.clone();
+        egui::ComboBox::from_id_source("wallet_switcher")
+            .selected_text(selected.as_deref().unwrap_or("No wallet"))
+            .show_ui(ui, |ui| {
+                for name in &wallets.loaded {
+                    ui.selectable_value(&mut selected, Some(name.clone()), name);
+                }
+            });
+        self.set_active(selected);
+    }
+
+    fn poll(&mut self, spawner: &LocalSpawner) {
+        match self.wallets_pending.poll() {
+            Some(Ok(wallets)) => {
+                let selected = self
+                    .select_after
+                    .take()
+                    .or_else(|| self.active.clone())
+                    .filter(|name| wallets.loaded.contains(name))
+                    .or_else(|| wallets.loaded.first().cloned());
+                self.wallets = Some(wallets);
+                self.set_active(selected);
+            }
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.wallets = Some(WalletList::default());
+            }
+            None => {}
+        }
+
+        match self.action.poll() {
+            Some(Ok(())) => self.refresh(),
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.refresh();
+            }
+            None => {}
+        }
+
+        match self.open.poll() {
+            Some(Ok(warnings)) => {
+                self.warnings = warnings;
+                self.refresh();
+            }
+            Some(Err(e)) => {
+                self.error = Some(e.to_string());
+                self.refresh();
+            }
+            None => {}
+        }
+
+        if self.busy() {
+            return;
+        }
+        if self.wallets.is_none() && !self.wallets_pending.is_waiting() {
+            self.wallets_pending = Pending::spawn(spawner, Task::ListWallets);
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        tx: &mpsc::UnboundedSender<WalletMessage>,
+        ready: bool,
+        states: &WalletStates,
+    ) {
+        self.poll(spawner);
+
+        if self.active().is_some() {
+            self.show_header(ui, spawner, ready, states);
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+        for warning in &self.warnings {
+            ui.colored_label(egui::Color32::YELLOW, warning);
+        }
+        ui.separator();
+        ui.add_enabled_ui(ready && !self.busy(), |ui| {
+            self.show_wallets(ui, spawner, tx);
+        });
+        self.show_dialog(ui.ctx(), spawner);
+        self.show_migration(ui.ctx(), spawner);
+    }
+
+    fn show_migration(&mut self, ctx: &egui::Context, spawner: &LocalSpawner) {
+        let Some(wizard) = &mut self.migration else {
+            return;
+        };
+        let open = wizard.show(ctx, spawner);
+        if let Some(name) = wizard.take_migrated() {
+            self.select(name);
+        }
+        if !open {
+            self.migration = None;
+        }
+    }
+
+    // Create, open, restore and close wallets
+    fn show_wallets(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        tx: &mpsc::UnboundedSender<WalletMessage>,
+    ) {
+        let Some(wallets) = &self.wallets else {
+            ui.spinner();
+            return;
+        };
+        let wallets = wallets.clone();
+
+        ui.heading("Loaded wallets");
+        if wallets.loaded.is_empty() {
+            ui.label("No wallets loaded");
+        }
+        for name in &wallets.loaded {
+            ui.horizontal(|ui| {
+                if self.active.as_ref() == Some(name) {
+                    ui.strong(name);
+                } else {
+                    ui.label(name);
+                }
+                if ui.button("Close wallet").clicked() {
+                    self.error = None;
+                    let name = name.clone();
+                    self.action = Pending::spawn(spawner, |send| Task::UnloadWallet(name, send));
+                }
+            });
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let closed: Vec<&String> = wallets
+                .available
+                .iter()
+                .filter(|name| !wallets.loaded.contains(name))
+                .collect();
+            egui::ComboBox::from_id_source("open_wallet")
+                .selected_text(self.open_wallet_name.as_deref().unwrap_or("Select wallet"))
+                .show_ui(ui, |ui| {
+                    for name in closed {
+                        ui.selectable_value(&mut self.open_wallet_name, Some(name.clone()), name);
+                    }
+                });
+            if ui
+                .add_enabled(
+                    self.open_wallet_name.is_some(),
+                    egui::Button::new("Open wallet"),
+                )
+                .clicked()
+            {
+                if let Some(name) = self.open_wallet_name.take() {
+                    self.error = None;
+                    self.warnings.clear();
+                    self.select_after = Some(name.clone());
+                    self.open = Pending::spawn(spawner, |send| Task::LoadWallet(name, send));
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_wallet_name).hint_text("Wallet name"));
+            if ui
+                .add_enabled(
+                    !self.new_wallet_name.is_empty(),
+                    egui::Button::new("Create new wallet"),
+                )
+                .clicked()
+            {
+                self.error = None;
+                self.warnings.clear();
+                let name = std::mem::take(&mut self.new_wallet_name);
+                if self.new_wallet_watch_only {
+                    self.select_after = Some(name.clone());
+                    self.open =
+                        Pending::spawn(spawner, |send| Task::CreateWatchOnlyWallet(name, send));
+                } else {
+                    let _ = tx.send(WalletMessage::CreateNewWallet(name));
+                }
+            }
+            ui.checkbox(
+                &mut self.new_wallet_watch_only,
+                "Watch-only (no private keys)",
+            );
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Restore from backup...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    self.restore_name = path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    self.restore_file = Some(path);
+                }
+            }
+            if let Some(path) = &self.restore_file {
+                ui.label(path.display().to_string());
+                ui.add(egui::TextEdit::singleline(&mut self.restore_name).hint_text("Wallet name"));
+                if ui
+                    .add_enabled(!self.restore_name.is_empty(), egui::Button::new("Restore"))
+                    .clicked()
+                {
+                    self.error = None;
+                    self.warnings.clear();
+                    let path = path.clone();
+                    let name = std::mem::take(&mut self.restore_name);
+                    self.restore_file = None;
+                    self.select_after = Some(name.clone());
+                    self.open =
+                        Pending::spawn(spawner, |send| Task::RestoreWallet(path, name, send));
+                }
+            }
+        });
+
+        if ui.button("Migrate legacy wallet...").clicked() {
+            self.migration = Some(MigrationWizard::new(spawner));
+        }
+    }
+
+    fn show_header(
+        &mut self,
+        ui: &mut egui::Ui,
+        spawner: &LocalSpawner,
+        ready: bool,
+        states: &WalletStates,
+    ) {
+        let Some(wallet) = self.active.clone() else {
+            return;
+        };
+        let Some(wallet_state) = states.get(&wallet) else {
+            ui.horizontal(|ui| {
+                ui.heading(&wallet);
+                match states.error(&wallet) {
+                    Some(error) => ui.colored_label(egui::Color32::LIGHT_RED, error),
+                    None => ui.spinner(),
+                };
+            });
+            return;
+        };
+        // The relock task Task::UnlockWallet started locks the wallet and the
+        // store reports it, so the countdown only has to stop
+        if self.relock_at.is_some_and(|at| Instant::now() >= at) {
+            self.relock_at = None;
+        }
+        let state = wallet_state.lock_state;
+        ui.horizontal(|ui| {
+            ui.heading(&wallet);
+
+            let busy = self.busy() || !ready;
+            if !state.crypted {
+                ui.label("🔓 Not encrypted");
+                if ui
+                    .add_enabled(!busy, egui::Button::new("Encrypt wallet"))
+                    .clicked()
+                {
+                    self.dialog = Some(Dialog::encrypt());
+                }
+                return;
+            }
+
+            if state.locked {
+                ui.label("🔒 Locked");
+                if ui.add_enabled(!busy, egui::Button::new("Unlock")).clicked() {
+                    self.dialog = Some(Dialog::unlock());
+                }
+            } else {
+                match self.relock_at {
+                    Some(at) => ui.label(format!(
+                        "🔓 Unlocked ({}s)",
+                        at.saturating_duration_since(Instant::now()).as_secs()
+                    )),
+                    None => ui.label("🔓 Unlocked"),
+                };
+                if ui.add_enabled(!busy, egui::Button::new("Lock")).clicked() {
+                    self.error = None;
+                    self.relock_at = None;
+                    let wallet = wallet.clone();
+                    self.action = Pending::spawn(spawner, |send| Task::LockWallet(wallet, send));
+                }
+            }
+            if ui
+                .add_enabled(!busy, egui::Button::new("Change passphrase"))
+                .clicked()
+            {
+                self.dialog = Some(Dialog::change_passphrase());
+            }
+        });
+
+        let watch_only = wallet_state.watch_only;
+        let balances = &wallet_state.balances;
+        // A wallet without keys can only watch, so all of its balance is watch-only
+        if watch_only.private_keys_disabled {
+            let color = WATCH_ONLY_COLOR;
+            ui.colored_label(color, "👁 Watch-only wallet");
+            ui.colored_label(
+                color,
+                format!("Balance: {}", balances.trusted.display_dynamic()),
+            );
+            ui.colored_label(
+                color,
+                format!("Pending: {}", balances.untrusted_pending.display_dynamic()),
+            );
+            ui.colored_label(
+                color,
+                format!("Immature: {}", balances.immature.display_dynamic()),
+            );
+            ui.colored_label(
+                color,
+                egui::RichText::new(format!("Total: {}", balances.total().display_dynamic()))
+                    .strong(),
+            );
+        } else {
+            ui.label(format!("Balance: {}", balances.trusted.display_dynamic()));
+            ui.label(format!(
+                "Pending: {}",
+                balances.untrusted_pending.display_dynamic()
+            ));
+            ui.label(format!("Immature: {}", balances.immature.display_dynamic()));
+            ui.strong(format!("Total: {}", balances.total().display_dynamic()));
+            if watch_only.have_watch_only {
+                show_watch_only_balances(ui, balances);
+            }
+        }
+    }
+
+    fn show_dialog(&mut self, ctx: &egui::Context, spawner: &LocalSpawner) {
+        let Some(wallet) = self.active.clone() else {
+            self.dialog = None;
+            return;
+        };
+        let Some(dialog) = &mut self.dialog else {
+            return;
+        };
+
+        let mut open = true;
+        let mut submitted = None;
+        let mut validation = None;
+        let mut relock_at = None;
+        let title = match dialog {
+            Dialog::Encrypt { .. } => "Encrypt wallet",
+            Dialog::Unlock { .. } => "Unlock wallet",
+            Dialog::ChangePassphrase { .. } => "Change passphrase",
+        };
+
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("passphrase_dialog")
+                    .num_columns(2)
+                    .show(ui, |ui| match dialog {
+                        Dialog::Encrypt {
+                            passphrase,
+                            confirm,
+                        } => {
+                            passphrase_field(ui, "New passphrase", passphrase);
+                            passphrase_field(ui, "Repeat passphrase", confirm);
+                        }
+                        Dialog::Unlock {
+                            passphrase,
+                            seconds,
+                        } => {
+                            passphrase_field(ui, "Passphrase", passphrase);
+                            ui.label("Relock after (seconds)");
+                            ui.add(egui::DragValue::new(seconds).range(1..=86400));
+                            ui.end_row();
+                        }
+                        Dialog::ChangePassphrase { old, new, confirm } => {
+                            passphrase_field(ui, "Current passphrase", old);
+                            passphrase_field(ui, "New passphrase", new);
+                            passphrase_field(ui, "Repeat new passphrase", confirm);
+                        }
+                    });
+
+                if let Dialog::Encrypt { .. } = dialog {
+                    ui.label("Encrypting locks the wallet. Losing the passphrase means losing your funds.");
+                }
+
+                if ui.button("OK").clicked() {
+                    match dialog {
+                        Dialog::Encrypt {
+                            passphrase,
+                            confirm,
+                        } => {
+                            if passphrase.is_empty() || **passphrase != **confirm {
+                                validation = Some("Passphrases are empty or do not match");
+                            } else {
+                                let passphrase = take(passphrase);
+                                submitted = Some(Pending::spawn(spawner, |send| {
+                                    Task::EncryptWallet(wallet, passphrase, send)
+                                }));
+                            }
+                        }
+                        Dialog::Unlock {
+                            passphrase,
+                            seconds,
+                        } => {
+                            let passphrase = take(passphrase);
+                            let timeout = Duration::from_secs(*seconds);
+                            relock_at = Some(Instant::now() + timeout);
+                            submitted = Some(Pending::spawn(spawner, |send| {
+                                Task::UnlockWallet(wallet, passphrase, timeout, send)
+                            }));
+                        }
+                        Dialog::ChangePassphrase { old, new, confirm } => {
+                            if new.is_empty() || **new != **confirm {
+                                validation = Some("New passphrases are empty or do not match");
+                            } else {
+                                let (old, new) = (take(old), take(new));
+                                submitted = Some(Pending::spawn(spawner, |send| {
+                                    Task::ChangeWalletPassphrase(wallet, old, new, send)
+                                }));
+                            }
+                        }
+                    }
+                }
+            });
+
+        if let Some(message) = validation {
+            self.error = Some(message.to_string());
+        }
+        if let Some(action) = submitted {
+            self.error = None;
+            self.relock_at = relock_at;
+            self.action = action;
+            self.dialog = None;
+        } else if !open {
+            self.dialog = None;
+        }
+    }
+}
+
+// Watch-only scripts held next to spendable ones
+fn show_watch_only_balances(ui: &mut egui::Ui, balances: &Balances) {
+    let color = WATCH_ONLY_COLOR;
+    ui.add_space(4.0);
+    ui.colored_label(color, "👁 Watch-only");
+    ui.colored_label(
+        color,
+        format!("Balance: {}", balances.watch_only_trusted.display_dynamic()),
+    );
+    ui.colored_label(
+        color,
+        format!(
+            "Pending: {}",
+            balances.watch_only_untrusted_pending.display_dynamic()
+        ),
+    );
+    ui.colored_label(
+        color,
+        format!(
+            "Immature: {}",
+            balances.watch_only_immature.display_dynamic()
+        ),
+    );
+}
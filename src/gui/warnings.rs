@@ -0,0 +1,44 @@
+use eframe::egui;
+
+// Node warnings, shown until dismissed or until the warnings change
+#[derive(Default)]
+pub struct WarningBanner {
+    warnings: Vec<String>,
+    dismissed: bool,
+}
+
+impl WarningBanner {
+    pub fn set_warnings(&mut self, warnings: Vec<String>) {
+        if warnings != self.warnings {
+            self.warnings = warnings;
+            self.dismissed = false;
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if self.warnings.is_empty() || self.dismissed {
+            return;
+        }
+
+        egui::TopBottomPanel::top("warnings")
+            .frame(
+                egui::Frame::default()
+                    .fill(egui::Color32::from_rgb(110, 80, 0))
+                    .inner_margin(6.0),
+            )
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        for warning in &self.warnings {
+                            ui.colored_label(egui::Color32::WHITE, format!("⚠ {}", warning));
+                        }
+                    });
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                        if ui.button("Dismiss").clicked() {
+                            self.dismissed = true;
+                        }
+                    });
+                });
+            });
+    }
+}
@@ -0,0 +1,239 @@
+use crate::wallet::get_address_label;
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::{Amount, SignedAmount, Transaction, TxOut, Txid};
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::{wallet, wallet_tx, wallet_tx_status};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxKind {
+    Received,
+    Sent,
+    ToSelf,
+    Mined,
+}
+
+impl TxKind {
+    pub const ALL: [TxKind; 4] = [
+        TxKind::Received,
+        TxKind::Sent,
+        TxKind::ToSelf,
+        TxKind::Mined,
+    ];
+}
+
+impl fmt::Display for TxKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            TxKind::Received => "Received",
+            TxKind::Sent => "Sent",
+            TxKind::ToSelf => "Payment to self",
+            TxKind::Mined => "Mined",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+// A wallet transaction with the status it had when it was fetched
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxRecord {
+    pub txid: Txid,
+    pub tx: Transaction,
+    pub kind: TxKind,
+    // Unix time in seconds
+    pub time: i64,
+    pub credit: Amount,
+    pub debit: Amount,
+    pub change: Amount,
+    pub output_is_mine: Vec<bool>,
+    pub output_is_change: Vec<bool>,
+    pub label: String,
+    pub comment: Option<String>,
+    pub block_height: Option<u32>,
+    // Negative when the transaction conflicts with the main chain
+    pub depth: i32,
+    // Chain height `depth` was measured at
+    pub num_blocks: i32,
+    pub abandoned: bool,
+    pub in_mempool: bool,
+}
+
+impl TxRecord {
+    pub fn amount(&self) -> SignedAmount {
+        SignedAmount::from_sat(self.credit.to_sat() as i64 - self.debit.to_sat() as i64)
+    }
+
+    // Only known for transactions spending our own coins
+    pub fn fee(&self) -> Option<Amount> {
+        if self.debit == Amount::ZERO {
+            return None;
+        }
+        let outputs: Amount = self.tx.output.iter().map(|output| output.value).sum();
+        self.debit.checked_sub(outputs)
+    }
+
+    // Confirmations at `tip`, without refetching the transaction on each block
+    pub fn confirmations(&self, tip: i32) -> i32 {
+        if self.depth <= 0 {
+            return self.depth;
+        }
+        self.depth + (tip - self.num_blocks).max(0)
+    }
+
+    // The output shown in the list: the payee for sends, our address for receives
+    pub fn primary_output(&self) -> Option<&TxOut> {
+        let index = match self.kind {
+            TxKind::Sent => self.output_is_mine.iter().position(|mine| !mine),
+            _ => self
+                .output_is_mine
+                .iter()
+                .zip(&self.output_is_change)
+                .position(|(mine, change)| *mine && !change),
+        };
+        self.tx.output.get(index?)
+    }
+}
+
+// Mirrors ChangeType in Bitcoin Core
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxEvent {
+    Updated(TxRecord),
+    Deleted(Txid),
+}
+
+fn amount(sats: i64) -> Amount {
+    Amount::from_sat(sats.max(0) as u64)
+}
+
+fn read_wallet_tx(
+    wtx: wallet_tx::Reader,
+    status: wallet_tx_status::Reader,
+    num_blocks: i32,
+) -> Result<TxRecord, Box<dyn std::error::Error>> {
+    let tx: Transaction = deserialize(wtx.get_tx()?)?;
+    let output_is_mine: Vec<bool> = wtx
+        .get_txout_is_mine()?
+        .iter()
+        .map(|mine| mine != 0)
+        .collect();
+    let output_is_change: Vec<bool> = wtx.get_txout_is_change()?.iter().collect();
+    let credit = amount(wtx.get_credit());
+    let debit = amount(wtx.get_debit());
+
+    let kind = if wtx.get_is_coinbase() {
+        TxKind::Mined
+    } else if debit == Amount::ZERO {
+        TxKind::Received
+    } else if output_is_mine.iter().all(|mine| *mine) {
+        TxKind::ToSelf
+    } else {
+        TxKind::Sent
+    };
+
+    let mut comment = None;
+    for pair in wtx.get_value_map()? {
+        if pair.get_key()?.to_str()? == "comment" {
+            comment = Some(pair.get_value()?.to_string()?);
+        }
+    }
+
+    // Unconfirmed transactions report the maximum int as their height
+    let block_height = u32::try_from(status.get_block_height())
+        .ok()
+        .filter(|_| status.get_is_in_main_chain());
+
+    Ok(TxRecord {
+        txid: tx.compute_txid(),
+        tx,
+        kind,
+        time: wtx.get_time(),
+        credit,
+        debit,
+        change: amount(wtx.get_change()),
+        output_is_mine,
+        output_is_change,
+        label: String::new(),
+        comment,
+        block_height,
+        depth: status.get_depth_in_main_chain(),
+        num_blocks,
+        abandoned: status.get_is_abandoned(),
+        in_mempool: false,
+    })
+}
+
+async fn fill_label(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    record: &mut TxRecord,
+) {
+    let Some(output) = record.primary_output() else {
+        return;
+    };
+    // Scripts without an address form, e.g. OP_RETURN, have no label
+    if let Ok(Some(label)) =
+        get_address_label(wallet_client, thread_client, &output.script_pubkey).await
+    {
+        record.label = label;
+    }
+}
+
+pub async fn get_wallet_tx(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    txid: Txid,
+) -> Result<TxRecord, Box<dyn std::error::Error>> {
+    let mut details_request = wallet_client.get_wallet_tx_details_request();
+    details_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    details_request.get().set_txid(&serialize(&txid));
+    let details_response = details_request.send().promise.await?;
+    let details = details_response.get()?;
+    let mut record = read_wallet_tx(
+        details.get_result()?,
+        details.get_tx_status()?,
+        details.get_num_blocks(),
+    )?;
+    record.in_mempool = details.get_in_mempool();
+    fill_label(wallet_client, thread_client, &mut record).await;
+    Ok(record)
+}
+
+// All wallet transactions, newest first
+pub async fn get_wallet_txs(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Vec<TxRecord>, Box<dyn std::error::Error>> {
+    let mut txs_request = wallet_client.get_wallet_txs_request();
+    txs_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let txs_response = txs_request.send().promise.await?;
+
+    let mut records = Vec::new();
+    for wtx in txs_response.get()?.get_result()? {
+        let tx: Transaction = deserialize(wtx.get_tx()?)?;
+        let mut status_request = wallet_client.try_get_tx_status_request();
+        status_request
+            .get()
+            .get_context()?
+            .set_thread(thread_client.clone());
+        status_request
+            .get()
+            .set_txid(&serialize(&tx.compute_txid()));
+        let status_response = status_request.send().promise.await?;
+        let status = status_response.get()?;
+        // The transaction may have been removed since getWalletTxs
+        if !status.get_result() {
+            continue;
+        }
+        let mut record = read_wallet_tx(wtx, status.get_tx_status()?, status.get_num_blocks())?;
+        fill_label(wallet_client, thread_client, &mut record).await;
+        records.push(record);
+    }
+    records.sort_by(|a, b| b.time.cmp(&a.time));
+    Ok(records)
+}
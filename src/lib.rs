@@ -1,6 +1,33 @@
+pub mod address;
+pub mod address_book;
+pub mod backup;
+pub mod bump;
+pub mod chain;
+pub mod cli;
 pub mod clients;
+pub mod coins;
+pub mod descriptor;
 pub mod echo;
+pub mod fee;
 pub mod gui;
+pub mod history;
+pub mod logging;
+pub mod mempool;
+pub mod message;
+pub mod migrate;
+pub mod mining;
+pub mod node;
+pub mod psbt;
+pub mod receive;
+pub mod registry;
+pub mod rescan;
+pub mod rpc;
+pub mod send;
+pub mod settings;
+pub mod signer;
 pub mod spawner;
+pub mod store;
+#[cfg(feature = "stratum")]
+pub mod stratum;
 pub mod tasks;
 pub mod wallet;
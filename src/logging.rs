@@ -0,0 +1,54 @@
+use crate::rpc::execute_rpc;
+use bitcoin_ipc::node_capnp::node;
+use bitcoin_ipc::proxy_capnp;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct LogCategories {
+    // Bitmask of enabled BCLog::LogFlags
+    pub mask: u64,
+    // Category name to enabled state
+    pub categories: BTreeMap<String, bool>,
+}
+
+pub async fn get_log_categories(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<LogCategories, Box<dyn std::error::Error>> {
+    set_log_categories(node_client, thread_client, &[], &[]).await
+}
+
+// Enable and disable categories via the `logging` RPC, returning the resulting state
+pub async fn set_log_categories(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    include: &[String],
+    exclude: &[String],
+) -> Result<LogCategories, Box<dyn std::error::Error>> {
+    let result = execute_rpc(
+        node_client,
+        thread_client,
+        "logging",
+        json!([include, exclude]),
+    )
+    .await?;
+    let categories = result
+        .as_object()
+        .ok_or("Unexpected logging response")?
+        .iter()
+        .map(|(name, enabled)| (name.clone(), enabled.as_bool().unwrap_or(false)))
+        .collect();
+
+    let mut log_categories_request = node_client.get_log_categories_request();
+    log_categories_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let log_categories_response = log_categories_request.send().promise.await?;
+
+    Ok(LogCategories {
+        mask: log_categories_response.get()?.get_result(),
+        categories,
+    })
+}
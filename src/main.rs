@@ -1,6 +1,8 @@
 use anyhow::Result;
 use clap::Parser;
-use frost_byte::gui::{App, WalletMessage};
+use frost_byte::cli;
+use frost_byte::gui::{App, AppEvent, WalletMessage};
+use frost_byte::settings::Settings;
 use frost_byte::spawner::LocalSpawner;
 use frost_byte::tasks::Task;
 use nix::sys::signal::{kill, Signal};
@@ -16,6 +18,8 @@ use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 use tokio::time::{sleep, Duration};
 
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -26,6 +30,10 @@ struct Args {
     /// Auto spawn a bitcoin-node binary at this path
     #[arg(long, conflicts_with = "socket")]
     spawn: Option<String>,
+
+    /// Run a single command instead of starting the GUI
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 fn main() -> Result<()> {
@@ -53,9 +61,15 @@ fn main() -> Result<()> {
             (None, Some(autospawn_cmd)) => {
                 let temp_path = random_temp_path();
 
+                let settings = Settings::load();
                 let child = Command::new(autospawn_cmd)
                     .arg(format!("-ipcbind=unix://{}", temp_path.to_str().unwrap()))
-                    .arg("-debug=ipc")
+                    .args(
+                        settings
+                            .debug_categories
+                            .iter()
+                            .map(|category| format!("-debug={}", category)),
+                    )
                     .arg("-regtest")
                     .arg("-daemon=0")
                     .stdout(Stdio::null())
@@ -98,6 +112,154 @@ fn main() -> Result<()> {
             Err(_) => println!("The sender dropped"),
         }
 
+        // NodeClient
+        let (send, response) = tokio::sync::oneshot::channel();
+        spawner.spawn(Task::SetupNodeClient(send));
+        match response.await {
+            Ok(Ok(())) => println!("NodeClient setup successfully"),
+            Ok(Err(e)) => println!("Error occurred: {}", e),
+            Err(_) => println!("The sender dropped"),
+        }
+
+        // ChainClient
+        let (send, response) = tokio::sync::oneshot::channel();
+        spawner.spawn(Task::SetupChainClient(send));
+        match response.await {
+            Ok(Ok(())) => println!("ChainClient setup successfully"),
+            Ok(Err(e)) => println!("Error occurred: {}", e),
+            Err(_) => println!("The sender dropped"),
+        }
+
+        // WalletLoaderClient
+        // This fails, and I don't know why!
+        let (send, response) = tokio::sync::oneshot::channel();
+        spawner.spawn(Task::SetupWalletLoaderClient(send));
+        match response.await {
+            Ok(Ok(())) => println!("WalletLoaderClient setup successfully"),
+            Ok(Err(e)) => println!("Error occurred: {}", e),
+            Err(_) => println!("The sender dropped"),
+        }
+
+        if let Some(command) = args.command {
+            return cli::run(command, &spawner).await;
+        }
+
+        // Setup channel for updates from the node to the GUI
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+
+        // Init messages and progress, shown while the node starts up
+        let (send, response) = tokio::sync::oneshot::channel();
+        spawner.spawn(Task::SubscribeNodeStatus(event_tx.clone(), send));
+        match response.await {
+            Ok(Ok(())) => println!("Subscribed to node status notifications"),
+            Ok(Err(e)) => println!("Error occurred: {}", e),
+            Err(_) => println!("The sender dropped"),
+        }
+
+        // Warnings, fetched once now and again whenever the node reports a change
+        let (send, response) = tokio::sync::oneshot::channel();
+        spawner.spawn(Task::SubscribeAlerts(event_tx.clone(), send));
+        match response.await {
+            Ok(Ok(())) => println!("Subscribed to alert notifications"),
+            Ok(Err(e)) => println!("Error occurred: {}", e),
+            Err(_) => println!("The sender dropped"),
+        }
+        let (send, response) = tokio::sync::oneshot::channel();
+        spawner.spawn(Task::GetWarnings(send));
+        match response.await {
+            Ok(Ok(warnings)) => {
+                let _ = event_tx.send(AppEvent::Warnings(warnings));
+            }
+            Ok(Err(e)) => println!("Error occurred: {}", e),
+            Err(_) => println!("The sender dropped"),
+        }
+
+        // Mempool notifications
+        let (send, response) = tokio::sync::oneshot::channel();
+        spawner.spawn(Task::SubscribeMempool(event_tx.clone(), send));
+        match response.await {
+            Ok(Ok(())) => println!("Subscribed to mempool notifications"),
+            Ok(Err(e)) => println!("Error occurred: {}", e),
+            Err(_) => println!("The sender dropped"),
+        }
+
+        // Automatic wallet backups, as configured on the settings page
+        if let Some(schedule) = Settings::load().backup_schedule {
+            let (send, response) = tokio::sync::oneshot::channel();
+            spawner.spawn(Task::SetBackupSchedule(
+                Some(schedule),
+                event_tx.clone(),
+                send,
+            ));
+            match response.await {
+                Ok(Ok(())) => println!("Scheduled wallet backups"),
+                Ok(Err(e)) => println!("Error occurred: {}", e),
+                Err(_) => println!("The sender dropped"),
+            }
+        }
+
+        // Poll statistics which have no notification
+        let spawner_clone = spawner.clone();
+        let poll_tx = event_tx.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let (send, response) = tokio::sync::oneshot::channel();
+                spawner_clone.spawn(Task::GetNodeStatus(send));
+                match response.await {
+                    Ok(Ok(status)) => {
+                        if poll_tx.send(AppEvent::NodeStatus(status)).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(Err(e)) => println!("Error occurred: {}", e),
+                    Err(_) => println!("The sender dropped"),
+                }
+
+                let (send, response) = tokio::sync::oneshot::channel();
+                spawner_clone.spawn(Task::GetMempoolInfo(send));
+                match response.await {
+                    Ok(Ok(info)) => {
+                        if poll_tx.send(AppEvent::MempoolInfo(info)).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(Err(e)) => println!("Error occurred: {}", e),
+                    Err(_) => println!("The sender dropped"),
+                }
+
+                // Estimates are cached per block tip, so this is cheap between blocks
+                let (send, response) = tokio::sync::oneshot::channel();
+                spawner_clone.spawn(Task::GetFeeEstimates(send));
+                match response.await {
+                    Ok(Ok(estimates)) => {
+                        if poll_tx.send(AppEvent::FeeEstimates(estimates)).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(Err(e)) => println!("Error occurred: {}", e),
+                    Err(_) => println!("The sender dropped"),
+                }
+
+                // Not every warning comes with an alert notification, e.g. clock skew
+                let (send, response) = tokio::sync::oneshot::channel();
+                spawner_clone.spawn(Task::GetWarnings(send));
+                match response.await {
+                    Ok(Ok(warnings)) => {
+                        if poll_tx.send(AppEvent::Warnings(warnings)).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(Err(e)) => println!("Error occurred: {}", e),
+                    Err(_) => println!("The sender dropped"),
+                }
+            }
+        });
+
+        // Wallet pages subscribe to their own notifications from the GUI
+        let notify_tx = event_tx.clone();
+
         // Setup communication channel
         let (tx, mut rx) = mpsc::unbounded_channel();
 
@@ -106,31 +268,15 @@ fn main() -> Result<()> {
         tokio::spawn(async move {
             while let Some(message) = rx.recv().await {
                 match message {
-                    WalletMessage::CreateNewWallet => {
-                        // NodeClient
-                        let (send, response) = tokio::sync::oneshot::channel();
-                        spawner_clone.spawn(Task::SetupNodeClient(send));
-                        match response.await {
-                            Ok(Ok(())) => println!("NodeClient setup successfully"),
-                            Ok(Err(e)) => println!("Error occurred: {}", e),
-                            Err(_) => println!("The sender dropped"),
-                        }
-
-                        // WalletLoaderClient
-                        // This fails, and I don't know why!
-                        let (send, response) = tokio::sync::oneshot::channel();
-                        spawner_clone.spawn(Task::SetupWalletLoaderClient(send));
-                        match response.await {
-                            Ok(Ok(())) => println!("WalletLoaderClient setup successfully"),
-                            Ok(Err(e)) => println!("Error occurred: {}", e),
-                            Err(_) => println!("The sender dropped"),
-                        }
-
+                    WalletMessage::CreateNewWallet(name) => {
                         // CreateNewWallet
                         let (send, response) = tokio::sync::oneshot::channel();
-                        spawner_clone.spawn(Task::CreateNewWallet(send));
+                        spawner_clone.spawn(Task::CreateNewWallet(name.clone(), send));
                         match response.await {
-                            Ok(Ok(())) => println!("Created new wallet setup successfully"),
+                            Ok(Ok(warnings)) => {
+                                println!("Created new wallet setup successfully");
+                                let _ = event_tx.send(AppEvent::WalletLoaded(name, warnings));
+                            }
                             Ok(Err(e)) => println!("Error occurred: {}", e),
                             Err(_) => println!("The sender dropped"),
                         }
@@ -144,7 +290,7 @@ fn main() -> Result<()> {
         eframe::run_native(
             "Frost byte",
             native_options,
-            Box::new(move |cc| Ok(Box::new(App::new(cc, spawner, tx)))),
+            Box::new(move |cc| Ok(Box::new(App::new(cc, spawner, tx, notify_tx, event_rx)))),
         )
         .expect("Failed to run app");
 
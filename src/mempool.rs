@@ -0,0 +1,338 @@
+use crate::gui::AppEvent;
+use crate::rpc::execute_rpc;
+use bitcoin::consensus::deserialize;
+use bitcoin::{Transaction, Txid};
+use bitcoin_ipc::chain_capnp::{chain, chain_notifications};
+use bitcoin_ipc::handler_capnp::handler;
+use bitcoin_ipc::node_capnp;
+use bitcoin_ipc::proxy_capnp;
+use capnp::capability::Promise;
+use capnp_rpc::pry;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+// Lower bounds (sat/vB) of the fee-rate histogram buckets
+pub const FEE_BUCKETS: [f64; 12] = [
+    0.0, 1.0, 2.0, 3.0, 5.0, 8.0, 13.0, 21.0, 34.0, 55.0, 89.0, 144.0,
+];
+
+#[derive(Debug, Clone)]
+pub struct MempoolInfo {
+    pub size: u64,
+    pub dynamic_usage: u64,
+    pub histogram: Vec<FeeBucket>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FeeBucket {
+    pub min_fee_rate: f64,
+    pub count: u64,
+    pub vsize: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MempoolTx {
+    pub txid: Txid,
+    pub vsize: usize,
+    pub output_value: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum MempoolEvent {
+    Added(MempoolTx),
+    Removed(MempoolTx, RemovalReason),
+}
+
+// Mirrors MemPoolRemovalReason in Bitcoin Core
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalReason {
+    Expiry,
+    SizeLimit,
+    Reorg,
+    Block,
+    Conflict,
+    Replaced,
+    Unknown,
+}
+
+impl From<i32> for RemovalReason {
+    fn from(reason: i32) -> Self {
+        match reason {
+            0 => RemovalReason::Expiry,
+            1 => RemovalReason::SizeLimit,
+            2 => RemovalReason::Reorg,
+            3 => RemovalReason::Block,
+            4 => RemovalReason::Conflict,
+            5 => RemovalReason::Replaced,
+            _ => RemovalReason::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for RemovalReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            RemovalReason::Expiry => "expiry",
+            RemovalReason::SizeLimit => "sizelimit",
+            RemovalReason::Reorg => "reorg",
+            RemovalReason::Block => "block",
+            RemovalReason::Conflict => "conflict",
+            RemovalReason::Replaced => "replaced",
+            RemovalReason::Unknown => "unknown",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl MempoolTx {
+    fn from_bytes(bytes: &[u8]) -> Result<Self, bitcoin::consensus::encode::Error> {
+        let tx: Transaction = deserialize(bytes)?;
+        Ok(Self {
+            txid: tx.compute_txid(),
+            vsize: tx.vsize(),
+            output_value: tx.output.iter().map(|o| o.value.to_sat()).sum(),
+        })
+    }
+}
+
+// Server side of ChainNotifications, forwarding mempool changes to the GUI
+pub struct MempoolNotifications {
+    events: mpsc::UnboundedSender<AppEvent>,
+}
+
+impl MempoolNotifications {
+    pub fn new(events: mpsc::UnboundedSender<AppEvent>) -> Self {
+        Self { events }
+    }
+
+    fn send(&self, event: MempoolEvent) {
+        let _ = self.events.send(AppEvent::Mempool(event));
+    }
+}
+
+impl chain_notifications::Server for MempoolNotifications {
+    fn destroy(
+        &mut self,
+        _: chain_notifications::DestroyParams,
+        _: chain_notifications::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn transaction_added_to_mempool(
+        &mut self,
+        params: chain_notifications::TransactionAddedToMempoolParams,
+        _: chain_notifications::TransactionAddedToMempoolResults,
+    ) -> Promise<(), capnp::Error> {
+        let tx = pry!(pry!(params.get()).get_tx());
+        match MempoolTx::from_bytes(tx) {
+            Ok(tx) => self.send(MempoolEvent::Added(tx)),
+            Err(e) => eprintln!("Failed to decode mempool transaction: {}", e),
+        }
+        Promise::ok(())
+    }
+
+    fn transaction_removed_from_mempool(
+        &mut self,
+        params: chain_notifications::TransactionRemovedFromMempoolParams,
+        _: chain_notifications::TransactionRemovedFromMempoolResults,
+    ) -> Promise<(), capnp::Error> {
+        let params = pry!(params.get());
+        let reason = RemovalReason::from(params.get_reason());
+        match MempoolTx::from_bytes(pry!(params.get_tx())) {
+            Ok(tx) => self.send(MempoolEvent::Removed(tx, reason)),
+            Err(e) => eprintln!("Failed to decode mempool transaction: {}", e),
+        }
+        Promise::ok(())
+    }
+
+    fn block_connected(
+        &mut self,
+        _: chain_notifications::BlockConnectedParams,
+        _: chain_notifications::BlockConnectedResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn block_disconnected(
+        &mut self,
+        _: chain_notifications::BlockDisconnectedParams,
+        _: chain_notifications::BlockDisconnectedResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn updated_block_tip(
+        &mut self,
+        _: chain_notifications::UpdatedBlockTipParams,
+        _: chain_notifications::UpdatedBlockTipResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn chain_state_flushed(
+        &mut self,
+        _: chain_notifications::ChainStateFlushedParams,
+        _: chain_notifications::ChainStateFlushedResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+}
+
+// Replay the current mempool contents and subscribe to further changes
+pub async fn subscribe_mempool(
+    chain_client: &chain::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    events: mpsc::UnboundedSender<AppEvent>,
+) -> Result<handler::Client, Box<dyn std::error::Error>> {
+    let notifications: chain_notifications::Client =
+        capnp_rpc::new_client(MempoolNotifications::new(events));
+
+    // Register before replaying, so transactions arriving in between are not
+    // missed. One may then be reported twice, which the feed tolerates.
+    let mut handle_notifications_request = chain_client.handle_notifications_request();
+    handle_notifications_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    handle_notifications_request
+        .get()
+        .set_notifications(notifications.clone());
+    let handle_notifications_response = handle_notifications_request.send().promise.await?;
+
+    let mut request_mempool_request = chain_client.request_mempool_transactions_request();
+    request_mempool_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    request_mempool_request
+        .get()
+        .set_notifications(notifications);
+    request_mempool_request.send().promise.await?;
+
+    Ok(handle_notifications_response.get()?.get_result()?)
+}
+
+// Fetch mempool size, memory usage and a fee-rate histogram
+pub async fn get_mempool_info(
+    node_client: &node_capnp::node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<MempoolInfo, Box<dyn std::error::Error>> {
+    let mut mempool_size_request = node_client.get_mempool_size_request();
+    mempool_size_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let mempool_size_response = mempool_size_request.send().promise.await?;
+    let size = mempool_size_response.get()?.get_result();
+
+    let mut dynamic_usage_request = node_client.get_mempool_dynamic_usage_request();
+    dynamic_usage_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let dynamic_usage_response = dynamic_usage_request.send().promise.await?;
+    let dynamic_usage = dynamic_usage_response.get()?.get_result();
+
+    // There is no IPC method exposing per-entry fees, so ask the RPC layer
+    let entries = execute_rpc(node_client, thread_client, "getrawmempool", json!([true])).await?;
+    let histogram = fee_histogram(&entries);
+
+    Ok(MempoolInfo {
+        size,
+        dynamic_usage,
+        histogram,
+    })
+}
+
+fn fee_histogram(entries: &serde_json::Value) -> Vec<FeeBucket> {
+    let mut buckets: Vec<FeeBucket> = FEE_BUCKETS
+        .iter()
+        .map(|&min_fee_rate| FeeBucket {
+            min_fee_rate,
+            ..Default::default()
+        })
+        .collect();
+
+    if let Some(entries) = entries.as_object() {
+        for entry in entries.values() {
+            let vsize = entry["vsize"].as_u64().unwrap_or(0);
+            let fee_btc = entry["fees"]["base"].as_f64().unwrap_or(0.0);
+            if vsize == 0 {
+                continue;
+            }
+            let fee_rate = fee_btc * 100_000_000.0 / vsize as f64;
+            let index = FEE_BUCKETS
+                .iter()
+                .rposition(|&min| fee_rate >= min)
+                .unwrap_or(0);
+            buckets[index].count += 1;
+            buckets[index].vsize += vsize;
+        }
+    }
+
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(transactions: &[(f64, u64)]) -> serde_json::Value {
+        let entries: serde_json::Map<_, _> = transactions
+            .iter()
+            .enumerate()
+            .map(|(i, &(fee_btc, vsize))| {
+                (
+                    format!("{:064x}", i),
+                    json!({ "vsize": vsize, "fees": { "base": fee_btc } }),
+                )
+            })
+            .collect();
+        serde_json::Value::Object(entries)
+    }
+
+    #[test]
+    fn histogram_has_a_bucket_per_bound() {
+        let histogram = fee_histogram(&json!({}));
+        assert_eq!(histogram.len(), FEE_BUCKETS.len());
+        assert!(histogram
+            .iter()
+            .zip(FEE_BUCKETS)
+            .all(|(bucket, min)| bucket.min_fee_rate == min && bucket.count == 0));
+        assert!(FEE_BUCKETS.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn histogram_buckets_by_fee_rate() {
+        let histogram = fee_histogram(&entries(&[
+            // 0.5 sat/vB
+            (0.00000100, 200),
+            // Exactly 1 sat/vB belongs to the bucket starting there
+            (0.00000200, 200),
+            // 4.99 sat/vB
+            (0.00000499, 100),
+            // 5 sat/vB
+            (0.00000500, 100),
+            // 1000 sat/vB, above the last bound
+            (0.00100000, 100),
+            // Skipped without a size
+            (0.00001000, 0),
+        ]));
+        let counts: Vec<(f64, u64, u64)> = histogram
+            .iter()
+            .filter(|bucket| bucket.count > 0)
+            .map(|bucket| (bucket.min_fee_rate, bucket.count, bucket.vsize))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                (0.0, 1, 200),
+                (1.0, 1, 200),
+                (3.0, 1, 100),
+                (5.0, 1, 100),
+                (144.0, 1, 100),
+            ]
+        );
+    }
+}
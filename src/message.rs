@@ -0,0 +1,64 @@
+use crate::rpc::execute_rpc;
+use bitcoin::hashes::Hash;
+use bitcoin::Address;
+use bitcoin_ipc::node_capnp::node;
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::wallet;
+use serde_json::json;
+
+// Mirrors SigningResult in Bitcoin Core
+fn signing_error_message(result: i32) -> String {
+    match result {
+        1 => "Private key for the address is not available (is the wallet locked?)".to_string(),
+        2 => "Message signing failed".to_string(),
+        _ => format!("Message signing failed with code {}", result),
+    }
+}
+
+// Sign with the key behind a legacy address, returning the base64 signature
+pub async fn sign_message(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    address: &Address,
+    message: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    // Message signatures (BIP137 without extensions) only commit to a key hash
+    let pkhash = address
+        .pubkey_hash()
+        .ok_or("Only legacy (P2PKH) addresses can sign messages")?;
+
+    let mut sign_request = wallet_client.sign_message_request();
+    sign_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    sign_request.get().set_message(message);
+    sign_request.get().set_pkhash(pkhash.as_byte_array());
+    let sign_response = sign_request.send().promise.await?;
+    let sign_response = sign_response.get()?;
+    let result = sign_response.get_result();
+    if result != 0 {
+        return Err(signing_error_message(result).into());
+    }
+    Ok(sign_response.get_str_sig()?.to_string()?)
+}
+
+// Check a signature with the node's verifymessage RPC
+pub async fn verify_message(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    address: &Address,
+    signature: &str,
+    message: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let result = execute_rpc(
+        node_client,
+        thread_client,
+        "verifymessage",
+        json!([address.to_string(), signature.trim(), message]),
+    )
+    .await?;
+    result
+        .as_bool()
+        .ok_or_else(|| "Unexpected verifymessage result".into())
+}
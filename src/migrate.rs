@@ -0,0 +1,195 @@
+use crate::backup::{backup_wallet, get_wallet_dir};
+use crate::wallet::list_wallet_dir;
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::{wallet, wallet_loader};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+// Outcome of migrating a legacy wallet to descriptors
+#[derive(Debug, Clone)]
+pub struct MigrationReport {
+    pub wallet: String,
+    // Scripts the legacy wallet watched but could not spend
+    pub watch_only_wallet: Option<String>,
+    // Scripts the legacy wallet could sign for but did not treat as its own
+    pub solvables_wallet: Option<String>,
+    // The node makes its own backup too, in the wallet directory
+    pub node_backup: PathBuf,
+}
+
+pub async fn is_legacy(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut is_legacy_request = wallet_client.is_legacy_request();
+    is_legacy_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let is_legacy_response = is_legacy_request.send().promise.await?;
+    Ok(is_legacy_response.get()?.get_result())
+}
+
+// Legacy wallets in the wallet directory, and those that could not be checked
+#[derive(Debug, Clone, Default)]
+pub struct LegacyScan {
+    pub legacy: Vec<String>,
+    pub failed: Vec<(String, String)>,
+}
+
+// File headers of the two wallet database formats. Berkeley DB keeps its btree
+// magic number at offset 12, in the byte order of the machine that wrote it.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+const BDB_BTREE_MAGIC: u32 = 0x00053162;
+
+// Old wallets can be bare files at the top of the wallet directory
+fn wallet_file(wallet_dir: &Path, name: &str) -> PathBuf {
+    let wallet_path = wallet_dir.join(name);
+    if wallet_path.is_dir() {
+        wallet_path.join("wallet.dat")
+    } else {
+        wallet_path
+    }
+}
+
+// Legacy wallets are Berkeley DB files and descriptor wallets SQLite ones. The
+// experimental Berkeley DB descriptor wallets of 0.21 are taken for legacy, and
+// the node refuses to migrate them.
+fn is_legacy_file(path: &Path) -> Result<bool, String> {
+    let mut header = [0u8; 16];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if &header == SQLITE_HEADER {
+        return Ok(false);
+    }
+    let magic = [header[12], header[13], header[14], header[15]];
+    if u32::from_le_bytes(magic) == BDB_BTREE_MAGIC || u32::from_be_bytes(magic) == BDB_BTREE_MAGIC
+    {
+        return Ok(true);
+    }
+    Err(format!("{} is not a wallet database", path.display()))
+}
+
+// Loaded wallets are asked whether they are legacy. Closed ones are told apart
+// by their file, as loading them would need legacy support the node may lack.
+pub async fn find_legacy_wallets(
+    wallet_loader_client: &wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    loaded: &[(String, wallet::Client)],
+) -> Result<LegacyScan, Box<dyn std::error::Error>> {
+    let wallet_dir = get_wallet_dir(wallet_loader_client, thread_client).await?;
+    let mut scan = LegacyScan::default();
+    for name in list_wallet_dir(wallet_loader_client, thread_client).await? {
+        let result = match loaded.iter().find(|(loaded, _)| *loaded == name) {
+            Some((_, wallet_client)) => is_legacy(wallet_client, thread_client)
+                .await
+                .map_err(|e| e.to_string()),
+            // The node runs on this machine, as the IPC socket is local
+            None => is_legacy_file(&wallet_file(&wallet_dir, &name)),
+        };
+        match result {
+            Ok(true) => scan.legacy.push(name),
+            Ok(false) => {}
+            Err(e) => scan.failed.push((name, e)),
+        }
+    }
+    Ok(scan)
+}
+
+// Back up a legacy wallet before migrating it. A loaded wallet is backed up by
+// the node; otherwise its file is copied from the wallet directory.
+pub async fn backup_legacy_wallet(
+    wallet_loader_client: &wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    wallet_client: Option<&wallet::Client>,
+    name: &str,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(wallet_client) = wallet_client {
+        return backup_wallet(wallet_client, thread_client, path).await;
+    }
+    let wallet_dir = get_wallet_dir(wallet_loader_client, thread_client).await?;
+    let wallet_file = wallet_file(&wallet_dir, name);
+    fs::copy(&wallet_file, path)
+        .map_err(|e| format!("Failed to copy {}: {}", wallet_file.display(), e))?;
+    Ok(())
+}
+
+// Convert a legacy wallet to a descriptor wallet. The node unloads the wallet
+// if needed and loads the migrated one, which keeps the same name.
+pub async fn migrate_wallet(
+    wallet_loader_client: &wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    name: &str,
+    passphrase: &Zeroizing<String>,
+) -> Result<(wallet::Client, MigrationReport), Box<dyn std::error::Error>> {
+    let mut migrate_request = wallet_loader_client.migrate_wallet_request();
+    migrate_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    migrate_request.get().set_name(name);
+    migrate_request.get().set_passphrase(passphrase.as_bytes());
+    let migrate_response = migrate_request.send().promise.await?;
+    let migrate_response = migrate_response.get()?;
+    let error = migrate_response.get_error()?.get_original()?.to_string()?;
+    if !error.is_empty() {
+        return Err(error.into());
+    }
+
+    let result = migrate_response.get_result()?;
+    let watch_only_wallet = if result.get_has_watchonly_wallet_name() {
+        Some(result.get_watchonly_wallet_name()?.to_string()?)
+    } else {
+        None
+    };
+    let solvables_wallet = if result.get_has_solvables_wallet_name() {
+        Some(result.get_solvables_wallet_name()?.to_string()?)
+    } else {
+        None
+    };
+    let report = MigrationReport {
+        wallet: name.to_string(),
+        watch_only_wallet,
+        solvables_wallet,
+        node_backup: PathBuf::from(result.get_backup_path()?.to_str()?),
+    };
+    Ok((result.get_wallet()?, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(name: &str, contents: &[u8]) -> Result<bool, String> {
+        let path =
+            std::env::temp_dir().join(format!("frost_byte_{}_{}.dat", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        let result = is_legacy_file(&path);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn tells_wallet_formats_apart() {
+        let mut sqlite = SQLITE_HEADER.to_vec();
+        sqlite.resize(4096, 0);
+        assert_eq!(check("sqlite", &sqlite), Ok(false));
+
+        let mut bdb = vec![0u8; 4096];
+        bdb[12..16].copy_from_slice(&BDB_BTREE_MAGIC.to_le_bytes());
+        assert_eq!(check("bdb_le", &bdb), Ok(true));
+        bdb[12..16].copy_from_slice(&BDB_BTREE_MAGIC.to_be_bytes());
+        assert_eq!(check("bdb_be", &bdb), Ok(true));
+
+        assert!(check("other", &[0u8; 4096])
+            .unwrap_err()
+            .contains("not a wallet database"));
+        assert!(check("short", b"SQLite")
+            .unwrap_err()
+            .contains("Failed to read"));
+    }
+}
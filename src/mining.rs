@@ -0,0 +1,300 @@
+use crate::fee::FeeRate;
+use bitcoin::block::Block;
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::{Amount, BlockHash, Script, Transaction, Weight};
+use bitcoin_ipc::init_capnp::init;
+use bitcoin_ipc::mining_capnp::{block_template, mining};
+use bitcoin_ipc::proxy_capnp;
+
+// Bitcoin Core's BlockCreateOptions defaults; fields left unset would be sent as 0
+const COINBASE_MAX_ADDITIONAL_WEIGHT: u64 = 4000;
+const COINBASE_OUTPUT_MAX_ADDITIONAL_SIGOPS: u64 = 400;
+
+// MAX_BLOCK_SIGOPS_COST in Bitcoin Core
+pub const MAX_BLOCK_SIGOPS_COST: i64 = 80_000;
+
+// The node's best block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockTip {
+    pub hash: BlockHash,
+    pub height: i32,
+}
+
+// Create Mining client
+pub async fn create_mining_client(
+    init_client: &init::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<mining::Client, Box<dyn std::error::Error>> {
+    let mut make_mining_request = init_client.make_mining_request();
+    make_mining_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let mining_client_response = make_mining_request.send().promise.await?;
+    Ok(mining_client_response.get()?.get_result()?)
+}
+
+// None while the node has no chain yet
+pub async fn get_tip(
+    mining_client: &mining::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Option<BlockTip>, Box<dyn std::error::Error>> {
+    let mut tip_request = mining_client.get_tip_request();
+    tip_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let tip_response = tip_request.send().promise.await?;
+    let tip = tip_response.get()?;
+    if !tip.get_has_result() {
+        return Ok(None);
+    }
+    let tip = tip.get_result()?;
+    Ok(Some(BlockTip {
+        hash: deserialize(tip.get_hash()?)?,
+        height: tip.get_height(),
+    }))
+}
+
+// A template's block with what the node worked out while selecting its transactions
+#[derive(Debug, Clone)]
+pub struct BlockTemplate {
+    pub block: Block,
+    // Fee and sigop cost of each transaction after the coinbase
+    pub fees: Vec<Amount>,
+    pub sigops: Vec<i64>,
+    // Sigop cost of the coinbase
+    pub coinbase_sigops: i64,
+}
+
+// A transaction the node selected for the next block
+#[derive(Debug, Clone)]
+pub struct TemplateTx<'a> {
+    pub tx: &'a Transaction,
+    pub fee: Amount,
+    pub sigops: i64,
+}
+
+impl TemplateTx<'_> {
+    pub fn weight(&self) -> Weight {
+        self.tx.weight()
+    }
+
+    pub fn fee_rate(&self) -> FeeRate {
+        let vsize = self.tx.vsize() as u64;
+        FeeRate::from_sat_per_kvb(self.fee.to_sat() * 1000 / vsize.max(1))
+    }
+}
+
+impl BlockTemplate {
+    // Selected transactions in block order, without the coinbase
+    pub fn transactions(&self) -> impl Iterator<Item = TemplateTx<'_>> {
+        self.block
+            .txdata
+            .iter()
+            .skip(1)
+            .zip(self.fees.iter().zip(&self.sigops))
+            .map(|(tx, (fee, sigops))| TemplateTx {
+                tx,
+                fee: *fee,
+                sigops: *sigops,
+            })
+    }
+
+    pub fn total_fees(&self) -> Amount {
+        self.fees.iter().copied().sum()
+    }
+
+    // Subsidy plus fees, as paid out by the coinbase
+    pub fn coinbase_value(&self) -> Amount {
+        self.block
+            .txdata
+            .first()
+            .map(|coinbase| coinbase.output.iter().map(|output| output.value).sum())
+            .unwrap_or(Amount::ZERO)
+    }
+
+    pub fn total_sigops(&self) -> i64 {
+        self.coinbase_sigops + self.sigops.iter().sum::<i64>()
+    }
+}
+
+async fn new_template(
+    mining_client: &mining::Client,
+    script_pubkey: &Script,
+    use_mempool: bool,
+) -> Result<block_template::Client, Box<dyn std::error::Error>> {
+    let mut create_request = mining_client.create_new_block_request();
+    create_request
+        .get()
+        .set_script_pub_key(script_pubkey.as_bytes());
+    let mut options = create_request.get().init_options();
+    options.set_use_mempool(use_mempool);
+    options.set_coinbase_max_additional_weight(COINBASE_MAX_ADDITIONAL_WEIGHT);
+    options.set_coinbase_output_max_additional_sigops(COINBASE_OUTPUT_MAX_ADDITIONAL_SIGOPS);
+    let create_response = create_request.send().promise.await?;
+    Ok(create_response.get()?.get_result()?)
+}
+
+async fn template_block(
+    template: &block_template::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Block, Box<dyn std::error::Error>> {
+    let mut block_request = template.get_block_request();
+    block_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let block_response = block_request.send().promise.await?;
+    Ok(deserialize(block_response.get()?.get_result()?)?)
+}
+
+// A block on the current tip, paying its coinbase to `script_pubkey`. The
+// header's merkle root and nonce are left for the miner to fill in.
+pub async fn create_new_block(
+    mining_client: &mining::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    script_pubkey: &Script,
+    use_mempool: bool,
+) -> Result<Block, Box<dyn std::error::Error>> {
+    let template = new_template(mining_client, script_pubkey, use_mempool).await?;
+    template_block(&template, thread_client).await
+}
+
+// A template from the mempool with its per-transaction fees and sigops
+pub async fn get_block_template(
+    mining_client: &mining::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    script_pubkey: &Script,
+) -> Result<BlockTemplate, Box<dyn std::error::Error>> {
+    let template = new_template(mining_client, script_pubkey, true).await?;
+    let block = template_block(&template, thread_client).await?;
+
+    let mut fees_request = template.get_tx_fees_request();
+    fees_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let fees_response = fees_request.send().promise.await?;
+    let mut fees: Vec<i64> = fees_response.get()?.get_result()?.iter().collect();
+
+    let mut sigops_request = template.get_tx_sigops_request();
+    sigops_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let sigops_response = sigops_request.send().promise.await?;
+    let mut sigops: Vec<i64> = sigops_response.get()?.get_result()?.iter().collect();
+
+    // Older nodes list the coinbase first, with minus the total fees
+    if fees.len() == block.txdata.len() {
+        fees.remove(0);
+    }
+    let coinbase_sigops = if sigops.len() == block.txdata.len() {
+        sigops.remove(0)
+    } else {
+        0
+    };
+    if fees.len() + 1 != block.txdata.len() || sigops.len() != fees.len() {
+        return Err("Template fees and sigops do not match its transactions".into());
+    }
+    Ok(BlockTemplate {
+        block,
+        fees: fees
+            .into_iter()
+            .map(|fee| Amount::from_sat(fee.max(0) as u64))
+            .collect(),
+        sigops,
+        coinbase_sigops,
+    })
+}
+
+// Check a block against the tip without its proof of work. Returns the reason
+// the node rejected it, or None if it is valid.
+pub async fn test_block_validity(
+    mining_client: &mining::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    block: &Block,
+    check_merkle_root: bool,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut test_request = mining_client.test_block_validity_request();
+    test_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    test_request.get().set_block(&serialize(block));
+    test_request.get().set_check_merkle_root(check_merkle_root);
+    let test_response = test_request.send().promise.await?;
+    let test = test_response.get()?;
+    if test.get_result() {
+        return Ok(None);
+    }
+    let state = test.get_state()?;
+    let reason = state.get_reject_reason()?.to_string()?;
+    let debug = state.get_debug_message()?.to_string()?;
+    Ok(Some(if debug.is_empty() {
+        reason
+    } else {
+        format!("{} ({})", reason, debug)
+    }))
+}
+
+// Hand a solved block to the node. Returns false if it already had the block.
+pub async fn process_new_block(
+    mining_client: &mining::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    block: &Block,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut process_request = mining_client.process_new_block_request();
+    process_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    process_request.get().set_block(&serialize(block));
+    let process_response = process_request.send().promise.await?;
+    let process = process_response.get()?;
+    if !process.get_result() {
+        return Err(format!("The node rejected block {}", block.block_hash()).into());
+    }
+    Ok(process.get_new_block())
+}
+
+// Grind the header nonce until the block hash meets its target, moving the
+// time on whenever the nonces run out. Only practical at regtest difficulty.
+pub fn solve_block(block: &mut Block) {
+    let target = block.header.target();
+    loop {
+        if block.header.validate_pow(target).is_ok() {
+            return;
+        }
+        match block.header.nonce.checked_add(1) {
+            Some(nonce) => block.header.nonce = nonce,
+            None => {
+                block.header.nonce = 0;
+                block.header.time += 1;
+            }
+        }
+    }
+}
+
+// Mine one block on the current tip with the local CPU and submit it
+pub async fn mine_block(
+    mining_client: &mining::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    script_pubkey: &Script,
+) -> Result<BlockHash, Box<dyn std::error::Error>> {
+    let mut block = create_new_block(mining_client, thread_client, script_pubkey, true).await?;
+    block.header.merkle_root = block
+        .compute_merkle_root()
+        .ok_or("Block template has no transactions")?;
+    if let Some(reason) = test_block_validity(mining_client, thread_client, &block, true).await? {
+        return Err(format!("Block template is invalid: {}", reason).into());
+    }
+    let block = tokio::task::spawn_blocking(move || {
+        solve_block(&mut block);
+        block
+    })
+    .await?;
+    process_new_block(mining_client, thread_client, &block).await?;
+    Ok(block.block_hash())
+}
@@ -0,0 +1,326 @@
+use crate::gui::AppEvent;
+use bitcoin::Network;
+use bitcoin_ipc::handler_capnp::handler;
+use bitcoin_ipc::node_capnp::{
+    init_message_callback, node, notify_alert_changed_callback, show_node_progress_callback,
+};
+use bitcoin_ipc::proxy_capnp;
+use capnp::capability::Promise;
+use capnp_rpc::pry;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone)]
+pub struct NodeStatus {
+    pub initial_block_download: bool,
+    pub loading_blocks: bool,
+    pub blocks: i32,
+    pub headers: i32,
+    pub verification_progress: f64,
+    pub network: Network,
+}
+
+impl NodeStatus {
+    // Wallet actions are enabled once the node has loaded its blocks and left
+    // initial block download. A fresh regtest node stays in IBD until the first
+    // block is mined, and a node whose blocks have caught up with its headers
+    // has nothing left to download, so neither waits for IBD to end.
+    pub fn is_ready(&self) -> bool {
+        let synced = !self.initial_block_download
+            || self.network == Network::Regtest
+            || (self.headers > 0 && self.blocks >= self.headers);
+        !self.loading_blocks && synced
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub title: String,
+    pub percent: i32,
+}
+
+impl Progress {
+    // Bitcoin Core signals the end of an operation with 100%
+    pub fn is_finished(&self) -> bool {
+        self.percent >= 100
+    }
+}
+
+// Server side of InitMessageCallback
+pub struct InitMessageHandler {
+    events: mpsc::UnboundedSender<AppEvent>,
+}
+
+impl init_message_callback::Server for InitMessageHandler {
+    fn destroy(
+        &mut self,
+        _: init_message_callback::DestroyParams,
+        _: init_message_callback::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn call(
+        &mut self,
+        params: init_message_callback::CallParams,
+        _: init_message_callback::CallResults,
+    ) -> Promise<(), capnp::Error> {
+        let message = pry!(pry!(pry!(params.get()).get_message()).to_string());
+        let _ = self.events.send(AppEvent::InitMessage(message));
+        Promise::ok(())
+    }
+}
+
+// Server side of ShowNodeProgressCallback
+pub struct ShowProgressHandler {
+    events: mpsc::UnboundedSender<AppEvent>,
+}
+
+impl show_node_progress_callback::Server for ShowProgressHandler {
+    fn destroy(
+        &mut self,
+        _: show_node_progress_callback::DestroyParams,
+        _: show_node_progress_callback::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn call(
+        &mut self,
+        params: show_node_progress_callback::CallParams,
+        _: show_node_progress_callback::CallResults,
+    ) -> Promise<(), capnp::Error> {
+        let params = pry!(params.get());
+        let progress = Progress {
+            title: pry!(pry!(params.get_title()).to_string()),
+            percent: params.get_progress(),
+        };
+        let _ = self.events.send(AppEvent::Progress(progress));
+        Promise::ok(())
+    }
+}
+
+// Server side of NotifyAlertChangedCallback, which carries no payload so the
+// warnings are re-fetched on each notification
+pub struct AlertChangedHandler {
+    node_client: node::Client,
+    thread_client: proxy_capnp::thread::Client,
+    events: mpsc::UnboundedSender<AppEvent>,
+}
+
+impl notify_alert_changed_callback::Server for AlertChangedHandler {
+    fn destroy(
+        &mut self,
+        _: notify_alert_changed_callback::DestroyParams,
+        _: notify_alert_changed_callback::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn call(
+        &mut self,
+        _: notify_alert_changed_callback::CallParams,
+        _: notify_alert_changed_callback::CallResults,
+    ) -> Promise<(), capnp::Error> {
+        let node_client = self.node_client.clone();
+        let thread_client = self.thread_client.clone();
+        let events = self.events.clone();
+        tokio::task::spawn_local(async move {
+            match get_warnings(&node_client, &thread_client).await {
+                Ok(warnings) => {
+                    let _ = events.send(AppEvent::Warnings(warnings));
+                }
+                Err(e) => eprintln!("Failed to get warnings: {}", e),
+            }
+        });
+        Promise::ok(())
+    }
+}
+
+// Subscribe to init messages and progress updates from the node
+pub async fn subscribe_node_status(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    events: mpsc::UnboundedSender<AppEvent>,
+) -> Result<Vec<handler::Client>, Box<dyn std::error::Error>> {
+    let mut handle_init_message_request = node_client.handle_init_message_request();
+    handle_init_message_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    handle_init_message_request
+        .get()
+        .set_fn(capnp_rpc::new_client(InitMessageHandler {
+            events: events.clone(),
+        }));
+    let init_message_response = handle_init_message_request.send().promise.await?;
+
+    let mut handle_show_progress_request = node_client.handle_show_progress_request();
+    handle_show_progress_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    handle_show_progress_request
+        .get()
+        .set_fn(capnp_rpc::new_client(ShowProgressHandler { events }));
+    let show_progress_response = handle_show_progress_request.send().promise.await?;
+
+    Ok(vec![
+        init_message_response.get()?.get_result()?,
+        show_progress_response.get()?.get_result()?,
+    ])
+}
+
+pub async fn get_node_status(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    network: Network,
+) -> Result<NodeStatus, Box<dyn std::error::Error>> {
+    let mut ibd_request = node_client.is_initial_block_download_request();
+    ibd_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let ibd_response = ibd_request.send().promise.await?;
+
+    let mut loading_blocks_request = node_client.is_loading_blocks_request();
+    loading_blocks_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let loading_blocks_response = loading_blocks_request.send().promise.await?;
+
+    let mut num_blocks_request = node_client.get_num_blocks_request();
+    num_blocks_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let num_blocks_response = num_blocks_request.send().promise.await?;
+
+    let mut header_tip_request = node_client.get_header_tip_request();
+    header_tip_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let header_tip_response = header_tip_request.send().promise.await?;
+    let header_tip = header_tip_response.get()?;
+
+    let mut progress_request = node_client.get_verification_progress_request();
+    progress_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let progress_response = progress_request.send().promise.await?;
+
+    let blocks = num_blocks_response.get()?.get_result();
+    Ok(NodeStatus {
+        initial_block_download: ibd_response.get()?.get_result(),
+        loading_blocks: loading_blocks_response.get()?.get_result(),
+        blocks,
+        headers: if header_tip.get_result() {
+            header_tip.get_height()
+        } else {
+            blocks
+        },
+        verification_progress: progress_response.get()?.get_result(),
+        network,
+    })
+}
+
+// Fetch the node's current warnings, one entry per warning
+pub async fn get_warnings(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut warnings_request = node_client.get_warnings_request();
+    warnings_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let warnings_response = warnings_request.send().promise.await?;
+    let warnings = warnings_response.get()?.get_result()?;
+    Ok(split_warnings(warnings.get_translated()?.to_str()?))
+}
+
+// Multiple warnings are joined with an HTML rule by GetWarnings()
+fn split_warnings(warnings: &str) -> Vec<String> {
+    warnings
+        .split("<hr />")
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// Subscribe to changes in the node's warnings
+pub async fn subscribe_alerts(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    events: mpsc::UnboundedSender<AppEvent>,
+) -> Result<handler::Client, Box<dyn std::error::Error>> {
+    let mut handle_alert_request = node_client.handle_notify_alert_changed_request();
+    handle_alert_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    handle_alert_request
+        .get()
+        .set_fn(capnp_rpc::new_client(AlertChangedHandler {
+            node_client: node_client.clone(),
+            thread_client: thread_client.clone(),
+            events,
+        }));
+    let handle_alert_response = handle_alert_request.send().promise.await?;
+    Ok(handle_alert_response.get()?.get_result()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(network: Network, ibd: bool, blocks: i32, headers: i32) -> NodeStatus {
+        NodeStatus {
+            initial_block_download: ibd,
+            loading_blocks: false,
+            blocks,
+            headers,
+            verification_progress: 0.5,
+            network,
+        }
+    }
+
+    #[test]
+    fn not_ready_during_initial_block_download() {
+        assert!(!status(Network::Bitcoin, true, 100, 800_000).is_ready());
+        // Before any headers arrive there is nothing to compare against
+        assert!(!status(Network::Signet, true, 0, 0).is_ready());
+        assert!(status(Network::Bitcoin, false, 800_000, 800_000).is_ready());
+    }
+
+    #[test]
+    fn ready_without_blocks_to_download() {
+        assert!(status(Network::Regtest, true, 0, 0).is_ready());
+        assert!(status(Network::Testnet, true, 1000, 1000).is_ready());
+    }
+
+    #[test]
+    fn not_ready_while_loading_blocks() {
+        let mut status = status(Network::Regtest, false, 10, 10);
+        status.loading_blocks = true;
+        assert!(!status.is_ready());
+    }
+
+    #[test]
+    fn split_warnings_on_html_rule() {
+        assert_eq!(
+            split_warnings("This is a pre-release test build <hr /> Disk space is low! <hr /> "),
+            vec!["This is a pre-release test build", "Disk space is low!"]
+        );
+        assert_eq!(split_warnings("Single warning"), vec!["Single warning"]);
+    }
+
+    #[test]
+    fn split_warnings_empty() {
+        assert!(split_warnings("").is_empty());
+        assert!(split_warnings(" <hr /> ").is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::send::{CoinControl, Recipient};
+use crate::wallet::create_transaction;
+use bitcoin::opcodes::all::{OP_CHECKMULTISIG, OP_PUSHNUM_1, OP_PUSHNUM_16};
+use bitcoin::psbt::{Input, Psbt};
+use bitcoin::script::Instruction;
+use bitcoin::{Script, Transaction, TxOut};
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::wallet;
+use std::str::FromStr;
+
+// SIGHASH_ALL, as used by Bitcoin Core's GUI
+const SIGHASH_ALL: i32 = 1;
+
+// Magic bytes at the start of a binary PSBT (BIP174)
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+
+// The wallet's view of a PSBT after updating or signing it
+#[derive(Debug, Clone)]
+pub struct FilledPsbt {
+    pub psbt: Psbt,
+    pub complete: bool,
+    // Inputs the wallet added signatures to
+    pub signed: usize,
+}
+
+// Signature progress of a single input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputStatus {
+    pub signatures: usize,
+    // Only known for single-key and bare multisig scripts
+    pub required: Option<usize>,
+    pub finalized: bool,
+}
+
+// Mirrors PSBTError in Bitcoin Core
+fn psbt_error_message(error: i32) -> String {
+    let message = match error {
+        0 => "Inputs missing or spent",
+        1 => "Specified sighash value does not match value stored in PSBT",
+        2 => "External signer not found",
+        3 => "External signer failed to sign",
+        4 => "Signer does not support PSBT",
+        5 => "Input needs additional signatures or other data",
+        _ => return format!("PSBT error {}", error),
+    };
+    message.to_string()
+}
+
+// Accept both binary PSBT files and base64 text, as exported by Bitcoin Core
+pub fn decode_psbt(data: &[u8]) -> Result<Psbt, String> {
+    if data.starts_with(PSBT_MAGIC) {
+        return Psbt::deserialize(data).map_err(|e| format!("Invalid PSBT: {}", e));
+    }
+    let text = std::str::from_utf8(data).map_err(|_| "Not a PSBT file".to_string())?;
+    Psbt::from_str(text.trim()).map_err(|e| format!("Invalid PSBT: {}", e))
+}
+
+// Merge signatures and data from PSBTs for the same transaction
+pub fn combine_psbts(mut psbt: Psbt, others: Vec<Psbt>) -> Result<Psbt, String> {
+    for other in others {
+        psbt.combine(other)
+            .map_err(|e| format!("Failed to combine PSBTs: {}", e))?;
+    }
+    Ok(psbt)
+}
+
+// Extract the network transaction once every input has its final scripts
+pub fn extract_transaction(psbt: &Psbt) -> Result<Transaction, String> {
+    if let Some(i) = psbt.inputs.iter().position(|input| !is_finalized(input)) {
+        return Err(format!("Input {} is not finalized", i));
+    }
+    // The fee rate is checked by the node when the transaction is broadcast
+    Ok(psbt.clone().extract_tx_unchecked_fee_rate())
+}
+
+fn is_finalized(input: &Input) -> bool {
+    input.final_script_sig.is_some() || input.final_script_witness.is_some()
+}
+
+// The threshold of an m-of-n OP_CHECKMULTISIG script
+fn multisig_threshold(script: &Script) -> Option<usize> {
+    let instructions: Vec<Instruction> = script.instructions().collect::<Result<_, _>>().ok()?;
+    let (Some(Instruction::Op(first)), Some(Instruction::Op(last))) =
+        (instructions.first(), instructions.last())
+    else {
+        return None;
+    };
+    let pushnum = OP_PUSHNUM_1.to_u8()..=OP_PUSHNUM_16.to_u8();
+    if *last != OP_CHECKMULTISIG || !pushnum.contains(&first.to_u8()) {
+        return None;
+    }
+    Some((first.to_u8() - OP_PUSHNUM_1.to_u8() + 1) as usize)
+}
+
+pub fn input_status(input: &Input) -> InputStatus {
+    let signatures = input.partial_sigs.len()
+        + input.tap_script_sigs.len()
+        + usize::from(input.tap_key_sig.is_some());
+    let required = match (&input.witness_script, &input.redeem_script) {
+        (Some(script), _) => multisig_threshold(script),
+        (None, Some(script)) if !script.is_p2wpkh() => multisig_threshold(script),
+        _ => Some(1),
+    };
+    InputStatus {
+        signatures,
+        required,
+        finalized: is_finalized(input),
+    }
+}
+
+// Update (and optionally sign) a PSBT with everything the wallet knows
+pub async fn fill_psbt(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    psbt: &Psbt,
+    sign: bool,
+) -> Result<FilledPsbt, Box<dyn std::error::Error>> {
+    let mut fill_request = wallet_client.fill_psbt_request();
+    fill_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    fill_request.get().set_sighash_type(SIGHASH_ALL);
+    fill_request.get().set_sign(sign);
+    // Include key origins so other signers can find their keys
+    fill_request.get().set_bip32derivs(true);
+    fill_request.get().set_want_n_signed(true);
+    fill_request.get().set_psbtx(&psbt.serialize());
+    let fill_response = fill_request.send().promise.await?;
+    let fill_response = fill_response.get()?;
+    if fill_response.get_has_result() {
+        return Err(psbt_error_message(fill_response.get_result()).into());
+    }
+    Ok(FilledPsbt {
+        psbt: Psbt::deserialize(fill_response.get_psbtx()?)?,
+        complete: fill_response.get_complete(),
+        signed: fill_response.get_n_signed() as usize,
+    })
+}
+
+// Fund a transaction without signing it, for signing elsewhere
+pub async fn create_psbt(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    recipients: &[Recipient],
+    coin_control: &CoinControl,
+) -> Result<Psbt, Box<dyn std::error::Error>> {
+    let proposal = create_transaction(
+        wallet_client,
+        thread_client,
+        recipients,
+        coin_control,
+        false,
+    )
+    .await?;
+    let psbt = Psbt::from_unsigned_tx(proposal.tx)?;
+    // Adds the spent outputs and derivation paths signers need
+    let filled = fill_psbt(wallet_client, thread_client, &psbt, false).await?;
+    Ok(filled.psbt)
+}
+
+// The output an input spends, if the PSBT carries it
+pub fn spent_output(psbt: &Psbt, index: usize) -> Option<&TxOut> {
+    let input = psbt.inputs.get(index)?;
+    if let Some(utxo) = &input.witness_utxo {
+        return Some(utxo);
+    }
+    let vout = psbt.unsigned_tx.input.get(index)?.previous_output.vout;
+    input.non_witness_utxo.as_ref()?.output.get(vout as usize)
+}
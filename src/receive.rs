@@ -0,0 +1,146 @@
+use crate::address::{bip21_uri, AddressPurpose, OutputType};
+use crate::wallet::{
+    get_address_receive_requests, get_new_destination, set_address_book,
+    set_address_receive_request,
+};
+use bitcoin::{Address, Amount, Network};
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A payment request, persisted in the wallet as JSON via setAddressReceiveRequest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiveRequest {
+    pub id: String,
+    pub address: String,
+    pub label: String,
+    pub message: String,
+    pub amount: Option<u64>,
+    // Unix time in seconds
+    pub created: u64,
+}
+
+impl ReceiveRequest {
+    pub fn amount(&self) -> Option<Amount> {
+        self.amount.map(Amount::from_sat)
+    }
+
+    pub fn uri(&self, network: Network) -> Option<String> {
+        let address = self.address.parse::<Address<_>>().ok()?;
+        let address = address.require_network(network).ok()?;
+        Some(bip21_uri(
+            &address,
+            self.amount(),
+            &self.label,
+            &self.message,
+        ))
+    }
+}
+
+// Derive a new address and record the request against it
+pub async fn create_receive_request(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    network: Network,
+    output_type: OutputType,
+    label: &str,
+    amount: Option<Amount>,
+    message: &str,
+) -> Result<ReceiveRequest, Box<dyn std::error::Error>> {
+    let script = get_new_destination(wallet_client, thread_client, output_type, label).await?;
+    let address = Address::from_script(&script, network)?;
+
+    let created = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let request = ReceiveRequest {
+        id: created.as_millis().to_string(),
+        address: address.to_string(),
+        label: label.to_string(),
+        message: message.to_string(),
+        amount: amount.map(|amount| amount.to_sat()),
+        created: created.as_secs(),
+    };
+    let value = serde_json::to_string(&request)?;
+    set_address_receive_request(wallet_client, thread_client, &script, &request.id, &value).await?;
+    Ok(request)
+}
+
+// Requests stored by other clients use their own encoding and are skipped
+pub async fn list_receive_requests(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Vec<ReceiveRequest>, Box<dyn std::error::Error>> {
+    let mut requests: Vec<ReceiveRequest> =
+        get_address_receive_requests(wallet_client, thread_client)
+            .await?
+            .iter()
+            .filter_map(|value| serde_json::from_str(value).ok())
+            .collect();
+    requests.sort_by(|a, b| b.created.cmp(&a.created));
+    Ok(requests)
+}
+
+// Change the address book label of a requested address
+pub async fn relabel_receive_request(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    network: Network,
+    request: &ReceiveRequest,
+    label: &str,
+) -> Result<ReceiveRequest, Box<dyn std::error::Error>> {
+    let address = request
+        .address
+        .parse::<Address<_>>()?
+        .require_network(network)?;
+    let script = address.script_pubkey();
+    set_address_book(
+        wallet_client,
+        thread_client,
+        &script,
+        label,
+        AddressPurpose::Receive,
+    )
+    .await?;
+
+    let request = ReceiveRequest {
+        label: label.to_string(),
+        ..request.clone()
+    };
+    let value = serde_json::to_string(&request)?;
+    set_address_receive_request(wallet_client, thread_client, &script, &request.id, &value).await?;
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(address: &str) -> ReceiveRequest {
+        ReceiveRequest {
+            id: "1".to_string(),
+            address: address.to_string(),
+            label: "Rent".to_string(),
+            message: String::new(),
+            amount: Some(100_000),
+            created: 0,
+        }
+    }
+
+    #[test]
+    fn uri_for_request() {
+        let request = request("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx");
+        assert_eq!(
+            request.uri(Network::Testnet).unwrap(),
+            "bitcoin:tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx?amount=0.001&label=Rent"
+        );
+    }
+
+    #[test]
+    fn uri_requires_matching_network() {
+        let request = request("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx");
+        assert!(request.uri(Network::Bitcoin).is_none());
+        assert!(self::request("not an address")
+            .uri(Network::Testnet)
+            .is_none());
+    }
+}
@@ -0,0 +1,81 @@
+use crate::store::WalletStore;
+use bitcoin_ipc::handler_capnp::handler;
+use bitcoin_ipc::wallet_capnp::wallet;
+use std::collections::BTreeMap;
+use tokio::task::AbortHandle;
+
+// A loaded wallet and the state frost_byte keeps alongside it
+pub struct WalletEntry {
+    pub client: wallet::Client,
+    // Keeps the wallet's state for the GUI from its notifications
+    pub store: Option<WalletStore>,
+    pub address_book_handler: Option<handler::Client>,
+    pub progress_handler: Option<handler::Client>,
+    pub relock_task: Option<AbortHandle>,
+}
+
+impl WalletEntry {
+    pub fn new(client: wallet::Client) -> Self {
+        Self {
+            client,
+            store: None,
+            address_book_handler: None,
+            progress_handler: None,
+            relock_task: None,
+        }
+    }
+
+    // Schedule a relock, replacing any earlier pending relock
+    pub fn set_relock_task(&mut self, relock: AbortHandle) {
+        if let Some(previous) = self.relock_task.replace(relock) {
+            previous.abort();
+        }
+    }
+
+    pub fn cancel_relock(&mut self) {
+        if let Some(relock) = self.relock_task.take() {
+            relock.abort();
+        }
+    }
+}
+
+impl Drop for WalletEntry {
+    fn drop(&mut self) {
+        self.cancel_relock();
+    }
+}
+
+// Loaded wallets keyed by wallet name
+#[derive(Default)]
+pub struct WalletRegistry {
+    entries: BTreeMap<String, WalletEntry>,
+}
+
+impl WalletRegistry {
+    // Keeps the existing entry if the wallet is already registered
+    pub fn insert(&mut self, name: String, client: wallet::Client) -> &mut WalletEntry {
+        self.entries
+            .entry(name)
+            .or_insert_with(|| WalletEntry::new(client))
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<WalletEntry> {
+        self.entries.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&WalletEntry> {
+        self.entries.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut WalletEntry> {
+        self.entries.get_mut(name)
+    }
+
+    pub fn client(&self, name: &str) -> Option<wallet::Client> {
+        self.entries.get(name).map(|entry| entry.client.clone())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.entries.keys().cloned().collect()
+    }
+}
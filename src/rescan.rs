@@ -0,0 +1,126 @@
+use crate::gui::AppEvent;
+use crate::node::Progress;
+use crate::rpc::execute_wallet_rpc;
+use bitcoin_ipc::handler_capnp::handler;
+use bitcoin_ipc::init_capnp::init;
+use bitcoin_ipc::node_capnp::node;
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::{show_wallet_progress_callback, wallet};
+use capnp::capability::Promise;
+use capnp_rpc::pry;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+// Blocks covered by a finished rescan
+#[derive(Debug, Clone, Copy)]
+pub struct RescanRange {
+    pub start_height: u32,
+    pub stop_height: Option<u32>,
+}
+
+// Server side of ShowWalletProgressCallback
+pub struct ShowWalletProgressHandler {
+    wallet: String,
+    events: mpsc::UnboundedSender<AppEvent>,
+}
+
+impl show_wallet_progress_callback::Server for ShowWalletProgressHandler {
+    fn destroy(
+        &mut self,
+        _: show_wallet_progress_callback::DestroyParams,
+        _: show_wallet_progress_callback::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn call(
+        &mut self,
+        params: show_wallet_progress_callback::CallParams,
+        _: show_wallet_progress_callback::CallResults,
+    ) -> Promise<(), capnp::Error> {
+        let params = pry!(params.get());
+        let progress = Progress {
+            title: pry!(pry!(params.get_title()).to_string()),
+            percent: params.get_progress(),
+        };
+        let _ = self
+            .events
+            .send(AppEvent::WalletProgress(self.wallet.clone(), progress));
+        Promise::ok(())
+    }
+}
+
+pub async fn subscribe_wallet_progress(
+    wallet: &str,
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    events: mpsc::UnboundedSender<AppEvent>,
+) -> Result<handler::Client, Box<dyn std::error::Error>> {
+    let mut handle_request = wallet_client.handle_show_progress_request();
+    handle_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    handle_request
+        .get()
+        .set_fn(capnp_rpc::new_client(ShowWalletProgressHandler {
+            wallet: wallet.to_string(),
+            events,
+        }));
+    let handle_response = handle_request.send().promise.await?;
+    Ok(handle_response.get()?.get_result()?)
+}
+
+// A separate server thread for a long-running call. Calls on one thread run in
+// order, so an abort sent on the main thread would otherwise wait for the rescan.
+pub async fn make_thread(
+    init_client: &init::Client,
+    name: &str,
+) -> Result<proxy_capnp::thread::Client, Box<dyn std::error::Error>> {
+    let construct_response = init_client.construct_request().send().promise.await?;
+    let thread_map = construct_response.get()?.get_thread_map()?;
+    let mut make_thread_request = thread_map.make_thread_request();
+    make_thread_request.get().set_name(name);
+    let make_thread_response = make_thread_request.send().promise.await?;
+    Ok(make_thread_response.get()?.get_result()?)
+}
+
+// Rescan the chain from `start_height` (the genesis block if unset) for wallet
+// transactions. Blocks until the rescan finishes or is aborted.
+pub async fn rescan_blockchain(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    wallet: &str,
+    start_height: Option<u32>,
+) -> Result<RescanRange, Box<dyn std::error::Error>> {
+    let result = execute_wallet_rpc(
+        node_client,
+        thread_client,
+        wallet,
+        "rescanblockchain",
+        json!([start_height.unwrap_or(0)]),
+    )
+    .await?;
+    let start_height = result["start_height"]
+        .as_u64()
+        .ok_or("Missing start_height in rescanblockchain result")?;
+    Ok(RescanRange {
+        start_height: start_height as u32,
+        // Absent when the rescan stopped before reaching the tip
+        stop_height: result["stop_height"].as_u64().map(|height| height as u32),
+    })
+}
+
+// Ask a running rescan to stop, returning false if none was running
+pub async fn abort_rescan(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut abort_request = wallet_client.abort_rescan_request();
+    abort_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let abort_response = abort_request.send().promise.await?;
+    Ok(abort_response.get()?.get_result())
+}
@@ -0,0 +1,70 @@
+use crate::address::percent_encode;
+use bitcoin_ipc::node_capnp;
+use bitcoin_ipc::proxy_capnp;
+use serde_json::Value;
+
+// UniValue::VType tags as used by the IPC UniValue struct
+const VNULL: i32 = 0;
+const VOBJ: i32 = 1;
+const VARR: i32 = 2;
+const VSTR: i32 = 3;
+const VNUM: i32 = 4;
+const VBOOL: i32 = 5;
+
+// Call an RPC method through the Node interface, for functionality with no direct IPC method
+pub async fn execute_rpc(
+    node_client: &node_capnp::node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    command: &str,
+    params: Value,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    execute_rpc_with_uri(node_client, thread_client, command, params, "").await
+}
+
+// Call a wallet RPC method, selecting the wallet by its URI like bitcoin-cli -rpcwallet
+pub async fn execute_wallet_rpc(
+    node_client: &node_capnp::node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    wallet: &str,
+    command: &str,
+    params: Value,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let uri = format!("/wallet/{}", percent_encode(wallet));
+    execute_rpc_with_uri(node_client, thread_client, command, params, &uri).await
+}
+
+async fn execute_rpc_with_uri(
+    node_client: &node_capnp::node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    command: &str,
+    params: Value,
+    uri: &str,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let mut execute_rpc_request = node_client.execute_rpc_request();
+    execute_rpc_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    execute_rpc_request.get().set_command(command);
+    execute_rpc_request.get().set_uri(uri);
+    {
+        let mut univalue = execute_rpc_request.get().init_params();
+        univalue.set_type(VARR);
+        univalue.set_value(params.to_string().as_str());
+    }
+
+    let execute_rpc_response = execute_rpc_request.send().promise.await?;
+    let result = execute_rpc_response.get()?.get_result()?;
+    univalue_to_json(result.get_type(), result.get_value()?.to_str()?)
+}
+
+// Scalars are sent as their bare string value, objects and arrays as JSON
+fn univalue_to_json(vtype: i32, value: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    match vtype {
+        VNULL => Ok(Value::Null),
+        VSTR => Ok(Value::String(value.to_string())),
+        VBOOL => Ok(Value::Bool(value == "1" || value == "true")),
+        VNUM | VOBJ | VARR => Ok(serde_json::from_str(value)?),
+        _ => Err(format!("Unknown UniValue type: {}", vtype).into()),
+    }
+}
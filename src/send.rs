@@ -0,0 +1,90 @@
+use crate::fee::{FeeMode, FeeRate};
+use bitcoin::consensus::serialize;
+use bitcoin::{Address, Amount, OutPoint, Transaction, TxOut};
+use bitcoin_ipc::wallet_capnp;
+
+pub struct Recipient {
+    pub address: Address,
+    pub amount: Amount,
+    pub subtract_fee: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeeChoice {
+    // An explicit fee rate, overriding the wallet's estimate
+    Rate(FeeRate),
+    // Let the wallet estimate a rate confirming within this many blocks
+    Target(u32, FeeMode),
+}
+
+// Mirrors the subset of CCoinControl frost_byte exposes
+#[derive(Debug, Clone)]
+pub struct CoinControl {
+    pub fee: FeeChoice,
+    pub signal_rbf: bool,
+    // Coins chosen by hand; when set, the wallet spends exactly these
+    pub inputs: Vec<OutPoint>,
+}
+
+// Mirrors FeeEstimateMode in Bitcoin Core
+fn fee_estimate_mode(mode: FeeMode) -> i32 {
+    match mode {
+        FeeMode::Economical => 1,
+        FeeMode::Conservative => 2,
+    }
+}
+
+pub(crate) fn write_coin_control(
+    mut builder: wallet_capnp::coin_control::Builder,
+    coin_control: &CoinControl,
+) {
+    match coin_control.fee {
+        FeeChoice::Rate(rate) => {
+            builder.set_override_fee_rate(true);
+            builder.set_has_fee_rate(true);
+            builder.set_fee_rate(&rate.to_bytes());
+        }
+        FeeChoice::Target(target, mode) => {
+            builder.set_has_confirm_target(true);
+            builder.set_confirm_target(target as i32);
+            builder.set_fee_mode(fee_estimate_mode(mode));
+        }
+    }
+    builder.set_has_signal_rbf(true);
+    builder.set_signal_rbf(coin_control.signal_rbf);
+    builder.set_allow_other_inputs(coin_control.inputs.is_empty());
+    let mut selected = builder.init_set_selected(coin_control.inputs.len() as u32);
+    for (i, outpoint) in coin_control.inputs.iter().enumerate() {
+        selected.set(i as u32, &serialize(outpoint));
+    }
+}
+
+// A transaction built by the wallet, awaiting confirmation before broadcast
+#[derive(Debug, Clone)]
+pub struct TxProposal {
+    pub tx: Transaction,
+    pub fee: Amount,
+    pub change_pos: Option<usize>,
+}
+
+impl TxProposal {
+    pub fn change(&self) -> Option<&TxOut> {
+        self.tx.output.get(self.change_pos?)
+    }
+
+    pub fn fee_rate(&self) -> FeeRate {
+        let vsize = self.tx.vsize() as u64;
+        FeeRate::from_sat_per_kvb(self.fee.to_sat() * 1000 / vsize.max(1))
+    }
+
+    // Amount leaving the wallet, excluding change
+    pub fn sent(&self) -> Amount {
+        self.tx
+            .output
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != self.change_pos)
+            .map(|(_, output)| output.value)
+            .sum()
+    }
+}
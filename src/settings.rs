@@ -0,0 +1,59 @@
+use crate::backup::BackupSchedule;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+// User preferences persisted between runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Categories passed as `-debug=<category>` to spawned nodes
+    pub debug_categories: Vec<String>,
+    // Automatic wallet backups, off when unset
+    pub backup_schedule: Option<BackupSchedule>,
+    // HWI-compatible program used to talk to hardware signers
+    pub signer_command: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            debug_categories: vec!["ipc".to_string()],
+            backup_schedule: None,
+            signer_command: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("frost_byte")
+            .join("settings.json")
+    }
+
+    // Missing or unreadable settings fall back to the defaults
+    pub fn load() -> Self {
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Change one setting on disk, keeping any other page's changes since we loaded
+    pub fn update(change: impl FnOnce(&mut Settings)) -> anyhow::Result<()> {
+        let mut settings = Self::load();
+        change(&mut settings);
+        settings.save()
+    }
+}
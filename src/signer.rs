@@ -0,0 +1,249 @@
+use crate::address::write_destination;
+use crate::rpc::execute_wallet_rpc;
+use bitcoin::bip32::Fingerprint;
+use bitcoin::psbt::Psbt;
+use bitcoin::{Address, Network};
+use bitcoin_ipc::node_capnp::node;
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::wallet;
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::str::FromStr;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+// A device found by `enumerate`, together with the command that talks to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalSigner {
+    // HWI or another program speaking its command line protocol
+    pub command: String,
+    pub fingerprint: String,
+    pub name: String,
+}
+
+// Output descriptors a signer offers for an account
+#[derive(Debug, Clone, Default)]
+pub struct SignerDescriptors {
+    pub receive: Vec<String>,
+    pub internal: Vec<String>,
+}
+
+// Run the signer command with `args`, feeding it `stdin` if given, and parse
+// the JSON it prints. Like Bitcoin Core's -signer, the command may carry its own
+// arguments, e.g. "python3 fake_signer.py".
+async fn run_signer(
+    command: &str,
+    args: &[&str],
+    stdin: Option<&str>,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let mut words = command.split_whitespace();
+    let program = words.next().ok_or("No signer command configured")?;
+    let mut child = Command::new(program)
+        .args(words)
+        .args(args)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run signer {}: {}", program, e))?;
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(input.as_bytes()).await?;
+        // Closing stdin tells the signer the request is complete
+        drop(pipe);
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Signer failed ({}): {}", output.status, stderr.trim()).into());
+    }
+    let result: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Signer printed invalid JSON: {}", e))?;
+    if let Some(error) = result.get("error").filter(|error| !error.is_null()) {
+        let error = error
+            .as_str()
+            .map_or_else(|| error.to_string(), str::to_string);
+        return Err(format!("Signer error: {}", error).into());
+    }
+    Ok(result)
+}
+
+fn chain_args(network: Network) -> [&'static str; 2] {
+    ["--chain", network.to_core_arg()]
+}
+
+// Devices connected to this machine, as listed by `<command> enumerate`
+pub async fn enumerate_signers(
+    command: &str,
+    network: Network,
+) -> Result<Vec<ExternalSigner>, Box<dyn std::error::Error>> {
+    let mut args = chain_args(network).to_vec();
+    args.push("enumerate");
+    let result = run_signer(command, &args, None).await?;
+    let devices = result
+        .as_array()
+        .ok_or("Signer did not return a device list")?;
+    let mut signers = Vec::new();
+    for device in devices {
+        // Locked or uninitialized devices are listed with an error instead
+        if let Some(error) = device["error"].as_str() {
+            eprintln!("Skipping signer: {}", error);
+            continue;
+        }
+        let fingerprint = device["fingerprint"]
+            .as_str()
+            .ok_or("Signer is missing a fingerprint")?;
+        Fingerprint::from_str(fingerprint)
+            .map_err(|e| format!("Invalid signer fingerprint {}: {}", fingerprint, e))?;
+        let name = ["model", "type"]
+            .iter()
+            .find_map(|field| device[field].as_str().filter(|name| !name.is_empty()))
+            .unwrap_or("Unknown device");
+        signers.push(ExternalSigner {
+            command: command.to_string(),
+            fingerprint: fingerprint.to_string(),
+            name: name.to_string(),
+        });
+    }
+    Ok(signers)
+}
+
+impl ExternalSigner {
+    fn args<'a>(&'a self, network: Network, rest: &[&'a str]) -> Vec<&'a str> {
+        let mut args = vec!["--fingerprint", self.fingerprint.as_str()];
+        args.extend(chain_args(network));
+        args.extend(rest);
+        args
+    }
+
+    pub async fn get_descriptors(
+        &self,
+        network: Network,
+        account: u32,
+    ) -> Result<SignerDescriptors, Box<dyn std::error::Error>> {
+        let account = account.to_string();
+        let args = self.args(network, &["getdescriptors", "--account", &account]);
+        let result = run_signer(&self.command, &args, None).await?;
+        let strings = |field: &str| -> Result<Vec<String>, String> {
+            result[field]
+                .as_array()
+                .ok_or(format!("Signer returned no {} descriptors", field))?
+                .iter()
+                .map(|descriptor| {
+                    descriptor
+                        .as_str()
+                        .map(str::to_string)
+                        .ok_or(format!("Invalid {} descriptor", field))
+                })
+                .collect()
+        };
+        Ok(SignerDescriptors {
+            receive: strings("receive")?,
+            internal: strings("internal")?,
+        })
+    }
+
+    // Ask the device to show the address for `descriptor`, returning the
+    // address it reports so the user can compare it with the wallet's
+    pub async fn display_address(
+        &self,
+        network: Network,
+        descriptor: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let args = self.args(network, &["displayaddress", "--desc", descriptor]);
+        let result = run_signer(&self.command, &args, None).await?;
+        Ok(result["address"]
+            .as_str()
+            .ok_or("Signer did not return an address")?
+            .to_string())
+    }
+
+    // Sign with the device. The PSBT goes over stdin, as it can be too long for
+    // a command line, in the same form Bitcoin Core uses.
+    pub async fn sign_psbt(
+        &self,
+        network: Network,
+        psbt: &Psbt,
+    ) -> Result<Psbt, Box<dyn std::error::Error>> {
+        let fingerprint = Fingerprint::from_str(&self.fingerprint)?;
+        let ours = psbt.inputs.iter().any(|input| {
+            input
+                .bip32_derivation
+                .values()
+                .chain(input.tap_key_origins.values().map(|(_, origin)| origin))
+                .any(|(master, _)| *master == fingerprint)
+        });
+        if !ours {
+            return Err(format!("No inputs are derived from signer {}", self.fingerprint).into());
+        }
+        let mut args = vec!["--stdin"];
+        args.extend(self.args(network, &[]));
+        let request = format!("signtx {}", psbt);
+        let result = run_signer(&self.command, &args, Some(&request)).await?;
+        let signed = result["psbt"]
+            .as_str()
+            .ok_or("Signer did not return a PSBT")?;
+        Ok(Psbt::from_str(signed)?)
+    }
+}
+
+pub async fn has_external_signer(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut signer_request = wallet_client.has_external_signer_request();
+    signer_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let signer_response = signer_request.send().promise.await?;
+    Ok(signer_response.get()?.get_result())
+}
+
+// Show an address on the signer the node was started with (-signer)
+pub async fn node_display_address(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    address: &Address,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut display_request = wallet_client.display_address_request();
+    display_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    write_destination(display_request.get().init_dest(), &address.script_pubkey())?;
+    let display_response = display_request.send().promise.await?;
+    if !display_response.get()?.get_result() {
+        return Err("The node could not show the address on its signer".into());
+    }
+    Ok(())
+}
+
+// The wallet's descriptor for a single address, with key origins, which is
+// what a device needs to derive and show it
+pub async fn address_descriptor(
+    node_client: &node::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    wallet: &str,
+    address: &Address,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let info = execute_wallet_rpc(
+        node_client,
+        thread_client,
+        wallet,
+        "getaddressinfo",
+        json!([address.to_string()]),
+    )
+    .await?;
+    if info["ismine"].as_bool() != Some(true) {
+        return Err(format!("{} does not belong to wallet {}", address, wallet).into());
+    }
+    Ok(info["desc"]
+        .as_str()
+        .ok_or("The wallet has no descriptor for this address")?
+        .to_string())
+}
@@ -0,0 +1,472 @@
+use crate::gui::AppEvent;
+use crate::history::{get_wallet_tx, get_wallet_txs, TxEvent, TxRecord};
+use crate::wallet::{
+    can_get_addresses, get_balances, get_lock_state, get_watch_only, Balances, LockState, WatchOnly,
+};
+use bitcoin::consensus::deserialize;
+use bitcoin::Txid;
+use bitcoin_ipc::handler_capnp::handler;
+use bitcoin_ipc::proxy_capnp;
+use bitcoin_ipc::wallet_capnp::{
+    can_get_addresses_changed_callback, status_changed_callback, transaction_changed_callback,
+    unload_wallet_callback, wallet, watch_only_changed_callback,
+};
+use capnp::capability::Promise;
+use capnp_rpc::pry;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+
+// What frost_byte knows about a loaded wallet, kept current from its notifications
+#[derive(Debug, Clone)]
+pub struct WalletState {
+    pub balances: Balances,
+    pub lock_state: LockState,
+    pub watch_only: WatchOnly,
+    pub can_get_addresses: bool,
+    // Newest first
+    pub transactions: Vec<TxRecord>,
+}
+
+// A change to a wallet's state; only what actually changed is sent
+#[derive(Debug, Clone)]
+pub enum StateChange {
+    // The whole state, sent when the GUI subscribes
+    Snapshot(WalletState),
+    Balances(Balances),
+    LockState(LockState),
+    Transaction(TxEvent),
+    WatchOnly(bool),
+    CanGetAddresses(bool),
+    // The wallet was unloaded, possibly by another client; no more changes follow
+    Unloaded,
+}
+
+impl WalletState {
+    // Snapshot and Unloaded replace or drop the whole state, so they are left to the caller
+    pub fn apply(&mut self, change: StateChange) {
+        match change {
+            StateChange::Balances(balances) => self.balances = balances,
+            StateChange::LockState(lock_state) => self.lock_state = lock_state,
+            StateChange::Transaction(TxEvent::Updated(record)) => {
+                match self.transactions.iter_mut().find(|r| r.txid == record.txid) {
+                    Some(existing) => *existing = record,
+                    None => {
+                        self.transactions.push(record);
+                        self.transactions.sort_by(|a, b| b.time.cmp(&a.time));
+                    }
+                }
+            }
+            StateChange::Transaction(TxEvent::Deleted(txid)) => {
+                self.transactions.retain(|r| r.txid != txid)
+            }
+            StateChange::WatchOnly(have_watch_only) => {
+                self.watch_only.have_watch_only = have_watch_only
+            }
+            StateChange::CanGetAddresses(can_get_addresses) => {
+                self.can_get_addresses = can_get_addresses
+            }
+            StateChange::Snapshot(_) | StateChange::Unloaded => {}
+        }
+    }
+}
+
+// What a wallet notification asks the store to refetch
+enum Notification {
+    Status,
+    // True if the transaction was deleted
+    Transaction(Txid, bool),
+    WatchOnly(bool),
+    CanGetAddresses,
+    Unload,
+    // The GUI subscribed again and needs a snapshot
+    Resend,
+}
+
+// Server side of the wallet's change callbacks. The callbacks run on the IPC
+// connection, so they only queue the notification for the store to handle.
+#[derive(Clone)]
+struct StoreNotifier {
+    notifications: mpsc::UnboundedSender<Notification>,
+}
+
+impl StoreNotifier {
+    fn notify(&self, notification: Notification) -> Promise<(), capnp::Error> {
+        let _ = self.notifications.send(notification);
+        Promise::ok(())
+    }
+}
+
+impl status_changed_callback::Server for StoreNotifier {
+    fn destroy(
+        &mut self,
+        _: status_changed_callback::DestroyParams,
+        _: status_changed_callback::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn call(
+        &mut self,
+        _: status_changed_callback::CallParams,
+        _: status_changed_callback::CallResults,
+    ) -> Promise<(), capnp::Error> {
+        self.notify(Notification::Status)
+    }
+}
+
+impl transaction_changed_callback::Server for StoreNotifier {
+    fn destroy(
+        &mut self,
+        _: transaction_changed_callback::DestroyParams,
+        _: transaction_changed_callback::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn call(
+        &mut self,
+        params: transaction_changed_callback::CallParams,
+        _: transaction_changed_callback::CallResults,
+    ) -> Promise<(), capnp::Error> {
+        let params = pry!(params.get());
+        let txid: Txid = pry!(deserialize(pry!(params.get_txid()))
+            .map_err(|e| capnp::Error::failed(format!("Invalid txid: {}", e))));
+        // CT_DELETED
+        let deleted = params.get_status() == 2;
+        self.notify(Notification::Transaction(txid, deleted))
+    }
+}
+
+impl watch_only_changed_callback::Server for StoreNotifier {
+    fn destroy(
+        &mut self,
+        _: watch_only_changed_callback::DestroyParams,
+        _: watch_only_changed_callback::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn call(
+        &mut self,
+        params: watch_only_changed_callback::CallParams,
+        _: watch_only_changed_callback::CallResults,
+    ) -> Promise<(), capnp::Error> {
+        let params = pry!(params.get());
+        self.notify(Notification::WatchOnly(params.get_have_watch_only()))
+    }
+}
+
+impl can_get_addresses_changed_callback::Server for StoreNotifier {
+    fn destroy(
+        &mut self,
+        _: can_get_addresses_changed_callback::DestroyParams,
+        _: can_get_addresses_changed_callback::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn call(
+        &mut self,
+        _: can_get_addresses_changed_callback::CallParams,
+        _: can_get_addresses_changed_callback::CallResults,
+    ) -> Promise<(), capnp::Error> {
+        self.notify(Notification::CanGetAddresses)
+    }
+}
+
+impl unload_wallet_callback::Server for StoreNotifier {
+    fn destroy(
+        &mut self,
+        _: unload_wallet_callback::DestroyParams,
+        _: unload_wallet_callback::DestroyResults,
+    ) -> Promise<(), capnp::Error> {
+        Promise::ok(())
+    }
+
+    fn call(
+        &mut self,
+        _: unload_wallet_callback::CallParams,
+        _: unload_wallet_callback::CallResults,
+    ) -> Promise<(), capnp::Error> {
+        self.notify(Notification::Unload)
+    }
+}
+
+// A running store; dropping it stops the store and disconnects its callbacks
+pub struct WalletStore {
+    // Only held, as dropping a handler disconnects its callback
+    _handlers: Vec<handler::Client>,
+    notifications: mpsc::UnboundedSender<Notification>,
+    new_transactions: Arc<AtomicUsize>,
+    task: AbortHandle,
+}
+
+impl WalletStore {
+    // Send the whole state again, for a GUI which subscribes after the store started
+    pub fn resend(&self) {
+        let _ = self.notifications.send(Notification::Resend);
+    }
+
+    // Transactions the wallet gained since the store started; never goes down
+    pub fn new_transactions(&self) -> usize {
+        self.new_transactions.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for WalletStore {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn subscribe(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    notifier: &StoreNotifier,
+) -> Result<Vec<handler::Client>, Box<dyn std::error::Error>> {
+    let mut handlers = Vec::new();
+
+    let mut status_request = wallet_client.handle_status_changed_request();
+    status_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    status_request
+        .get()
+        .set_fn(capnp_rpc::new_client(notifier.clone()));
+    let status_response = status_request.send().promise.await?;
+    handlers.push(status_response.get()?.get_result()?);
+
+    let mut transaction_request = wallet_client.handle_transaction_changed_request();
+    transaction_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    transaction_request
+        .get()
+        .set_fn(capnp_rpc::new_client(notifier.clone()));
+    let transaction_response = transaction_request.send().promise.await?;
+    handlers.push(transaction_response.get()?.get_result()?);
+
+    let mut watch_only_request = wallet_client.handle_watch_only_changed_request();
+    watch_only_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    watch_only_request
+        .get()
+        .set_fn(capnp_rpc::new_client(notifier.clone()));
+    let watch_only_response = watch_only_request.send().promise.await?;
+    handlers.push(watch_only_response.get()?.get_result()?);
+
+    let mut addresses_request = wallet_client.handle_can_get_addresses_changed_request();
+    addresses_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    addresses_request
+        .get()
+        .set_fn(capnp_rpc::new_client(notifier.clone()));
+    let addresses_response = addresses_request.send().promise.await?;
+    handlers.push(addresses_response.get()?.get_result()?);
+
+    let mut unload_request = wallet_client.handle_unload_request();
+    unload_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    unload_request
+        .get()
+        .set_fn(capnp_rpc::new_client(notifier.clone()));
+    let unload_response = unload_request.send().promise.await?;
+    handlers.push(unload_response.get()?.get_result()?);
+
+    Ok(handlers)
+}
+
+async fn load_state(
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<WalletState, Box<dyn std::error::Error>> {
+    Ok(WalletState {
+        balances: get_balances(wallet_client, thread_client).await?,
+        lock_state: get_lock_state(wallet_client, thread_client).await?,
+        watch_only: get_watch_only(wallet_client, thread_client).await?,
+        can_get_addresses: can_get_addresses(wallet_client, thread_client).await?,
+        transactions: get_wallet_txs(wallet_client, thread_client).await?,
+    })
+}
+
+// Keeps a wallet's state and pushes changes to the GUI as the wallet's
+// notifications arrive. Must be called on the LocalSpawner thread, where the
+// store runs until the wallet is unloaded or the store is dropped.
+pub async fn start_wallet_store(
+    wallet: &str,
+    wallet_client: &wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    events: mpsc::UnboundedSender<AppEvent>,
+    on_unload: impl FnOnce() + 'static,
+) -> Result<WalletStore, Box<dyn std::error::Error>> {
+    let (notifications, receiver) = mpsc::unbounded_channel();
+    // Subscribe before the initial fetch so no change is missed
+    let notifier = StoreNotifier {
+        notifications: notifications.clone(),
+    };
+    let handlers = subscribe(wallet_client, thread_client, &notifier).await?;
+    let state = load_state(wallet_client, thread_client).await?;
+    let new_transactions = Arc::new(AtomicUsize::new(0));
+    let store = Store {
+        wallet: wallet.to_string(),
+        wallet_client: wallet_client.clone(),
+        thread_client: thread_client.clone(),
+        events,
+        state,
+        new_transactions: new_transactions.clone(),
+    };
+    let task = tokio::task::spawn_local(store.run(receiver, on_unload));
+    Ok(WalletStore {
+        _handlers: handlers,
+        notifications,
+        new_transactions,
+        task: task.abort_handle(),
+    })
+}
+
+struct Store {
+    wallet: String,
+    wallet_client: wallet::Client,
+    thread_client: proxy_capnp::thread::Client,
+    events: mpsc::UnboundedSender<AppEvent>,
+    state: WalletState,
+    new_transactions: Arc<AtomicUsize>,
+}
+
+impl Store {
+    async fn run(
+        mut self,
+        mut receiver: mpsc::UnboundedReceiver<Notification>,
+        on_unload: impl FnOnce(),
+    ) {
+        if !self.send(StateChange::Snapshot(self.state.clone())) {
+            return;
+        }
+        while let Some(notification) = receiver.recv().await {
+            // A block or a rescan touches many transactions at once, so
+            // everything queued so far is handled together
+            let mut batch = vec![notification];
+            while let Ok(notification) = receiver.try_recv() {
+                batch.push(notification);
+            }
+            let mut status = false;
+            let mut can_get_addresses = false;
+            let mut resend = false;
+            let mut transactions = BTreeMap::new();
+            for notification in batch {
+                match notification {
+                    Notification::Status => status = true,
+                    // Only the latest change to a transaction matters
+                    Notification::Transaction(txid, deleted) => {
+                        transactions.insert(txid, deleted);
+                    }
+                    Notification::WatchOnly(have_watch_only) => {
+                        if self.state.watch_only.have_watch_only != have_watch_only {
+                            self.change(StateChange::WatchOnly(have_watch_only));
+                        }
+                    }
+                    Notification::CanGetAddresses => can_get_addresses = true,
+                    Notification::Unload => {
+                        self.send(StateChange::Unloaded);
+                        on_unload();
+                        return;
+                    }
+                    Notification::Resend => resend = true,
+                }
+            }
+
+            if status {
+                self.refresh_lock_state().await;
+            }
+            if can_get_addresses {
+                self.refresh_can_get_addresses().await;
+            }
+            for (txid, deleted) in &transactions {
+                self.refresh_transaction(*txid, *deleted).await;
+            }
+            // Balances follow from transactions and, e.g. after a rescan, the status
+            if status || !transactions.is_empty() {
+                self.refresh_balances().await;
+            }
+            if resend && !self.send(StateChange::Snapshot(self.state.clone())) {
+                return;
+            }
+            if self.events.is_closed() {
+                return;
+            }
+        }
+    }
+
+    fn send(&self, change: StateChange) -> bool {
+        self.events
+            .send(AppEvent::WalletState(self.wallet.clone(), change))
+            .is_ok()
+    }
+
+    // Apply a change to the store's own state and pass it on
+    fn change(&mut self, change: StateChange) {
+        self.state.apply(change.clone());
+        self.send(change);
+    }
+
+    async fn refresh_lock_state(&mut self) {
+        match get_lock_state(&self.wallet_client, &self.thread_client).await {
+            Ok(lock_state) if lock_state != self.state.lock_state => {
+                self.change(StateChange::LockState(lock_state))
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to get lock state of {}: {}", self.wallet, e),
+        }
+    }
+
+    async fn refresh_can_get_addresses(&mut self) {
+        match can_get_addresses(&self.wallet_client, &self.thread_client).await {
+            Ok(can) if can != self.state.can_get_addresses => {
+                self.change(StateChange::CanGetAddresses(can))
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to check addresses of {}: {}", self.wallet, e),
+        }
+    }
+
+    async fn refresh_transaction(&mut self, txid: Txid, deleted: bool) {
+        let known = self.state.transactions.iter().find(|r| r.txid == txid);
+        if deleted {
+            if known.is_some() {
+                self.change(StateChange::Transaction(TxEvent::Deleted(txid)));
+            }
+            return;
+        }
+        match get_wallet_tx(&self.wallet_client, &self.thread_client, txid).await {
+            Ok(record) if known != Some(&record) => {
+                if known.is_none() {
+                    self.new_transactions.fetch_add(1, Ordering::Relaxed);
+                }
+                self.change(StateChange::Transaction(TxEvent::Updated(record)))
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to get transaction {}: {}", txid, e),
+        }
+    }
+
+    async fn refresh_balances(&mut self) {
+        match get_balances(&self.wallet_client, &self.thread_client).await {
+            Ok(balances) if balances != self.state.balances => {
+                self.change(StateChange::Balances(balances))
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to get balances of {}: {}", self.wallet, e),
+        }
+    }
+}
@@ -0,0 +1,634 @@
+use crate::mining::{create_new_block, get_tip, process_new_block, BlockTip};
+use bitcoin::block::{Block, Header};
+use bitcoin::consensus::serialize;
+use bitcoin::hashes::{sha256d, Hash, HashEngine};
+use bitcoin::hex::{DisplayHex, FromHex};
+use bitcoin::{BlockHash, Script, ScriptBuf, Target, Transaction, TxMerkleNode};
+use bitcoin_ipc::mining_capnp::mining;
+use bitcoin_ipc::proxy_capnp;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinSet;
+use tokio::time::{Duration, Instant};
+
+// Each miner gets a unique extranonce1 and rolls its own extranonce2 after it
+// in the coinbase's scriptSig
+const EXTRANONCE1_SIZE: usize = 4;
+const EXTRANONCE2_SIZE: usize = 4;
+const EXTRANONCE_SIZE: usize = EXTRANONCE1_SIZE + EXTRANONCE2_SIZE;
+// The Mining interface has no tip notification, so the tip is polled
+const TIP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+// Templates are rebuilt this often to pick up new mempool transactions
+const TEMPLATE_MAX_AGE: Duration = Duration::from_secs(30);
+// Older jobs on the same tip still take shares, so slow miners don't lose work
+const MAX_JOBS: usize = 8;
+// MAX_FUTURE_BLOCK_TIME in Bitcoin Core
+const MAX_FUTURE_BLOCK_TIME: u32 = 2 * 60 * 60;
+
+// Stratum error codes, as used by most pools
+const ERROR_OTHER: i64 = 20;
+const ERROR_JOB_NOT_FOUND: i64 = 21;
+const ERROR_DUPLICATE_SHARE: i64 = 22;
+const ERROR_LOW_DIFFICULTY: i64 = 23;
+const ERROR_UNAUTHORIZED: i64 = 24;
+const ERROR_NOT_SUBSCRIBED: i64 = 25;
+
+#[derive(Debug, Clone)]
+pub struct StratumConfig {
+    pub listen: SocketAddr,
+    // Every block found pays its coinbase here, whichever worker found it
+    pub script_pubkey: ScriptBuf,
+    // Share difficulty, lowered to the block's own where blocks are easier to find
+    pub difficulty: f64,
+}
+
+// Difficulty 1 is the largest mainnet target, the same scale the node uses
+fn target_for_difficulty(difficulty: f64) -> Target {
+    let target = 65535.0 * 2f64.powi(208) / difficulty;
+    if !target.is_finite() || target >= 2f64.powi(256) {
+        return Target::from_be_bytes([0xff; 32]);
+    }
+    let shift = target.log2().floor() as i32 - 52;
+    let mantissa = (target / 2f64.powi(shift)) as u64;
+    let mut bytes = [0; 32];
+    for bit in 0..64 {
+        let position = bit + shift;
+        if (mantissa >> bit) & 1 == 1 && (0..256).contains(&position) {
+            bytes[31 - (position / 8) as usize] |= 1 << (position % 8);
+        }
+    }
+    Target::from_be_bytes(bytes)
+}
+
+fn hash_pair(left: &sha256d::Hash, right: &sha256d::Hash) -> sha256d::Hash {
+    let mut engine = sha256d::Hash::engine();
+    engine.input(left.as_byte_array());
+    engine.input(right.as_byte_array());
+    sha256d::Hash::from_engine(engine)
+}
+
+// The hashes a miner combines with the coinbase's txid to get the merkle root
+fn merkle_branch(txdata: &[Transaction]) -> Vec<sha256d::Hash> {
+    let mut hashes: Vec<sha256d::Hash> = txdata
+        .iter()
+        .map(|tx| tx.compute_txid().to_raw_hash())
+        .collect();
+    let mut branch = Vec::new();
+    while hashes.len() > 1 {
+        if hashes.len() % 2 == 1 {
+            hashes.push(hashes[hashes.len() - 1]);
+        }
+        branch.push(hashes[1]);
+        hashes = hashes
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+    }
+    branch
+}
+
+fn with_extranonce(script_sig: &Script, extranonce: &[u8]) -> ScriptBuf {
+    let mut bytes = script_sig.to_bytes();
+    // A direct push, as the extranonce is shorter than OP_PUSHDATA1
+    bytes.push(extranonce.len() as u8);
+    bytes.extend_from_slice(extranonce);
+    ScriptBuf::from_bytes(bytes)
+}
+
+// Stratum sends the previous block hash as eight words with their bytes swapped
+fn stratum_prevhash(hash: &BlockHash) -> String {
+    let mut bytes = hash.to_byte_array();
+    for word in bytes.chunks_mut(4) {
+        word.reverse();
+    }
+    bytes.to_lower_hex_string()
+}
+
+fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+// A block template cut up the way stratum miners expect it
+struct Job {
+    id: String,
+    template: Block,
+    // The coinbase without its witness, before and after the extranonce
+    coinb1: Vec<u8>,
+    coinb2: Vec<u8>,
+    branch: Vec<sha256d::Hash>,
+    difficulty: f64,
+    share_target: Target,
+    // Shares already taken, by extranonce, ntime and nonce
+    shares: HashSet<(Vec<u8>, u32, u32)>,
+    created: Instant,
+}
+
+impl Job {
+    fn new(
+        id: String,
+        template: Block,
+        difficulty: f64,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let coinbase = template
+            .txdata
+            .first()
+            .ok_or("Block template has no coinbase")?;
+        let script_sig = &coinbase.input[0].script_sig;
+        let mut placeholder = coinbase.clone();
+        placeholder.input[0].script_sig = with_extranonce(script_sig, &[0; EXTRANONCE_SIZE]);
+        placeholder.input[0].witness.clear();
+        let bytes = serialize(&placeholder);
+
+        // Version, input count, prevout and scriptSig length come before the
+        // template's scriptSig and the extranonce's push opcode
+        let script_sig_len = placeholder.input[0].script_sig.len();
+        let start = 4 + 1 + 36 + 1 + script_sig.len() + 1;
+        if script_sig_len > 100 || bytes[41] as usize != script_sig_len {
+            return Err("Block template's coinbase has no room for an extranonce".into());
+        }
+
+        // Blocks easier than the share difficulty make every block a share
+        let block_target = template.header.target();
+        let difficulty = difficulty.min(block_target.difficulty_float());
+        let share_target = target_for_difficulty(difficulty).max(block_target);
+        Ok(Self {
+            id,
+            coinb1: bytes[..start].to_vec(),
+            coinb2: bytes[start + EXTRANONCE_SIZE..].to_vec(),
+            branch: merkle_branch(&template.txdata),
+            template,
+            difficulty,
+            share_target,
+            shares: HashSet::new(),
+            created: Instant::now(),
+        })
+    }
+
+    fn notify(&self, clean: bool) -> Value {
+        let header = &self.template.header;
+        json!([
+            self.id,
+            stratum_prevhash(&header.prev_blockhash),
+            self.coinb1.to_lower_hex_string(),
+            self.coinb2.to_lower_hex_string(),
+            self.branch
+                .iter()
+                .map(|hash| hash.to_byte_array().to_lower_hex_string())
+                .collect::<Vec<_>>(),
+            format!("{:08x}", header.version.to_consensus()),
+            format!("{:08x}", header.bits.to_consensus()),
+            format!("{:08x}", header.time),
+            clean,
+        ])
+    }
+
+    // The header a miner hashed, rebuilt from its share
+    fn header(&self, extranonce: &[u8], time: u32, nonce: u32) -> Header {
+        let coinbase = [&self.coinb1[..], extranonce, &self.coinb2[..]].concat();
+        let root = self
+            .branch
+            .iter()
+            .fold(sha256d::Hash::hash(&coinbase), |root, hash| {
+                hash_pair(&root, hash)
+            });
+        Header {
+            merkle_root: TxMerkleNode::from_raw_hash(root),
+            time,
+            nonce,
+            ..self.template.header
+        }
+    }
+
+    fn block(&self, extranonce: &[u8], header: Header) -> Block {
+        let mut block = self.template.clone();
+        let input = &mut block.txdata[0].input[0];
+        input.script_sig = with_extranonce(&input.script_sig, extranonce);
+        block.header = header;
+        block
+    }
+}
+
+#[derive(Default)]
+struct State {
+    tip: Option<BlockTip>,
+    // Newest last
+    jobs: Vec<Job>,
+    next_job: u64,
+    next_session: u32,
+    // Subscribed miners, by session
+    sessions: HashMap<u32, mpsc::UnboundedSender<String>>,
+}
+
+impl State {
+    fn broadcast(&self, message: String) {
+        for session in self.sessions.values() {
+            let _ = session.send(message.clone());
+        }
+    }
+}
+
+// What a miner has done on its connection so far
+struct Session {
+    id: u32,
+    peer: SocketAddr,
+    subscribed: bool,
+    worker: Option<String>,
+}
+
+impl Session {
+    fn extranonce1(&self) -> [u8; EXTRANONCE1_SIZE] {
+        self.id.to_be_bytes()
+    }
+}
+
+fn notification(method: &str, params: Value) -> String {
+    format!(
+        "{}\n",
+        json!({ "id": null, "method": method, "params": params })
+    )
+}
+
+fn response(id: &Value, result: Result<Value, (i64, String)>) -> String {
+    let message = match result {
+        Ok(result) => json!({ "id": id, "result": result, "error": null }),
+        Err((code, message)) => {
+            json!({ "id": id, "result": null, "error": [code, message, null] })
+        }
+    };
+    format!("{}\n", message)
+}
+
+struct Bridge {
+    mining_client: mining::Client,
+    thread_client: proxy_capnp::thread::Client,
+    config: StratumConfig,
+    state: RefCell<State>,
+    // Woken when a share solved a block, so miners move on without waiting for the poll
+    found_block: Notify,
+}
+
+impl Bridge {
+    // Hand out a new job when the tip moves or the current template gets old
+    async fn refresh(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let tip = get_tip(&self.mining_client, &self.thread_client).await?;
+        let (new_tip, expired) = {
+            let state = self.state.borrow();
+            let expired = match state.jobs.last() {
+                Some(job) => job.created.elapsed() >= TEMPLATE_MAX_AGE,
+                None => true,
+            };
+            (state.tip != tip, expired)
+        };
+        if !new_tip && !expired {
+            return Ok(());
+        }
+        let template = create_new_block(
+            &self.mining_client,
+            &self.thread_client,
+            &self.config.script_pubkey,
+            true,
+        )
+        .await?;
+
+        let mut state = self.state.borrow_mut();
+        state.next_job += 1;
+        let job = Job::new(
+            format!("{:x}", state.next_job),
+            template,
+            self.config.difficulty,
+        )?;
+        if new_tip {
+            if let Some(tip) = tip {
+                println!(
+                    "Stratum job {} on tip {} at height {}",
+                    job.id, tip.hash, tip.height
+                );
+            }
+            state.tip = tip;
+            state.jobs.clear();
+        } else if state.jobs.len() >= MAX_JOBS {
+            state.jobs.remove(0);
+        }
+        state.broadcast(notification(
+            "mining.set_difficulty",
+            json!([job.difficulty]),
+        ));
+        state.broadcast(notification("mining.notify", job.notify(new_tip)));
+        state.jobs.push(job);
+        Ok(())
+    }
+
+    async fn serve(self: Rc<Self>, stream: TcpStream, peer: SocketAddr) {
+        let mut session = {
+            let mut state = self.state.borrow_mut();
+            state.next_session += 1;
+            Session {
+                id: state.next_session,
+                peer,
+                subscribed: false,
+                worker: None,
+            }
+        };
+        let (reader, mut writer) = stream.into_split();
+        let (sender, mut outgoing) = mpsc::unbounded_channel::<String>();
+
+        let write = async move {
+            while let Some(message) = outgoing.recv().await {
+                if writer.write_all(message.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        };
+        let read = async {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let request: Value = match serde_json::from_str(&line) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        eprintln!("Invalid stratum request from {}: {}", peer, e);
+                        break;
+                    }
+                };
+                self.handle(&mut session, request, &sender).await;
+            }
+        };
+        tokio::select! {
+            _ = write => {}
+            _ = read => {}
+        }
+
+        self.state.borrow_mut().sessions.remove(&session.id);
+        println!("Stratum miner {} disconnected", peer);
+    }
+
+    async fn handle(
+        &self,
+        session: &mut Session,
+        request: Value,
+        sender: &mpsc::UnboundedSender<String>,
+    ) {
+        let id = &request["id"];
+        let params = &request["params"];
+        match request["method"].as_str().unwrap_or_default() {
+            "mining.subscribe" => {
+                let result = json!([
+                    [
+                        ["mining.set_difficulty", session.id.to_string()],
+                        ["mining.notify", session.id.to_string()],
+                    ],
+                    session.extranonce1().to_lower_hex_string(),
+                    EXTRANONCE2_SIZE,
+                ]);
+                let _ = sender.send(response(id, Ok(result)));
+                session.subscribed = true;
+
+                // Start the miner on the current job straight away
+                let mut state = self.state.borrow_mut();
+                if let Some(job) = state.jobs.last() {
+                    let _ = sender.send(notification(
+                        "mining.set_difficulty",
+                        json!([job.difficulty]),
+                    ));
+                    let _ = sender.send(notification("mining.notify", job.notify(true)));
+                }
+                state.sessions.insert(session.id, sender.clone());
+            }
+            "mining.authorize" => {
+                // Anyone may mine, the payout address is the bridge's own
+                let worker = params[0].as_str().unwrap_or_default().to_string();
+                println!("Stratum worker {} authorized from {}", worker, session.peer);
+                session.worker = Some(worker);
+                let _ = sender.send(response(id, Ok(json!(true))));
+            }
+            "mining.submit" => {
+                let result = self.submit(session, params).await;
+                let _ = sender.send(response(id, result.map(|()| json!(true))));
+            }
+            method => {
+                let error = (ERROR_OTHER, format!("Unknown method {}", method));
+                let _ = sender.send(response(id, Err(error)));
+            }
+        }
+    }
+
+    // Check a share against its job, and pass it on to the node if it solves a block
+    async fn submit(&self, session: &Session, params: &Value) -> Result<(), (i64, String)> {
+        if !session.subscribed {
+            return Err((ERROR_NOT_SUBSCRIBED, "Not subscribed".to_string()));
+        }
+        let Some(worker) = &session.worker else {
+            return Err((ERROR_UNAUTHORIZED, "Unauthorized worker".to_string()));
+        };
+        let param = |index: usize| params[index].as_str().unwrap_or_default();
+        let invalid = |what: &str| (ERROR_OTHER, format!("Invalid {}", what));
+        let extranonce2 = Vec::<u8>::from_hex(param(2)).map_err(|_| invalid("extranonce2"))?;
+        if extranonce2.len() != EXTRANONCE2_SIZE {
+            return Err(invalid("extranonce2"));
+        }
+        let time = u32::from_str_radix(param(3), 16).map_err(|_| invalid("ntime"))?;
+        let nonce = u32::from_str_radix(param(4), 16).map_err(|_| invalid("nonce"))?;
+        let extranonce = [&session.extranonce1()[..], &extranonce2].concat();
+
+        let solved = {
+            let mut state = self.state.borrow_mut();
+            let job = state
+                .jobs
+                .iter_mut()
+                .find(|job| job.id == param(1))
+                .ok_or_else(|| (ERROR_JOB_NOT_FOUND, "Job not found".to_string()))?;
+            if time < job.template.header.time || time > now() + MAX_FUTURE_BLOCK_TIME {
+                return Err(invalid("ntime"));
+            }
+            if !job.shares.insert((extranonce.clone(), time, nonce)) {
+                return Err((ERROR_DUPLICATE_SHARE, "Duplicate share".to_string()));
+            }
+            let header = job.header(&extranonce, time, nonce);
+            let hash = header.block_hash();
+            if !job.share_target.is_met_by(hash) {
+                return Err((ERROR_LOW_DIFFICULTY, "Low difficulty share".to_string()));
+            }
+            header
+                .target()
+                .is_met_by(hash)
+                .then(|| job.block(&extranonce, header))
+        };
+
+        let Some(block) = solved else {
+            return Ok(());
+        };
+        let hash = block.block_hash();
+        match process_new_block(&self.mining_client, &self.thread_client, &block).await {
+            Ok(_) => {
+                println!("Stratum worker {} found block {}", worker, hash);
+                self.found_block.notify_one();
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("Failed to submit block {}: {}", hash, e);
+                Err((ERROR_OTHER, e.to_string()))
+            }
+        }
+    }
+}
+
+// Serve stratum miners on `listener` until the task is aborted. Shares are
+// checked here, only blocks reach the node.
+pub async fn run_stratum(
+    mining_client: mining::Client,
+    thread_client: proxy_capnp::thread::Client,
+    listener: TcpListener,
+    config: StratumConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bridge = Rc::new(Bridge {
+        mining_client,
+        thread_client,
+        config,
+        state: RefCell::new(State::default()),
+        found_block: Notify::new(),
+    });
+    bridge.refresh().await?;
+
+    // Dropping the set when the server is aborted disconnects every miner
+    let mut sessions = JoinSet::new();
+    let mut poll = tokio::time::interval(TIP_POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, peer) = accepted?;
+                println!("Stratum miner connected from {}", peer);
+                sessions.spawn_local(bridge.clone().serve(stream, peer));
+            }
+            _ = poll.tick() => {
+                if let Err(e) = bridge.refresh().await {
+                    eprintln!("Failed to update stratum job: {}", e);
+                }
+            }
+            _ = bridge.found_block.notified() => {
+                if let Err(e) = bridge.refresh().await {
+                    eprintln!("Failed to update stratum job: {}", e);
+                }
+            }
+            Some(_) = sessions.join_next() => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::Network;
+
+    const EXTRANONCE: [u8; EXTRANONCE_SIZE] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    // The genesis block with extra transactions after its coinbase
+    fn template(transactions: u32) -> Block {
+        let mut block = genesis_block(Network::Bitcoin);
+        for lock_time in 1..=transactions {
+            let mut tx = block.txdata[0].clone();
+            tx.lock_time = LockTime::from_consensus(lock_time);
+            block.txdata.push(tx);
+        }
+        block.header.merkle_root = block.compute_merkle_root().unwrap();
+        block
+    }
+
+    // What a miner would submit for the template, with the merkle root
+    // computed over the whole block
+    fn solved(template: &Block) -> Block {
+        let mut block = template.clone();
+        let input = &mut block.txdata[0].input[0];
+        input.script_sig = with_extranonce(&input.script_sig, &EXTRANONCE);
+        block.header.merkle_root = block.compute_merkle_root().unwrap();
+        block
+    }
+
+    #[test]
+    fn difficulty_one_is_largest_mainnet_target() {
+        assert_eq!(target_for_difficulty(1.0), Target::MAX_ATTAINABLE_MAINNET);
+        assert_eq!(
+            target_for_difficulty(1.0).to_be_bytes()[..6],
+            [0, 0, 0, 0, 0xff, 0xff]
+        );
+        assert_eq!(
+            target_for_difficulty(2.0),
+            Target::from_be_bytes({
+                let mut bytes = [0; 32];
+                bytes[4] = 0x7f;
+                bytes[5] = 0xff;
+                bytes[6] = 0x80;
+                bytes
+            })
+        );
+        // Below difficulty 1 the target only saturates at 2^256 - 1
+        assert!(target_for_difficulty(0.5) > Target::MAX_ATTAINABLE_MAINNET);
+        assert_eq!(
+            target_for_difficulty(0.0),
+            Target::from_be_bytes([0xff; 32])
+        );
+    }
+
+    #[test]
+    fn prevhash_swaps_bytes_within_words() {
+        let genesis = genesis_block(Network::Bitcoin).block_hash();
+        assert_eq!(
+            genesis.to_string(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(
+            stratum_prevhash(&genesis),
+            "0a8ce26f72b3f1b646a2a6c14ff763ae65831e939c085ae10019d66800000000"
+        );
+    }
+
+    #[test]
+    fn merkle_branch_folds_to_merkle_root() {
+        for transactions in 0..6 {
+            let block = template(transactions);
+            let root = merkle_branch(&block.txdata).iter().fold(
+                block.txdata[0].compute_txid().to_raw_hash(),
+                |root, hash| hash_pair(&root, hash),
+            );
+            assert_eq!(TxMerkleNode::from_raw_hash(root), block.header.merkle_root);
+        }
+    }
+
+    #[test]
+    fn coinbase_halves_surround_extranonce() {
+        let template = template(2);
+        let job = Job::new("1".to_string(), template.clone(), 1.0).unwrap();
+        let coinbase = [&job.coinb1[..], &EXTRANONCE, &job.coinb2[..]].concat();
+        assert_eq!(coinbase, serialize(&solved(&template).txdata[0]));
+        assert_eq!(job.share_target, Target::MAX_ATTAINABLE_MAINNET);
+    }
+
+    #[test]
+    fn share_rebuilds_known_block() {
+        let genesis = genesis_block(Network::Bitcoin);
+        for transactions in [0, 1, 4] {
+            let template = template(transactions);
+            let expected = solved(&template);
+            let job = Job::new("1".to_string(), template, 1.0).unwrap();
+            let header = job.header(&EXTRANONCE, genesis.header.time, genesis.header.nonce);
+            assert_eq!(header, expected.header);
+            let block = job.block(&EXTRANONCE, header);
+            assert_eq!(block, expected);
+            assert_eq!(block.block_hash(), header.block_hash());
+            assert!(block.check_merkle_root());
+        }
+    }
+}
@@ -1,13 +1,74 @@
+use crate::address::{get_network, AddressPurpose, OutputType};
+use crate::address_book::{delete_address, get_addresses, subscribe_address_book, AddressEntry};
+use crate::backup::{backup_and_verify, run_schedule, BackupSchedule};
+use crate::bump::{
+    abandon_transaction, commit_bump_transaction, create_bump_transaction, get_tx_actions,
+    BumpProposal, TxActions,
+};
+use crate::chain::{broadcast_transaction, create_chain_client};
 use crate::clients::Clients;
+use crate::coins::{get_coins, list_coins, set_coin_locked, CoinGroup};
+use crate::descriptor::{import_descriptors, parse_descriptor, DescriptorImport, ImportResult};
 use crate::echo::create_echo_client;
-use crate::wallet::create_new_wallet;
+use crate::fee::{get_fee_estimates, FeeEstimates};
+use crate::gui::AppEvent;
+use crate::history::{get_wallet_txs, TxRecord};
+use crate::logging::{get_log_categories, set_log_categories, LogCategories};
+use crate::mempool::{get_mempool_info, subscribe_mempool, MempoolInfo};
+use crate::message::{sign_message, verify_message};
+use crate::migrate::{
+    backup_legacy_wallet, find_legacy_wallets, migrate_wallet, LegacyScan, MigrationReport,
+};
+use crate::mining::{
+    create_mining_client, get_block_template, get_tip, mine_block, BlockTemplate, BlockTip,
+};
+use crate::node::{
+    get_node_status, get_warnings, subscribe_alerts, subscribe_node_status, NodeStatus,
+};
+use crate::psbt::{combine_psbts, create_psbt, fill_psbt, FilledPsbt};
+use crate::receive::{
+    create_receive_request, list_receive_requests, relabel_receive_request, ReceiveRequest,
+};
+use crate::rescan::{
+    abort_rescan, make_thread, rescan_blockchain, subscribe_wallet_progress, RescanRange,
+};
+use crate::send::{CoinControl, Recipient, TxProposal};
+use crate::signer::{
+    address_descriptor, enumerate_signers, has_external_signer, node_display_address,
+    ExternalSigner,
+};
+use crate::store::start_wallet_store;
+#[cfg(feature = "stratum")]
+use crate::stratum::{run_stratum, StratumConfig};
+use crate::wallet::{
+    change_wallet_passphrase, commit_transaction, create_external_signer_wallet, create_new_wallet,
+    create_transaction, create_watch_only_wallet, encrypt_wallet, get_lock_state, get_wallets,
+    get_watch_only, list_wallet_dir, load_wallet, lock_wallet, restore_wallet, set_address_book,
+    unload_wallet, unlock_wallet, LockState, WalletList, WatchOnly,
+};
 use anyhow::{anyhow, Result};
+use bitcoin::opcodes::OP_TRUE;
+use bitcoin::psbt::Psbt;
+use bitcoin::script::Builder;
+use bitcoin::{Address, Amount, BlockHash, Network, OutPoint, ScriptBuf, Transaction, Txid};
+use bitcoin_ipc::chain_capnp::chain;
 use bitcoin_ipc::init::setup_connection;
+use bitcoin_ipc::init_capnp::init;
+use bitcoin_ipc::mining_capnp::mining;
 use bitcoin_ipc::node::create_node_client;
+use bitcoin_ipc::node_capnp::node;
+use bitcoin_ipc::proxy_capnp::thread;
 use bitcoin_ipc::wallet::create_wallet_loader_client;
+use bitcoin_ipc::wallet_capnp::{wallet, wallet_loader};
+#[cfg(feature = "stratum")]
+use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::oneshot;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+#[cfg(feature = "stratum")]
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use zeroize::Zeroizing;
 
 pub enum Task {
     SetupConnection(PathBuf, oneshot::Sender<Result<()>>),
@@ -16,183 +77,1398 @@ pub enum Task {
     SetupChainClient(oneshot::Sender<Result<()>>),
     SetupNodeClient(oneshot::Sender<Result<()>>),
     SetupWalletLoaderClient(oneshot::Sender<Result<()>>),
-    CreateNewWallet(oneshot::Sender<Result<()>>),
+    CreateNewWallet(String, oneshot::Sender<Result<Vec<String>>>),
+    SubscribeMempool(mpsc::UnboundedSender<AppEvent>, oneshot::Sender<Result<()>>),
+    GetMempoolInfo(oneshot::Sender<Result<MempoolInfo>>),
+    GetFeeEstimates(oneshot::Sender<Result<FeeEstimates>>),
+    SubscribeNodeStatus(mpsc::UnboundedSender<AppEvent>, oneshot::Sender<Result<()>>),
+    GetNodeStatus(oneshot::Sender<Result<NodeStatus>>),
+    SubscribeAlerts(mpsc::UnboundedSender<AppEvent>, oneshot::Sender<Result<()>>),
+    GetWarnings(oneshot::Sender<Result<Vec<String>>>),
+    GetLogCategories(oneshot::Sender<Result<LogCategories>>),
+    SetLogCategories(
+        Vec<String>,
+        Vec<String>,
+        oneshot::Sender<Result<LogCategories>>,
+    ),
+    GetWalletLockState(String, oneshot::Sender<Result<LockState>>),
+    EncryptWallet(String, Zeroizing<String>, oneshot::Sender<Result<()>>),
+    LockWallet(String, oneshot::Sender<Result<()>>),
+    UnlockWallet(
+        String,
+        Zeroizing<String>,
+        Duration,
+        oneshot::Sender<Result<()>>,
+    ),
+    ChangeWalletPassphrase(
+        String,
+        Zeroizing<String>,
+        Zeroizing<String>,
+        oneshot::Sender<Result<()>>,
+    ),
+    ListWallets(oneshot::Sender<Result<WalletList>>),
+    LoadWallet(String, oneshot::Sender<Result<Vec<String>>>),
+    RestoreWallet(PathBuf, String, oneshot::Sender<Result<Vec<String>>>),
+    UnloadWallet(String, oneshot::Sender<Result<()>>),
+    GetNetwork(oneshot::Sender<Result<Network>>),
+    CreateReceiveRequest(
+        String,
+        OutputType,
+        String,
+        Option<Amount>,
+        String,
+        oneshot::Sender<Result<ReceiveRequest>>,
+    ),
+    ListReceiveRequests(String, oneshot::Sender<Result<Vec<ReceiveRequest>>>),
+    RelabelReceiveRequest(
+        String,
+        ReceiveRequest,
+        String,
+        oneshot::Sender<Result<ReceiveRequest>>,
+    ),
+    CreateTransaction(
+        String,
+        Vec<Recipient>,
+        CoinControl,
+        oneshot::Sender<Result<TxProposal>>,
+    ),
+    CommitTransaction(String, Transaction, oneshot::Sender<Result<Txid>>),
+    GetTransactions(String, oneshot::Sender<Result<Vec<TxRecord>>>),
+    GetTxActions(String, Txid, oneshot::Sender<Result<TxActions>>),
+    AbandonTransaction(String, Txid, oneshot::Sender<Result<()>>),
+    CreateBumpTransaction(
+        String,
+        Txid,
+        CoinControl,
+        oneshot::Sender<Result<BumpProposal>>,
+    ),
+    CommitBumpTransaction(String, BumpProposal, oneshot::Sender<Result<Txid>>),
+    ListCoins(String, oneshot::Sender<Result<Vec<CoinGroup>>>),
+    SetCoinLocked(String, OutPoint, bool, oneshot::Sender<Result<bool>>),
+    CreatePsbt(
+        String,
+        Vec<Recipient>,
+        CoinControl,
+        oneshot::Sender<Result<Psbt>>,
+    ),
+    FillPsbt(String, Psbt, bool, oneshot::Sender<Result<FilledPsbt>>),
+    BroadcastTransaction(Transaction, oneshot::Sender<Result<Txid>>),
+    ListAddresses(String, oneshot::Sender<Result<Vec<AddressEntry>>>),
+    SetAddressLabel(
+        String,
+        ScriptBuf,
+        String,
+        AddressPurpose,
+        oneshot::Sender<Result<()>>,
+    ),
+    DeleteAddress(String, ScriptBuf, oneshot::Sender<Result<()>>),
+    ImportAddresses(
+        String,
+        Vec<(Address, String)>,
+        oneshot::Sender<Result<usize>>,
+    ),
+    SubscribeAddressBook(
+        String,
+        mpsc::UnboundedSender<AppEvent>,
+        oneshot::Sender<Result<()>>,
+    ),
+    SignMessage(String, Address, String, oneshot::Sender<Result<String>>),
+    VerifyMessage(Address, String, String, oneshot::Sender<Result<bool>>),
+    BackupWallet(String, PathBuf, oneshot::Sender<Result<()>>),
+    SetBackupSchedule(
+        Option<BackupSchedule>,
+        mpsc::UnboundedSender<AppEvent>,
+        oneshot::Sender<Result<()>>,
+    ),
+    SubscribeWalletProgress(
+        String,
+        mpsc::UnboundedSender<AppEvent>,
+        oneshot::Sender<Result<()>>,
+    ),
+    RescanWallet(String, Option<u32>, oneshot::Sender<Result<RescanRange>>),
+    AbortRescan(String, oneshot::Sender<Result<bool>>),
+    FindLegacyWallets(oneshot::Sender<Result<LegacyScan>>),
+    MigrateWallet(
+        String,
+        PathBuf,
+        Zeroizing<String>,
+        oneshot::Sender<Result<MigrationReport>>,
+    ),
+    CreateWatchOnlyWallet(String, oneshot::Sender<Result<Vec<String>>>),
+    ImportDescriptors(
+        String,
+        Vec<DescriptorImport>,
+        oneshot::Sender<Result<Vec<ImportResult>>>,
+    ),
+    GetWatchOnly(String, oneshot::Sender<Result<WatchOnly>>),
+    EnumerateSigners(String, oneshot::Sender<Result<Vec<ExternalSigner>>>),
+    CreateSignerWallet(
+        String,
+        ExternalSigner,
+        u32,
+        oneshot::Sender<Result<Vec<String>>>,
+    ),
+    HasExternalSigner(String, oneshot::Sender<Result<bool>>),
+    DisplayAddress(
+        String,
+        Address,
+        Option<ExternalSigner>,
+        oneshot::Sender<Result<Option<String>>>,
+    ),
+    SignPsbtWithSigner(ExternalSigner, Psbt, oneshot::Sender<Result<Psbt>>),
+    SubscribeWalletState(
+        String,
+        mpsc::UnboundedSender<AppEvent>,
+        oneshot::Sender<Result<()>>,
+    ),
+    GetMiningTip(oneshot::Sender<Result<Option<BlockTip>>>),
+    MineBlocks(u32, Address, oneshot::Sender<Result<Vec<BlockHash>>>),
+    GetBlockTemplate(oneshot::Sender<Result<BlockTemplate>>),
+    #[cfg(feature = "stratum")]
+    StartStratum(StratumConfig, oneshot::Sender<Result<SocketAddr>>),
+}
+
+// Look up a loaded wallet's handle and the thread client for a wallet-scoped task
+fn wallet_clients(
+    shared_state: &Clients,
+    wallet: &str,
+) -> Result<(wallet::Client, thread::Client)> {
+    let wallet_client = shared_state
+        .wallets
+        .read()
+        .unwrap()
+        .client(wallet)
+        .ok_or_else(|| anyhow!("Wallet {} is not loaded", wallet))?;
+    Ok((wallet_client, thread_client(shared_state)?))
+}
+
+// A client stored by one of the Setup tasks, or an error if it has not run yet
+fn initialized<T: Clone>(client: &RwLock<Option<T>>) -> Result<T> {
+    client
+        .read()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| anyhow!("Clients are not initialized"))
+}
+
+fn init_client(shared_state: &Clients) -> Result<init::Client> {
+    initialized(&shared_state.init_client)
+}
+
+fn thread_client(shared_state: &Clients) -> Result<thread::Client> {
+    initialized(&shared_state.thread_client)
+}
+
+fn node_client(shared_state: &Clients) -> Result<node::Client> {
+    initialized(&shared_state.node_client)
+}
+
+fn chain_client(shared_state: &Clients) -> Result<chain::Client> {
+    initialized(&shared_state.chain_client)
+}
+
+fn wallet_loader_client(shared_state: &Clients) -> Result<wallet_loader::Client> {
+    initialized(&shared_state.wallet_loader_client)
+}
+
+// The thread rescans run on, created on first use
+async fn rescan_thread(shared_state: &Clients) -> Result<thread::Client> {
+    if let Some(thread) = shared_state.rescan_thread.read().unwrap().clone() {
+        return Ok(thread);
+    }
+    let init = init_client(shared_state)?;
+    let thread = make_thread(&init, "frost_byte-rescan")
+        .await
+        .map_err(|e| anyhow!("Failed to create rescan thread: {}", e))?;
+    *shared_state.rescan_thread.write().unwrap() = Some(thread.clone());
+    Ok(thread)
+}
+
+// The Mining interface, created on first use
+async fn mining_client(shared_state: &Clients) -> Result<(mining::Client, thread::Client)> {
+    let thread = thread_client(shared_state)?;
+    if let Some(mining) = shared_state.mining_client.read().unwrap().clone() {
+        return Ok((mining, thread));
+    }
+    let init = init_client(shared_state)?;
+    let mining = create_mining_client(&init, &thread)
+        .await
+        .map_err(|e| anyhow!("Failed to create mining client: {}", e))?;
+    *shared_state.mining_client.write().unwrap() = Some(mining.clone());
+    Ok((mining, thread))
+}
+
+// The node's network never changes, so it is fetched once and cached
+async fn network(shared_state: &Clients) -> Result<Network> {
+    if let Some(network) = *shared_state.network.read().unwrap() {
+        return Ok(network);
+    }
+    let node = node_client(shared_state)?;
+    let thread = thread_client(shared_state)?;
+    let network = get_network(&node, &thread)
+        .await
+        .map_err(|e| anyhow!("Failed to get network: {}", e))?;
+    *shared_state.network.write().unwrap() = Some(network);
+    Ok(network)
 }
 
 pub async fn run_task(task: Task, shared_state: Arc<Clients>) -> Result<()> {
     match task {
         Task::SetupConnection(path, response) => {
             println!("Setting up connection with path: {:?}", path);
-            let (init_client, thread_client) = match setup_connection(path.as_ref()).await {
-                Ok(clients) => {
-                    println!("Connection setup successful");
-                    clients
-                }
-                Err(e) => {
-                    let err = anyhow!("Failed to setup connection: {}", e);
-                    eprintln!("Error: {}", err);
-                    response
-                        .send(Err(err))
-                        .unwrap_or_else(|_| eprintln!("Failed to send error response"));
-                    return Err(anyhow!("Failed to setup connection"));
-                }
-            };
-
-            {
-                let mut init_lock = shared_state.init_client.write().unwrap();
-                let mut thread_lock = shared_state.thread_client.write().unwrap();
-                *init_lock = Some(init_client);
-                *thread_lock = Some(thread_client);
-                println!("Clients stored in shared state");
+            let result = async {
+                let (init, thread) = setup_connection(path.as_ref())
+                    .await
+                    .map_err(|e| anyhow!("Failed to setup connection: {}", e))?;
+                *shared_state.init_client.write().unwrap() = Some(init);
+                *shared_state.thread_client.write().unwrap() = Some(thread);
+                // Threads and clients belong to the previous connection
+                *shared_state.rescan_thread.write().unwrap() = None;
+                *shared_state.mining_client.write().unwrap() = None;
+                Ok::<_, anyhow::Error>(())
             }
-
+            .await;
             response
-                .send(Ok(()))
-                .unwrap_or_else(|_| eprintln!("Failed to send success response"));
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send connection response"));
         }
         Task::SetupEchoClient(response) => {
             println!("Setting up Echo client");
-            let init_client = shared_state.init_client.read().unwrap();
-            let thread_client = shared_state.thread_client.read().unwrap();
-
-            if let (Some(ref init), Some(ref thread)) = (&*init_client, &*thread_client) {
-                println!("Init and Thread clients available");
-                let echo_client = match create_echo_client(init, thread).await {
-                    Ok(client) => {
-                        println!("Echo client created successfully");
-                        client
-                    }
-                    Err(e) => {
-                        let err = anyhow!("Failed to create echo client: {}", e);
-                        eprintln!("Error: {}", err);
-                        response
-                            .send(Err(err))
-                            .unwrap_or_else(|_| eprintln!("Failed to send error response"));
-                        return Err(anyhow!("Failed to create echo client"));
-                    }
-                };
-
-                {
-                    let mut echo_lock = shared_state.echo_client.write().unwrap();
-                    *echo_lock = Some(echo_client);
-                    println!("Echo client stored in shared state");
-                }
-
-                response
-                    .send(Ok(()))
-                    .unwrap_or_else(|_| eprintln!("Failed to send success response"));
-            } else {
-                let err = anyhow!("Clients are not initialized");
-                eprintln!("Error: {}", err);
-                response
-                    .send(Err(err))
-                    .unwrap_or_else(|_| eprintln!("Failed to send error response"));
-                return Err(anyhow!("Clients are not initialized"));
+            let result = async {
+                let init = init_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let echo = create_echo_client(&init, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to create echo client: {}", e))?;
+                *shared_state.echo_client.write().unwrap() = Some(echo);
+                Ok::<_, anyhow::Error>(())
             }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send echo client response"));
         }
         Task::SetupNodeClient(response) => {
             println!("Setting up Node client");
-            let init_client = shared_state.init_client.read().unwrap();
-            let thread_client = shared_state.thread_client.read().unwrap();
+            let result = async {
+                let init = init_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let node = create_node_client(&init, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to create node client: {}", e))?;
+                *shared_state.node_client.write().unwrap() = Some(node);
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send node client response"));
+        }
+        Task::SetupWalletLoaderClient(response) => {
+            println!("Setting up WalletLoader client");
+            let result = async {
+                let node = node_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let wallet_loader = create_wallet_loader_client(&node, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to create wallet loader client: {}", e))?;
+                *shared_state.wallet_loader_client.write().unwrap() = Some(wallet_loader);
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send wallet loader client response"));
+        }
+        Task::CreateNewWallet(name, response) => {
+            println!("Creating new wallet: {}", name);
+            let result = async {
+                let wallet_loader = wallet_loader_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let (wallet, warnings) = create_new_wallet(&wallet_loader, &thread, &name)
+                    .await
+                    .map_err(|e| anyhow!("Error creating new wallet: {}", e))?;
+                shared_state.wallets.write().unwrap().insert(name, wallet);
+                Ok::<_, anyhow::Error>(warnings)
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send create wallet response"));
+        }
+        Task::SetupChainClient(response) => {
+            println!("Setting up Chain client");
+            let result = async {
+                let init = init_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let chain = create_chain_client(&init, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to create chain client: {}", e))?;
+                *shared_state.chain_client.write().unwrap() = Some(chain);
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send chain client response"));
+        }
+        Task::SubscribeMempool(events, response) => {
+            println!("Subscribing to mempool notifications");
+            let result = async {
+                let chain = chain_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let handler = subscribe_mempool(&chain, &thread, events)
+                    .await
+                    .map_err(|e| anyhow!("Failed to subscribe to mempool: {}", e))?;
+                // Dropping the handler would disconnect the notifications
+                *shared_state.mempool_handler.write().unwrap() = Some(handler);
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send mempool subscription response"));
+        }
+        Task::GetMempoolInfo(response) => {
+            let result = async {
+                let node = node_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                get_mempool_info(&node, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get mempool info: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send mempool info response"));
+        }
+        Task::GetFeeEstimates(response) => {
+            let result = async {
+                let node = node_client(&shared_state)?;
+                let chain = chain_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let cached = shared_state.fee_estimates.read().unwrap().clone();
+                let estimates = get_fee_estimates(&node, &chain, &thread, cached)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get fee estimates: {}", e))?;
+                *shared_state.fee_estimates.write().unwrap() = Some(estimates.clone());
+                Ok::<_, anyhow::Error>(estimates)
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send fee estimates response"));
+        }
+        Task::SubscribeNodeStatus(events, response) => {
+            println!("Subscribing to node status notifications");
+            let result = async {
+                let node = node_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let handlers = subscribe_node_status(&node, &thread, events)
+                    .await
+                    .map_err(|e| anyhow!("Failed to subscribe to node status: {}", e))?;
+                shared_state.node_handlers.write().unwrap().extend(handlers);
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send node status subscription response"));
+        }
+        Task::GetNodeStatus(response) => {
+            let result = async {
+                let node = node_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let network = network(&shared_state).await?;
+                get_node_status(&node, &thread, network)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get node status: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send node status response"));
+        }
+        Task::SubscribeAlerts(events, response) => {
+            println!("Subscribing to alert notifications");
+            let result = async {
+                let node = node_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let handler = subscribe_alerts(&node, &thread, events)
+                    .await
+                    .map_err(|e| anyhow!("Failed to subscribe to alerts: {}", e))?;
+                shared_state.node_handlers.write().unwrap().push(handler);
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send alert subscription response"));
+        }
+        Task::GetWarnings(response) => {
+            let result = async {
+                let node = node_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                get_warnings(&node, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get warnings: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send warnings response"));
+        }
+        Task::GetLogCategories(response) => {
+            let result = async {
+                let node = node_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                get_log_categories(&node, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get log categories: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send log categories response"));
+        }
+        Task::SetLogCategories(include, exclude, response) => {
+            println!("Updating log categories: +{:?} -{:?}", include, exclude);
+            let result = async {
+                let node = node_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                set_log_categories(&node, &thread, &include, &exclude)
+                    .await
+                    .map_err(|e| anyhow!("Failed to set log categories: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send log categories response"));
+        }
+        Task::GetWalletLockState(wallet, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                get_lock_state(&wallet_client, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get wallet lock state: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send lock state response"));
+        }
+        Task::EncryptWallet(wallet, passphrase, response) => {
+            println!("Encrypting wallet: {}", wallet);
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                encrypt_wallet(&wallet_client, &thread, &passphrase)
+                    .await
+                    .map_err(|e| anyhow!("Failed to encrypt wallet: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send encrypt response"));
+        }
+        Task::LockWallet(wallet, response) => {
+            println!("Locking wallet: {}", wallet);
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                if let Some(entry) = shared_state.wallets.write().unwrap().get_mut(&wallet) {
+                    entry.cancel_relock();
+                }
+                lock_wallet(&wallet_client, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to lock wallet: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send lock response"));
+        }
+        Task::UnlockWallet(wallet, passphrase, timeout, response) => {
+            println!("Unlocking wallet {} for {:?}", wallet, timeout);
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                unlock_wallet(&wallet_client, &thread, &passphrase)
+                    .await
+                    .map_err(|e| anyhow!("Failed to unlock wallet: {}", e))?;
 
-            if let (Some(ref init), Some(ref thread)) = (&*init_client, &*thread_client) {
-                println!("Init and Thread clients available");
-                let node_client = match create_node_client(init, thread).await {
-                    Ok(client) => {
-                        println!("Node client created successfully");
-                        client
-                    }
-                    Err(e) => {
-                        let err = anyhow!("Failed to create node client: {}", e);
-                        eprintln!("Error: {}", err);
-                        response
-                            .send(Err(err))
-                            .unwrap_or_else(|_| eprintln!("Failed to send error response"));
-                        return Err(anyhow!("Failed to create node client"));
+                let relock = tokio::task::spawn_local(async move {
+                    tokio::time::sleep(timeout).await;
+                    match lock_wallet(&wallet_client, &thread).await {
+                        Ok(()) => println!("Wallet relocked after timeout"),
+                        Err(e) => eprintln!("Failed to relock wallet: {}", e),
                     }
+                });
+                if let Some(entry) = shared_state.wallets.write().unwrap().get_mut(&wallet) {
+                    entry.set_relock_task(relock.abort_handle());
+                }
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            drop(passphrase);
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send unlock response"));
+        }
+        Task::ChangeWalletPassphrase(wallet, old_passphrase, new_passphrase, response) => {
+            println!("Changing wallet passphrase: {}", wallet);
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                change_wallet_passphrase(&wallet_client, &thread, &old_passphrase, &new_passphrase)
+                    .await
+                    .map_err(|e| anyhow!("Failed to change wallet passphrase: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send change passphrase response"));
+        }
+        Task::ListWallets(response) => {
+            let result = async {
+                let wallet_loader = wallet_loader_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let available = list_wallet_dir(&wallet_loader, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to list wallets: {}", e))?;
+                // Pick up wallets loaded by other clients, e.g. bitcoin-cli
+                let loaded = get_wallets(&wallet_loader, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to list wallets: {}", e))?;
+                for (name, wallet) in loaded {
+                    shared_state.wallets.write().unwrap().insert(name, wallet);
+                }
+                Ok::<_, anyhow::Error>(WalletList {
+                    available,
+                    loaded: shared_state.wallets.read().unwrap().names(),
+                })
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send wallet list response"));
+        }
+        Task::LoadWallet(name, response) => {
+            println!("Loading wallet: {}", name);
+            let result = async {
+                let wallet_loader = wallet_loader_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let (wallet, warnings) = load_wallet(&wallet_loader, &thread, &name)
+                    .await
+                    .map_err(|e| anyhow!("Failed to load wallet: {}", e))?;
+                shared_state.wallets.write().unwrap().insert(name, wallet);
+                Ok::<_, anyhow::Error>(warnings)
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send load wallet response"));
+        }
+        Task::RestoreWallet(backup_file, name, response) => {
+            println!("Restoring wallet {} from {:?}", name, backup_file);
+            let result = async {
+                let wallet_loader = wallet_loader_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let (wallet, warnings) =
+                    restore_wallet(&wallet_loader, &thread, &backup_file, &name)
+                        .await
+                        .map_err(|e| anyhow!("Failed to restore wallet: {}", e))?;
+                shared_state.wallets.write().unwrap().insert(name, wallet);
+                Ok::<_, anyhow::Error>(warnings)
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send restore wallet response"));
+        }
+        Task::UnloadWallet(wallet, response) => {
+            println!("Unloading wallet: {}", wallet);
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                unload_wallet(&wallet_client, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to unload wallet: {}", e))?;
+                // Dropping the entry releases its subscriptions and pending relock
+                shared_state.wallets.write().unwrap().remove(&wallet);
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send unload wallet response"));
+        }
+        Task::GetNetwork(response) => {
+            let result = network(&shared_state).await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send network response"));
+        }
+        Task::CreateReceiveRequest(wallet, output_type, label, amount, message, response) => {
+            println!(
+                "Creating {} receive request in wallet: {}",
+                output_type, wallet
+            );
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                let network = network(&shared_state).await?;
+                create_receive_request(
+                    &wallet_client,
+                    &thread,
+                    network,
+                    output_type,
+                    &label,
+                    amount,
+                    &message,
+                )
+                .await
+                .map_err(|e| anyhow!("Failed to create receive request: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send receive request response"));
+        }
+        Task::ListReceiveRequests(wallet, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                list_receive_requests(&wallet_client, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to list receive requests: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send receive requests response"));
+        }
+        Task::RelabelReceiveRequest(wallet, request, label, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                let network = network(&shared_state).await?;
+                relabel_receive_request(&wallet_client, &thread, network, &request, &label)
+                    .await
+                    .map_err(|e| anyhow!("Failed to relabel address: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send relabel response"));
+        }
+        Task::CreateTransaction(wallet, recipients, coin_control, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                // Catch a stale manual selection before the wallet reports a generic error
+                let coins = get_coins(&wallet_client, &thread, &coin_control.inputs)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get selected coins: {}", e))?;
+                if let Some(coin) = coins.iter().find(|coin| coin.is_spent) {
+                    return Err(anyhow!("Selected coin {} is already spent", coin.outpoint));
+                }
+                create_transaction(&wallet_client, &thread, &recipients, &coin_control, true)
+                    .await
+                    .map_err(|e| anyhow!("Failed to create transaction: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send transaction response"));
+        }
+        Task::CommitTransaction(wallet, tx, response) => {
+            let txid = tx.compute_txid();
+            println!("Broadcasting transaction {} from wallet: {}", txid, wallet);
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                commit_transaction(&wallet_client, &thread, &tx)
+                    .await
+                    .map_err(|e| anyhow!("Failed to commit transaction: {}", e))?;
+                Ok::<_, anyhow::Error>(txid)
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send commit response"));
+        }
+        Task::GetTransactions(wallet, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                get_wallet_txs(&wallet_client, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get transactions: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send transactions response"));
+        }
+        Task::GetTxActions(wallet, txid, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                get_tx_actions(&wallet_client, &thread, txid)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get transaction actions: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send transaction actions response"));
+        }
+        Task::AbandonTransaction(wallet, txid, response) => {
+            println!("Abandoning transaction {} in wallet: {}", txid, wallet);
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                abandon_transaction(&wallet_client, &thread, txid)
+                    .await
+                    .map_err(|e| anyhow!("Failed to abandon transaction: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send abandon response"));
+        }
+        Task::CreateBumpTransaction(wallet, txid, coin_control, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                create_bump_transaction(&wallet_client, &thread, txid, &coin_control)
+                    .await
+                    .map_err(|e| anyhow!("Failed to bump fee: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send bump fee response"));
+        }
+        Task::CommitBumpTransaction(wallet, proposal, response) => {
+            println!(
+                "Replacing transaction {} in wallet: {}",
+                proposal.txid, wallet
+            );
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                commit_bump_transaction(&wallet_client, &thread, &proposal)
+                    .await
+                    .map_err(|e| anyhow!("Failed to commit fee bump: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send bump commit response"));
+        }
+        Task::ListCoins(wallet, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                list_coins(&wallet_client, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to list coins: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send coins response"));
+        }
+        Task::SetCoinLocked(wallet, outpoint, lock, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                set_coin_locked(&wallet_client, &thread, outpoint, lock)
+                    .await
+                    .map_err(|e| anyhow!("Failed to update coin lock: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send coin lock response"));
+        }
+        Task::CreatePsbt(wallet, recipients, coin_control, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                create_psbt(&wallet_client, &thread, &recipients, &coin_control)
+                    .await
+                    .map_err(|e| anyhow!("Failed to create PSBT: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send PSBT response"));
+        }
+        Task::FillPsbt(wallet, psbt, sign, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                fill_psbt(&wallet_client, &thread, &psbt, sign)
+                    .await
+                    .map_err(|e| anyhow!("Failed to process PSBT: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send PSBT response"));
+        }
+        Task::BroadcastTransaction(tx, response) => {
+            let txid = tx.compute_txid();
+            println!("Broadcasting transaction {}", txid);
+            let result = async {
+                let chain = chain_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                broadcast_transaction(&chain, &thread, &tx)
+                    .await
+                    .map_err(|e| anyhow!("Failed to broadcast transaction: {}", e))?;
+                Ok::<_, anyhow::Error>(txid)
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send broadcast response"));
+        }
+        Task::ListAddresses(wallet, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                get_addresses(&wallet_client, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to list addresses: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send addresses response"));
+        }
+        Task::SetAddressLabel(wallet, script, label, purpose, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                set_address_book(&wallet_client, &thread, &script, &label, purpose)
+                    .await
+                    .map_err(|e| anyhow!("Failed to set address label: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send address label response"));
+        }
+        Task::DeleteAddress(wallet, script, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                delete_address(&wallet_client, &thread, &script)
+                    .await
+                    .map_err(|e| anyhow!("Failed to delete address: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send delete address response"));
+        }
+        Task::ImportAddresses(wallet, rows, response) => {
+            println!("Importing {} addresses into wallet: {}", rows.len(), wallet);
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                for (address, label) in &rows {
+                    set_address_book(
+                        &wallet_client,
+                        &thread,
+                        &address.script_pubkey(),
+                        label,
+                        AddressPurpose::Send,
+                    )
+                    .await
+                    .map_err(|e| anyhow!("Failed to import {}: {}", address, e))?;
+                }
+                Ok::<_, anyhow::Error>(rows.len())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send import response"));
+        }
+        Task::SubscribeAddressBook(wallet, events, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                let subscribed = shared_state
+                    .wallets
+                    .read()
+                    .unwrap()
+                    .get(&wallet)
+                    .is_some_and(|entry| entry.address_book_handler.is_some());
+                if subscribed {
+                    return Ok(());
+                }
+                let handler = subscribe_address_book(&wallet, &wallet_client, &thread, events)
+                    .await
+                    .map_err(|e| anyhow!("Failed to subscribe to address book: {}", e))?;
+                if let Some(entry) = shared_state.wallets.write().unwrap().get_mut(&wallet) {
+                    entry.address_book_handler = Some(handler);
+                }
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send subscribe response"));
+        }
+        Task::SignMessage(wallet, address, message, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                sign_message(&wallet_client, &thread, &address, &message)
+                    .await
+                    .map_err(|e| anyhow!("Failed to sign message: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send signature response"));
+        }
+        Task::VerifyMessage(address, signature, message, response) => {
+            let result = async {
+                let node = node_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                verify_message(&node, &thread, &address, &signature, &message)
+                    .await
+                    .map_err(|e| anyhow!("Failed to verify message: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send verification response"));
+        }
+        Task::BackupWallet(wallet, path, response) => {
+            println!("Backing up wallet {} to {:?}", wallet, path);
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                let node = node_client(&shared_state)?;
+                let wallet_loader = wallet_loader_client(&shared_state)?;
+                backup_and_verify(&wallet_client, &node, &wallet_loader, &thread, &path)
+                    .await
+                    .map_err(|e| anyhow!("Failed to back up wallet: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send backup response"));
+        }
+        Task::SetBackupSchedule(schedule, events, response) => {
+            // Replace any running schedule, even when only its settings changed
+            if let Some(previous) = shared_state.backup_schedule.write().unwrap().take() {
+                previous.abort();
+            }
+            if let Some(schedule) = schedule {
+                println!("Scheduling wallet backups into {:?}", schedule.directory);
+                let task =
+                    tokio::task::spawn_local(run_schedule(shared_state.clone(), schedule, events));
+                *shared_state.backup_schedule.write().unwrap() = Some(task.abort_handle());
+            }
+            response
+                .send(Ok(()))
+                .unwrap_or_else(|_| eprintln!("Failed to send backup schedule response"));
+        }
+        Task::SubscribeWalletProgress(wallet, events, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                let subscribed = shared_state
+                    .wallets
+                    .read()
+                    .unwrap()
+                    .get(&wallet)
+                    .is_some_and(|entry| entry.progress_handler.is_some());
+                if subscribed {
+                    return Ok(());
+                }
+                let handler = subscribe_wallet_progress(&wallet, &wallet_client, &thread, events)
+                    .await
+                    .map_err(|e| anyhow!("Failed to subscribe to wallet progress: {}", e))?;
+                if let Some(entry) = shared_state.wallets.write().unwrap().get_mut(&wallet) {
+                    entry.progress_handler = Some(handler);
+                }
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send subscribe response"));
+        }
+        Task::RescanWallet(wallet, start_height, response) => {
+            println!(
+                "Rescanning wallet {} from height {:?}",
+                wallet, start_height
+            );
+            let result = async {
+                // Checked up front, as the RPC error for an unloaded wallet is less clear
+                wallet_clients(&shared_state, &wallet)?;
+                let node = node_client(&shared_state)?;
+                let thread = rescan_thread(&shared_state).await?;
+                rescan_blockchain(&node, &thread, &wallet, start_height)
+                    .await
+                    .map_err(|e| anyhow!("Failed to rescan wallet: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send rescan response"));
+        }
+        Task::AbortRescan(wallet, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                abort_rescan(&wallet_client, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to abort rescan: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send abort response"));
+        }
+        Task::FindLegacyWallets(response) => {
+            let result = async {
+                let wallet_loader = wallet_loader_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let loaded: Vec<_> = {
+                    let wallets = shared_state.wallets.read().unwrap();
+                    wallets
+                        .names()
+                        .into_iter()
+                        .filter_map(|name| wallets.client(&name).map(|client| (name, client)))
+                        .collect()
                 };
-
+                find_legacy_wallets(&wallet_loader, &thread, &loaded)
+                    .await
+                    .map_err(|e| anyhow!("Failed to look for legacy wallets: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send legacy wallets response"));
+        }
+        Task::MigrateWallet(wallet, backup, passphrase, response) => {
+            println!("Migrating wallet {}, backing up to {:?}", wallet, backup);
+            let result = async {
+                let wallet_loader = wallet_loader_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let wallet_client = shared_state.wallets.read().unwrap().client(&wallet);
+                backup_legacy_wallet(
+                    &wallet_loader,
+                    &thread,
+                    wallet_client.as_ref(),
+                    &wallet,
+                    &backup,
+                )
+                .await
+                .map_err(|e| anyhow!("Failed to back up wallet, not migrating: {}", e))?;
+                // The node can only migrate a wallet nobody holds on to
+                let was_loaded = wallet_client.is_some();
+                if let Some(wallet_client) = wallet_client {
+                    unload_wallet(&wallet_client, &thread)
+                        .await
+                        .map_err(|e| anyhow!("Failed to unload wallet: {}", e))?;
+                    shared_state.wallets.write().unwrap().remove(&wallet);
+                }
+                let (migrated, report) =
+                    match migrate_wallet(&wallet_loader, &thread, &wallet, &passphrase).await {
+                        Ok(migrated) => migrated,
+                        Err(e) if was_loaded => {
+                            // Leave the wallet loaded as it was before the attempt
+                            return match load_wallet(&wallet_loader, &thread, &wallet).await {
+                                Ok((reloaded, _)) => {
+                                    shared_state
+                                        .wallets
+                                        .write()
+                                        .unwrap()
+                                        .insert(wallet, reloaded);
+                                    Err(anyhow!("Failed to migrate wallet: {}", e))
+                                }
+                                Err(reload) => Err(anyhow!(
+                                    "Failed to migrate wallet: {}; failed to load it again: {}",
+                                    e,
+                                    reload
+                                )),
+                            };
+                        }
+                        Err(e) => return Err(anyhow!("Failed to migrate wallet: {}", e)),
+                    };
+                shared_state
+                    .wallets
+                    .write()
+                    .unwrap()
+                    .insert(wallet, migrated);
+                Ok::<_, anyhow::Error>(report)
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send migration response"));
+        }
+        Task::CreateWatchOnlyWallet(name, response) => {
+            println!("Creating watch-only wallet: {}", name);
+            let result = async {
+                let wallet_loader = wallet_loader_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                let (wallet, warnings) = create_watch_only_wallet(&wallet_loader, &thread, &name)
+                    .await
+                    .map_err(|e| anyhow!("Failed to create watch-only wallet: {}", e))?;
+                shared_state.wallets.write().unwrap().insert(name, wallet);
+                Ok::<_, anyhow::Error>(warnings)
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send create wallet response"));
+        }
+        Task::ImportDescriptors(wallet, imports, response) => {
+            println!("Importing {} descriptors into {}", imports.len(), wallet);
+            let result = async {
+                wallet_clients(&shared_state, &wallet)?;
+                let node = node_client(&shared_state)?;
+                // Imports with a past timestamp rescan, so keep them off the main thread
+                let thread = rescan_thread(&shared_state).await?;
+                import_descriptors(&node, &thread, &wallet, &imports)
+                    .await
+                    .map_err(|e| anyhow!("Failed to import descriptors: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send import response"));
+        }
+        Task::GetWatchOnly(wallet, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                get_watch_only(&wallet_client, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to check for watch-only scripts: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send watch-only response"));
+        }
+        Task::EnumerateSigners(command, response) => {
+            let result = async {
+                let network = network(&shared_state).await?;
+                enumerate_signers(&command, network)
+                    .await
+                    .map_err(|e| anyhow!("Failed to enumerate signers: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send signers response"));
+        }
+        Task::CreateSignerWallet(name, signer, account, response) => {
+            println!("Creating wallet {} for signer {}", name, signer.fingerprint);
+            let result = async {
+                let network = network(&shared_state).await?;
+                let wallet_loader = wallet_loader_client(&shared_state)?;
+                let node = node_client(&shared_state)?;
+                let thread = thread_client(&shared_state)?;
+                // Fetch descriptors first, so an unreachable device leaves no empty wallet behind
+                let descriptors = signer
+                    .get_descriptors(network, account)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get descriptors from signer: {}", e))?;
+                let mut imports = Vec::new();
+                let internal = descriptors.internal.iter().map(|d| (d, true));
+                for (descriptor, internal) in descriptors
+                    .receive
+                    .iter()
+                    .map(|d| (d, false))
+                    .chain(internal)
                 {
-                    let mut node_lock = shared_state.node_client.write().unwrap();
-                    *node_lock = Some(node_client);
-                    println!("Node client stored in shared state");
+                    let parsed = parse_descriptor(descriptor)
+                        .map_err(|e| anyhow!("Signer returned an invalid descriptor: {}", e))?;
+                    imports.push(DescriptorImport {
+                        descriptor: parsed.descriptor,
+                        timestamp: None,
+                        range: None,
+                        active: parsed.ranged,
+                        internal,
+                        label: String::new(),
+                    });
                 }
 
-                response
-                    .send(Ok(()))
-                    .unwrap_or_else(|_| eprintln!("Failed to send success response"));
-            } else {
-                let err = anyhow!("Clients are not initialized");
-                eprintln!("Error: {}", err);
-                response
-                    .send(Err(err))
-                    .unwrap_or_else(|_| eprintln!("Failed to send error response"));
-                return Err(anyhow!("Clients are not initialized"));
+                let (wallet, warnings) =
+                    create_external_signer_wallet(&wallet_loader, &thread, &name)
+                        .await
+                        .map_err(|e| anyhow!("Failed to create wallet: {}", e))?;
+                shared_state
+                    .wallets
+                    .write()
+                    .unwrap()
+                    .insert(name.clone(), wallet);
+                let results = import_descriptors(&node, &thread, &name, &imports)
+                    .await
+                    .map_err(|e| anyhow!("Failed to import signer descriptors: {}", e))?;
+                let errors: Vec<String> = results
+                    .into_iter()
+                    .filter(|result| !result.success)
+                    .map(|result| result.error.unwrap_or_else(|| "Unknown error".to_string()))
+                    .collect();
+                if !errors.is_empty() {
+                    return Err(anyhow!(
+                        "Failed to import signer descriptors: {}",
+                        errors.join("; ")
+                    ));
+                }
+                Ok::<_, anyhow::Error>(warnings)
             }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send create wallet response"));
         }
-        Task::SetupWalletLoaderClient(response) => {
-            println!("Setting up WalletLoader client");
-            let node_client = shared_state.node_client.read().unwrap();
-            let thread_client = shared_state.thread_client.read().unwrap();
-
-            if let (Some(ref node), Some(ref thread)) = (&*node_client, &*thread_client) {
-                println!("Node and Thread clients available");
-                let wallet_client = match create_wallet_loader_client(node, thread).await {
-                    Ok(client) => {
-                        println!("WalletLoader client created successfully");
-                        client
-                    }
-                    Err(e) => {
-                        let err = anyhow!("Failed to create wallet loader client: {}", e);
-                        eprintln!("Error: {}", err);
-                        response
-                            .send(Err(err))
-                            .unwrap_or_else(|_| eprintln!("Failed to send error response"));
-                        return Err(anyhow!("Failed to create wallet loader client"));
-                    }
+        Task::HasExternalSigner(wallet, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                has_external_signer(&wallet_client, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to check for an external signer: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send external signer response"));
+        }
+        Task::DisplayAddress(wallet, address, signer, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                let Some(signer) = signer else {
+                    // Without a signer of our own, leave it to the node's -signer
+                    node_display_address(&wallet_client, &thread, &address)
+                        .await
+                        .map_err(|e| anyhow!("Failed to display address: {}", e))?;
+                    return Ok(None);
                 };
-
+                let network = network(&shared_state).await?;
+                let node = node_client(&shared_state)?;
+                let descriptor = address_descriptor(&node, &thread, &wallet, &address)
+                    .await
+                    .map_err(|e| anyhow!("Failed to display address: {}", e))?;
+                let shown = signer
+                    .display_address(network, &descriptor)
+                    .await
+                    .map_err(|e| anyhow!("Failed to display address: {}", e))?;
+                Ok::<_, anyhow::Error>(Some(shown))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send display address response"));
+        }
+        Task::SignPsbtWithSigner(signer, psbt, response) => {
+            println!("Signing PSBT with signer {}", signer.fingerprint);
+            let result = async {
+                let network = network(&shared_state).await?;
+                let signed = signer
+                    .sign_psbt(network, &psbt)
+                    .await
+                    .map_err(|e| anyhow!("Failed to sign with signer: {}", e))?;
+                // Keep anything the device dropped, such as other cosigners' signatures
+                combine_psbts(psbt, vec![signed]).map_err(|e| anyhow!(e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send signed PSBT response"));
+        }
+        Task::SubscribeWalletState(wallet, events, response) => {
+            let result = async {
+                let (wallet_client, thread) = wallet_clients(&shared_state, &wallet)?;
+                let wallets = shared_state.wallets.read().unwrap();
+                if let Some(store) = wallets.get(&wallet).and_then(|entry| entry.store.as_ref()) {
+                    store.resend();
+                    return Ok(());
+                }
+                drop(wallets);
+                println!("Starting state store for wallet: {}", wallet);
+                // Forget a wallet unloaded by another client, e.g. bitcoin-cli
+                let unloaded_state = shared_state.clone();
+                let unloaded = wallet.clone();
+                let store =
+                    start_wallet_store(&wallet, &wallet_client, &thread, events, move || {
+                        unloaded_state.wallets.write().unwrap().remove(&unloaded);
+                    })
+                    .await
+                    .map_err(|e| anyhow!("Failed to start wallet state store: {}", e))?;
+                if let Some(entry) = shared_state.wallets.write().unwrap().get_mut(&wallet) {
+                    entry.store = Some(store);
+                }
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send subscribe response"));
+        }
+        Task::GetMiningTip(response) => {
+            let result = async {
+                let (mining, thread) = mining_client(&shared_state).await?;
+                get_tip(&mining, &thread)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get tip: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send tip response"));
+        }
+        Task::MineBlocks(blocks, address, response) => {
+            println!("Mining {} blocks to {}", blocks, address);
+            let result = async {
+                // Only regtest's minimum difficulty can be mined on a CPU
+                if network(&shared_state).await? != Network::Regtest {
+                    return Err(anyhow!("Mining is only available on regtest"));
+                }
+                let (mining, thread) = mining_client(&shared_state).await?;
+                let script_pubkey = address.script_pubkey();
+                let mut hashes = Vec::new();
+                for _ in 0..blocks {
+                    let hash = mine_block(&mining, &thread, &script_pubkey)
+                        .await
+                        .map_err(|e| anyhow!("Failed to mine block: {}", e))?;
+                    hashes.push(hash);
+                }
+                Ok::<_, anyhow::Error>(hashes)
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send mined blocks response"));
+        }
+        Task::GetBlockTemplate(response) => {
+            let result = async {
+                let (mining, thread) = mining_client(&shared_state).await?;
+                // The coinbase only needs somewhere to pay, the template is never mined
+                let script_pubkey = Builder::new().push_opcode(OP_TRUE).into_script();
+                get_block_template(&mining, &thread, &script_pubkey)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get block template: {}", e))
+            }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send block template response"));
+        }
+        #[cfg(feature = "stratum")]
+        Task::StartStratum(config, response) => {
+            let result = async {
+                let (mining, thread) = mining_client(&shared_state).await?;
+                let listener = TcpListener::bind(config.listen)
+                    .await
+                    .map_err(|e| anyhow!("Failed to listen on {}: {}", config.listen, e))?;
+                let address = listener.local_addr()?;
+                println!("Stratum server listening on {}", address);
+                let server = tokio::task::spawn_local(async move {
+                    if let Err(e) = run_stratum(mining, thread, listener, config).await {
+                        eprintln!("Stratum server stopped: {}", e);
+                    }
+                });
+                // Only one server runs, a new one replaces it
+                if let Some(previous) = shared_state
+                    .stratum_server
+                    .write()
+                    .unwrap()
+                    .replace(server.abort_handle())
                 {
-                    let mut wallet_lock = shared_state.wallet_loader_client.write().unwrap();
-                    *wallet_lock = Some(wallet_client);
-                    println!("WalletLoader client stored in shared state");
+                    previous.abort();
                 }
-
-                response
-                    .send(Ok(()))
-                    .unwrap_or_else(|_| eprintln!("Failed to send success response"));
-            } else {
-                let err = anyhow!("Clients are not initialized");
-                eprintln!("Error: {}", err);
-                response
-                    .send(Err(err))
-                    .unwrap_or_else(|_| eprintln!("Failed to send error response"));
-                return Err(anyhow!("Clients are not initialized"));
-            }
-        }
-        Task::CreateNewWallet(response) => {
-            println!("Creating new wallet");
-            let wallet_loader_client = shared_state.wallet_loader_client.read().unwrap();
-            let thread_client = shared_state.thread_client.read().unwrap();
-            if let (Some(ref wallet_loader), Some(ref thread)) =
-                (&*wallet_loader_client, &*thread_client)
-            {
-                println!("WalletLoader and Thread clients available");
-                match create_new_wallet(wallet_loader, thread, "frost_byte999").await {
-                    Ok(_) => println!("New wallet created successfully"),
-                    Err(e) => eprintln!("Error creating new wallet: {}", e),
-                }
-                response
-                    .send(Ok(()))
-                    .unwrap_or_else(|_| eprintln!("Failed to send success response"));
-            } else {
-                let err = anyhow!("Clients are not initialized");
-                eprintln!("Error: {}", err);
-                response
-                    .send(Err(err))
-                    .unwrap_or_else(|_| eprintln!("Failed to send error response"));
-                return Err(anyhow!("Clients are not initialized"));
+                Ok::<_, anyhow::Error>(address)
             }
+            .await;
+            response
+                .send(result)
+                .unwrap_or_else(|_| eprintln!("Failed to send stratum response"));
         }
         _ => {
             eprintln!("Unimplemented task encountered");
@@ -1,30 +1,586 @@
+use crate::address::{read_destination, write_destination, AddressPurpose, OutputType};
+use crate::send::{write_coin_control, CoinControl, Recipient, TxProposal};
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::{Amount, Script, ScriptBuf, Transaction};
+use bitcoin_ipc::common_capnp::bilingual_str;
 use bitcoin_ipc::proxy_capnp;
 use bitcoin_ipc::wallet_capnp;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockState {
+    pub crypted: bool,
+    pub locked: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WalletList {
+    // Wallets found in the node's wallet directory
+    pub available: Vec<String>,
+    pub loaded: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Balances {
+    pub trusted: Amount,
+    pub untrusted_pending: Amount,
+    pub immature: Amount,
+    pub have_watch_only: bool,
+    pub watch_only_trusted: Amount,
+    pub watch_only_untrusted_pending: Amount,
+    pub watch_only_immature: Amount,
+}
+
+impl Balances {
+    pub fn total(&self) -> Amount {
+        self.trusted + self.untrusted_pending + self.immature
+    }
+}
+
+// CAmount values are signed, but balances are never negative
+fn amount(sats: i64) -> Amount {
+    Amount::from_sat(sats.max(0) as u64)
+}
+
+// Wallet loader calls report failure in `error` rather than as an exception,
+// and warnings about a wallet which did load, e.g. one made by a newer version
+fn loader_warnings(
+    error: bilingual_str::Reader,
+    warnings: capnp::struct_list::Reader<bilingual_str::Owned>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let error = error.get_original()?.to_string()?;
+    if !error.is_empty() {
+        return Err(error.into());
+    }
+    let mut messages = Vec::new();
+    for warning in warnings {
+        messages.push(warning.get_original()?.to_string()?);
+    }
+    Ok(messages)
+}
 
 // Manage wallets using the Wallet Loader client
 pub async fn create_new_wallet(
     wallet_loader_client: &wallet_capnp::wallet_loader::Client,
     thread_client: &proxy_capnp::thread::Client,
     name: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(wallet_capnp::wallet::Client, Vec<String>), Box<dyn std::error::Error>> {
     let mut create_wallet_request = wallet_loader_client.create_wallet_request();
     create_wallet_request
         .get()
         .get_context()?
         .set_thread(thread_client.clone());
     create_wallet_request.get().set_name(name);
-    create_wallet_request.send().promise.await?;
+    let create_wallet_response = create_wallet_request.send().promise.await?;
+    let create_wallet_response = create_wallet_response.get()?;
+    let warnings = loader_warnings(
+        create_wallet_response.get_error()?,
+        create_wallet_response.get_warning()?,
+    )?;
+    Ok((create_wallet_response.get_result()?, warnings))
+}
+
+// WalletFlags from Bitcoin Core's walletutil.h
+const WALLET_FLAG_BLANK_WALLET: u64 = 1 << 28;
+const WALLET_FLAG_DISABLE_PRIVATE_KEYS: u64 = 1 << 32;
+const WALLET_FLAG_DESCRIPTORS: u64 = 1 << 34;
+const WALLET_FLAG_EXTERNAL_SIGNER: u64 = 1 << 35;
 
+async fn create_wallet_with_flags(
+    wallet_loader_client: &wallet_capnp::wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    name: &str,
+    flags: u64,
+) -> Result<(wallet_capnp::wallet::Client, Vec<String>), Box<dyn std::error::Error>> {
+    let mut create_wallet_request = wallet_loader_client.create_wallet_request();
+    create_wallet_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    create_wallet_request.get().set_name(name);
+    create_wallet_request.get().set_flags(flags);
+    let create_wallet_response = create_wallet_request.send().promise.await?;
+    let create_wallet_response = create_wallet_response.get()?;
+    let warnings = loader_warnings(
+        create_wallet_response.get_error()?,
+        create_wallet_response.get_warning()?,
+    )?;
+    Ok((create_wallet_response.get_result()?, warnings))
+}
+
+// Create a descriptor wallet without keys, for importing watch-only descriptors
+pub async fn create_watch_only_wallet(
+    wallet_loader_client: &wallet_capnp::wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    name: &str,
+) -> Result<(wallet_capnp::wallet::Client, Vec<String>), Box<dyn std::error::Error>> {
+    let flags =
+        WALLET_FLAG_BLANK_WALLET | WALLET_FLAG_DISABLE_PRIVATE_KEYS | WALLET_FLAG_DESCRIPTORS;
+    create_wallet_with_flags(wallet_loader_client, thread_client, name, flags).await
+}
+
+// Create a keyless wallet marked as using an external signer. It is left blank,
+// so its descriptors come from our own signer rather than the node's -signer.
+pub async fn create_external_signer_wallet(
+    wallet_loader_client: &wallet_capnp::wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    name: &str,
+) -> Result<(wallet_capnp::wallet::Client, Vec<String>), Box<dyn std::error::Error>> {
+    let flags = WALLET_FLAG_BLANK_WALLET
+        | WALLET_FLAG_DISABLE_PRIVATE_KEYS
+        | WALLET_FLAG_DESCRIPTORS
+        | WALLET_FLAG_EXTERNAL_SIGNER;
+    create_wallet_with_flags(wallet_loader_client, thread_client, name, flags).await
+}
+
+pub async fn load_wallet(
+    wallet_loader_client: &wallet_capnp::wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    name: &str,
+) -> Result<(wallet_capnp::wallet::Client, Vec<String>), Box<dyn std::error::Error>> {
+    let mut load_wallet_request = wallet_loader_client.load_wallet_request();
+    load_wallet_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    load_wallet_request.get().set_name(name);
+    let load_wallet_response = load_wallet_request.send().promise.await?;
+    let load_wallet_response = load_wallet_response.get()?;
+    let warnings = loader_warnings(
+        load_wallet_response.get_error()?,
+        load_wallet_response.get_warning()?,
+    )?;
+    Ok((load_wallet_response.get_result()?, warnings))
+}
+
+// Restore a wallet from a backup file into the wallet directory under `name`
+pub async fn restore_wallet(
+    wallet_loader_client: &wallet_capnp::wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    backup_file: &Path,
+    name: &str,
+) -> Result<(wallet_capnp::wallet::Client, Vec<String>), Box<dyn std::error::Error>> {
+    let backup_file = backup_file
+        .to_str()
+        .ok_or("Backup file path is not valid UTF-8")?;
+    let mut restore_wallet_request = wallet_loader_client.restore_wallet_request();
+    restore_wallet_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    restore_wallet_request.get().set_backup_file(backup_file);
+    restore_wallet_request.get().set_wallet_name(name);
+    let restore_wallet_response = restore_wallet_request.send().promise.await?;
+    let restore_wallet_response = restore_wallet_response.get()?;
+    let warnings = loader_warnings(
+        restore_wallet_response.get_error()?,
+        restore_wallet_response.get_warning()?,
+    )?;
+    Ok((restore_wallet_response.get_result()?, warnings))
+}
+
+pub async fn list_wallet_dir(
+    wallet_loader_client: &wallet_capnp::wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     let mut list_wallet_dir_request = wallet_loader_client.list_wallet_dir_request();
     list_wallet_dir_request
         .get()
         .get_context()?
         .set_thread(thread_client.clone());
     let list_wallet_dir_response = list_wallet_dir_request.send().promise.await?;
-    println!(
-        "got a list wallet dir response: {:?}",
-        list_wallet_dir_response.get()?
-    );
+    let mut names = Vec::new();
+    for name in list_wallet_dir_response.get()?.get_result()? {
+        names.push(name?.to_string()?);
+    }
+    Ok(names)
+}
+
+// Wallets currently loaded in the node, including ones loaded by other clients
+pub async fn get_wallets(
+    wallet_loader_client: &wallet_capnp::wallet_loader::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Vec<(String, wallet_capnp::wallet::Client)>, Box<dyn std::error::Error>> {
+    let mut get_wallets_request = wallet_loader_client.get_wallets_request();
+    get_wallets_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let get_wallets_response = get_wallets_request.send().promise.await?;
+    let mut wallets = Vec::new();
+    for wallet_client in get_wallets_response.get()?.get_result()? {
+        let wallet_client = wallet_client?;
+        let name = get_wallet_name(&wallet_client, thread_client).await?;
+        wallets.push((name, wallet_client));
+    }
+    Ok(wallets)
+}
+
+// Unload a wallet from the node
+pub async fn unload_wallet(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut remove_request = wallet_client.remove_request();
+    remove_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    remove_request.send().promise.await?;
+    Ok(())
+}
+
+pub async fn get_wallet_name(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut wallet_name_request = wallet_client.get_wallet_name_request();
+    wallet_name_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let wallet_name_response = wallet_name_request.send().promise.await?;
+    Ok(wallet_name_response.get()?.get_result()?.to_string()?)
+}
+
+pub async fn get_lock_state(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<LockState, Box<dyn std::error::Error>> {
+    let mut is_crypted_request = wallet_client.is_crypted_request();
+    is_crypted_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let is_crypted_response = is_crypted_request.send().promise.await?;
+
+    let mut is_locked_request = wallet_client.is_locked_request();
+    is_locked_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let is_locked_response = is_locked_request.send().promise.await?;
+
+    Ok(LockState {
+        crypted: is_crypted_response.get()?.get_result(),
+        locked: is_locked_response.get()?.get_result(),
+    })
+}
+
+// Passphrases are copied into the request message, whose segments capnp-rpc
+// allocates itself and frees without zeroing, as it does the transport's write
+// buffers. The generated request builders take no allocator of ours, so only
+// the Zeroizing buffers on this side are wiped.
+pub async fn encrypt_wallet(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    passphrase: &Zeroizing<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut encrypt_request = wallet_client.encrypt_wallet_request();
+    encrypt_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    encrypt_request
+        .get()
+        .set_wallet_passphrase(passphrase.as_bytes());
+    let encrypt_response = encrypt_request.send().promise.await?;
+    if !encrypt_response.get()?.get_result() {
+        return Err("Wallet encryption failed".into());
+    }
+    Ok(())
+}
+
+pub async fn lock_wallet(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut lock_request = wallet_client.lock_request();
+    lock_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let lock_response = lock_request.send().promise.await?;
+    if !lock_response.get()?.get_result() {
+        return Err("Failed to lock wallet".into());
+    }
+    Ok(())
+}
 
+// The passphrase copy in the request is not wiped, see encrypt_wallet
+pub async fn unlock_wallet(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    passphrase: &Zeroizing<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut unlock_request = wallet_client.unlock_request();
+    unlock_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    unlock_request
+        .get()
+        .set_wallet_passphrase(passphrase.as_bytes());
+    let unlock_response = unlock_request.send().promise.await?;
+    if !unlock_response.get()?.get_result() {
+        return Err("The wallet passphrase entered was incorrect".into());
+    }
     Ok(())
 }
+
+// Neither passphrase copy in the request is wiped, see encrypt_wallet
+pub async fn change_wallet_passphrase(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    old_passphrase: &Zeroizing<String>,
+    new_passphrase: &Zeroizing<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut change_request = wallet_client.change_wallet_passphrase_request();
+    change_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    change_request
+        .get()
+        .set_old_wallet_passphrase(old_passphrase.as_bytes());
+    change_request
+        .get()
+        .set_new_wallet_passphrase(new_passphrase.as_bytes());
+    let change_response = change_request.send().promise.await?;
+    if !change_response.get()?.get_result() {
+        return Err("The old wallet passphrase entered was incorrect".into());
+    }
+    Ok(())
+}
+
+// How much of a wallet is watch-only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WatchOnly {
+    // Holds scripts it can watch but not spend, next to spendable ones
+    pub have_watch_only: bool,
+    // Holds no private keys at all, so everything is watch-only
+    pub private_keys_disabled: bool,
+}
+
+pub async fn get_watch_only(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<WatchOnly, Box<dyn std::error::Error>> {
+    let mut watch_only_request = wallet_client.have_watch_only_request();
+    watch_only_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let watch_only_response = watch_only_request.send().promise.await?;
+
+    let mut keys_disabled_request = wallet_client.private_keys_disabled_request();
+    keys_disabled_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let keys_disabled_response = keys_disabled_request.send().promise.await?;
+
+    Ok(WatchOnly {
+        have_watch_only: watch_only_response.get()?.get_result(),
+        private_keys_disabled: keys_disabled_response.get()?.get_result(),
+    })
+}
+
+// False for a wallet without keys or descriptors to derive new addresses from
+pub async fn can_get_addresses(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut addresses_request = wallet_client.can_get_addresses_request();
+    addresses_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let addresses_response = addresses_request.send().promise.await?;
+    Ok(addresses_response.get()?.get_result())
+}
+
+pub async fn get_balances(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Balances, Box<dyn std::error::Error>> {
+    let mut balances_request = wallet_client.get_balances_request();
+    balances_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let balances_response = balances_request.send().promise.await?;
+    let balances = balances_response.get()?.get_result()?;
+    Ok(Balances {
+        trusted: amount(balances.get_balance()),
+        untrusted_pending: amount(balances.get_unconfirmed_balance()),
+        immature: amount(balances.get_immature_balance()),
+        have_watch_only: balances.get_have_watch_only(),
+        watch_only_trusted: amount(balances.get_watch_only_balance()),
+        watch_only_untrusted_pending: amount(balances.get_unconfirmed_watch_only_balance()),
+        watch_only_immature: amount(balances.get_immature_watch_only_balance()),
+    })
+}
+
+// Derive a fresh receive address of the given type
+pub async fn get_new_destination(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    output_type: OutputType,
+    label: &str,
+) -> Result<ScriptBuf, Box<dyn std::error::Error>> {
+    let mut new_destination_request = wallet_client.get_new_destination_request();
+    new_destination_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    new_destination_request
+        .get()
+        .set_output_type(output_type as i32);
+    new_destination_request.get().set_label(label);
+    let new_destination_response = new_destination_request.send().promise.await?;
+    read_destination(new_destination_response.get()?.get_result()?)
+}
+
+pub async fn set_address_book(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    script: &Script,
+    label: &str,
+    purpose: AddressPurpose,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut address_book_request = wallet_client.set_address_book_request();
+    address_book_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    write_destination(address_book_request.get().init_dest(), script)?;
+    address_book_request.get().set_name(label);
+    address_book_request.get().set_purpose(purpose as i32);
+    address_book_request.get().set_has_purpose(true);
+    let address_book_response = address_book_request.send().promise.await?;
+    if !address_book_response.get()?.get_result() {
+        return Err("Failed to update address book".into());
+    }
+    Ok(())
+}
+
+// Store an opaque payment request blob against an address. An empty value erases it.
+pub async fn set_address_receive_request(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    script: &Script,
+    id: &str,
+    value: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut receive_request = wallet_client.set_address_receive_request_request();
+    receive_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    write_destination(receive_request.get().init_dest(), script)?;
+    receive_request.get().set_id(id);
+    receive_request.get().set_value(value);
+    let receive_response = receive_request.send().promise.await?;
+    if !receive_response.get()?.get_result() {
+        return Err("Failed to store payment request".into());
+    }
+    Ok(())
+}
+
+pub async fn get_address_receive_requests(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut receive_requests_request = wallet_client.get_address_receive_requests_request();
+    receive_requests_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    let receive_requests_response = receive_requests_request.send().promise.await?;
+    let mut values = Vec::new();
+    for value in receive_requests_response.get()?.get_result()? {
+        values.push(value?.to_string()?);
+    }
+    Ok(values)
+}
+
+// Build (and optionally sign) a transaction without broadcasting it
+pub async fn create_transaction(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    recipients: &[Recipient],
+    coin_control: &CoinControl,
+    sign: bool,
+) -> Result<TxProposal, Box<dyn std::error::Error>> {
+    let mut create_request = wallet_client.create_transaction_request();
+    create_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    {
+        let mut list = create_request
+            .get()
+            .init_recipients(recipients.len() as u32);
+        for (i, recipient) in recipients.iter().enumerate() {
+            let mut builder = list.reborrow().get(i as u32);
+            write_destination(
+                builder.reborrow().init_dest(),
+                &recipient.address.script_pubkey(),
+            )?;
+            builder.set_amount(recipient.amount.to_sat() as i64);
+            builder.set_subtract_fee_from_amount(recipient.subtract_fee);
+        }
+    }
+    write_coin_control(create_request.get().init_coin_control(), coin_control);
+    create_request.get().set_sign(sign);
+    // Let the wallet pick a random change position
+    create_request.get().set_change_pos(-1);
+    let create_response = create_request.send().promise.await?;
+    let create_response = create_response.get()?;
+    Ok(TxProposal {
+        tx: deserialize(create_response.get_result()?)?,
+        fee: amount(create_response.get_fee()),
+        change_pos: usize::try_from(create_response.get_change_pos()).ok(),
+    })
+}
+
+// Add a signed transaction to the wallet and broadcast it
+pub async fn commit_transaction(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    tx: &Transaction,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut commit_request = wallet_client.commit_transaction_request();
+    commit_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    commit_request.get().set_tx(&serialize(tx));
+    commit_request.get().init_value_map(0);
+    commit_request.get().init_order_form(0);
+    commit_request.send().promise.await?;
+    Ok(())
+}
+
+// Address book label for a script, if the wallet has an entry for it
+pub async fn get_address_label(
+    wallet_client: &wallet_capnp::wallet::Client,
+    thread_client: &proxy_capnp::thread::Client,
+    script: &Script,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut get_address_request = wallet_client.get_address_request();
+    get_address_request
+        .get()
+        .get_context()?
+        .set_thread(thread_client.clone());
+    write_destination(get_address_request.get().init_dest(), script)?;
+    get_address_request.get().set_want_name(true);
+    let get_address_response = get_address_request.send().promise.await?;
+    let get_address_response = get_address_response.get()?;
+    if !get_address_response.get_result() {
+        return Ok(None);
+    }
+    Ok(Some(get_address_response.get_name()?.to_string()?))
+}